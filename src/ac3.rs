@@ -0,0 +1,233 @@
+// AC-3 sync frames, see ATSC A/52
+//
+// Each sync frame starts with the 0x0b77 sync word, followed by crc1, the
+// sample rate and frame size codes, then the bit stream information: bsid,
+// bsmod, the audio coding mode (acmod) and whether the LFE channel is on.
+// A frame holds 1536 samples per channel.
+
+use std::collections::VecDeque;
+
+/// Samples per channel in an AC-3 sync frame.
+pub const AC3_SAMPLES_PER_FRAME: usize = 1536;
+
+const SYNC_WORD: [u8; 2] = [0x0b, 0x77];
+
+/// Bitrates in kbit/s, by `frmsizecod / 2`
+const BITRATES: [u32; 19] = [
+    32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640,
+];
+
+/// Sample rates by `fscod`, 3 is reserved
+const SAMPLE_RATES: [u32; 3] = [48000, 44100, 32000];
+
+/// Largest `bsid` of the streams an AC-3 decoder can play, E-AC-3 uses 16
+const MAX_BSID: u8 = 8;
+
+/// AC-3 sync frame header and bit stream information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ac3Header {
+    pub sample_rate: u32,
+    /// Bits per second
+    pub bitrate: u32,
+    /// Size of the sync frame in bytes
+    pub frame_size: usize,
+    /// Bit stream identification, 8 for the standard syntax, 6 for the alternate one
+    pub bsid: u8,
+    /// Bit stream mode: main audio, commentary, karaoke...
+    pub bsmod: u8,
+    /// Audio coding mode, the arrangement of the full bandwidth channels
+    pub acmod: u8,
+    /// Low frequency effects channel
+    pub lfe: bool,
+}
+
+impl Ac3Header {
+    /// Parses the first bytes of a sync frame, `None` if they are not a valid AC-3 header
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 8 || bytes[..2] != SYNC_WORD {
+            return None;
+        }
+
+        let fscod = usize::from(bytes[4] >> 6);
+        let frmsizecod = usize::from(bytes[4] & 0x3f);
+        let bsid = bytes[5] >> 3;
+        if fscod == 3 || frmsizecod >= 2 * BITRATES.len() || bsid > MAX_BSID {
+            return None;
+        }
+        let sample_rate = SAMPLE_RATES[fscod];
+        let kbps = BITRATES[frmsizecod / 2];
+
+        // Frames last 1536 samples, 44.1 kHz frames of odd codes hold one more 16-bit word
+        let words = kbps * 1000 * AC3_SAMPLES_PER_FRAME as u32 / (16 * sample_rate);
+        let padding = u32::from(sample_rate == 44100 && frmsizecod % 2 == 1);
+
+        // The mixing levels and surround mode before `lfeon` depend on `acmod`
+        let bsi = u16::from_be_bytes([bytes[6], bytes[7]]);
+        let acmod = (bsi >> 13) as u8;
+        let mut lfe_bit = 12;
+        if acmod & 0x1 != 0 && acmod != 1 {
+            lfe_bit -= 2;
+        }
+        if acmod & 0x4 != 0 {
+            lfe_bit -= 2;
+        }
+        if acmod == 2 {
+            lfe_bit -= 2;
+        }
+
+        Some(Self {
+            sample_rate,
+            bitrate: kbps * 1000,
+            frame_size: 2 * (words + padding) as usize,
+            bsid,
+            bsmod: bytes[5] & 0x07,
+            acmod,
+            lfe: (bsi >> lfe_bit) & 1 != 0,
+        })
+    }
+
+    /// Number of full bandwidth channels
+    pub fn full_bandwidth_channels(&self) -> usize {
+        [2, 1, 2, 3, 3, 4, 4, 5][usize::from(self.acmod)]
+    }
+
+    /// Number of channels, LFE included
+    pub fn channels(&self) -> usize {
+        self.full_bandwidth_channels() + usize::from(self.lfe)
+    }
+
+    /// Channel layout as front/rear channels, such as "3/2+LFE" for 5.1
+    pub fn channel_layout(&self) -> String {
+        let layout =
+            ["1+1", "1/0", "2/0", "3/0", "2/1", "3/1", "2/2", "3/2"][usize::from(self.acmod)];
+
+        if self.lfe {
+            format!("{}+LFE", layout)
+        } else {
+            layout.to_string()
+        }
+    }
+}
+
+/// CRC-16 of AC-3 sync frames, `x^16 + x^15 + x^2 + 1`
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for byte in data {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Validated sync frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ac3Frame {
+    pub header: Ac3Header,
+    /// Presentation time stamp of the PES packet the frame starts in, in ticks of the 90 kHz clock
+    pub pts: Option<u64>,
+    /// The whole sync frame, sync word included
+    pub data: Vec<u8>,
+}
+
+/// Splits an AC-3 elementary stream pushed in pieces into validated sync frames.
+///
+/// A header is trusted if the next sync word, when already pushed, follows the frame. Trusted
+/// frames are then checked against their CRC and dropped if it does not match.
+#[derive(Default)]
+pub struct Ac3Parser {
+    buffer: Vec<u8>,
+    /// Time stamps pushed with the data, with the position in `buffer` of their first byte
+    timestamps: VecDeque<(usize, u64)>,
+    /// Bytes skipped while looking for a sync word
+    skipped: usize,
+    /// Frames dropped because of a CRC mismatch
+    corrupted: usize,
+}
+
+impl Ac3Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends stream bytes, `pts` applying to the first frame that starts in them
+    pub fn push(&mut self, data: &[u8], pts: Option<u64>) {
+        if let Some(pts) = pts {
+            self.timestamps.push_back((self.buffer.len(), pts));
+        }
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Bytes skipped so far while looking for sync words
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Frames dropped so far because of a CRC mismatch
+    pub fn corrupted(&self) -> usize {
+        self.corrupted
+    }
+
+    /// Returns the next valid sync frame pushed, `None` until more data is pushed
+    pub fn next_frame(&mut self) -> Option<Ac3Frame> {
+        let mut start = 0;
+        loop {
+            let Some(bytes) = self.buffer.get(start..start + 8) else {
+                self.consume(start, 0);
+                return None;
+            };
+
+            if let Some(header) = Ac3Header::parse(bytes) {
+                let end = start + header.frame_size;
+                if end > self.buffer.len() {
+                    // The end of the frame has not been pushed yet
+                    self.consume(start, 0);
+                    return None;
+                }
+
+                let next = self.buffer.get(end..end + 2);
+                if next.is_none() || next == Some(&SYNC_WORD[..]) {
+                    // Time stamp of the last PES packet starting before the frame
+                    let mut pts = None;
+                    while let Some((position, timestamp)) = self.timestamps.front() {
+                        if *position > start {
+                            break;
+                        }
+                        pts = Some(*timestamp);
+                        self.timestamps.pop_front();
+                    }
+                    let valid = crc16(&self.buffer[start + 2..end]) == 0;
+                    let frame = Ac3Frame {
+                        header,
+                        pts,
+                        data: self.buffer[start..end].to_vec(),
+                    };
+                    self.consume(start, header.frame_size);
+
+                    if valid {
+                        return Some(frame);
+                    }
+                    self.corrupted += 1;
+                    start = 0;
+                    continue;
+                }
+            }
+            start += 1;
+        }
+    }
+
+    /// Drops `skipped` bytes of garbage, then `frame` bytes of a sync frame
+    fn consume(&mut self, skipped: usize, frame: usize) {
+        let count = skipped + frame;
+        self.buffer.drain(..count);
+        self.skipped += skipped;
+        for (position, _) in self.timestamps.iter_mut() {
+            *position = position.saturating_sub(count);
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// Active Format Description and bar data, see ETSI TS 101 154 and ATSC A/53
+//
+// AFD user data: "DTG1", then a byte whose bit 6 is active_format_flag, then
+// when the flag is set a byte ending with the 4-bit active_format.
+//
+// Bar data user data: "GA94", user_data_type_code 0x06, a byte of
+// top/bottom/left/right flags in its high bits, then for every flag set a
+// 16-bit word of two marker bits and a 14-bit line or pixel number.
+
+use std::{fmt, str::FromStr};
+
+use crate::yuv::YuvFrame;
+
+/// Shape of the active image inside the coded frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ActiveFormat(pub u8);
+
+impl ActiveFormat {
+    /// Active format of AFD user data, `None` for other user data or without `active_format_flag`
+    pub fn parse(user_data: &[u8]) -> Option<Self> {
+        let data = user_data.strip_prefix(b"DTG1")?;
+        if data.first()? & 0x40 == 0 {
+            return None;
+        }
+
+        Some(Self(data.get(1)? & 0x0f))
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self.0 {
+            2 => "16:9 box, top",
+            3 => "14:9 box, top",
+            4 => "box wider than 16:9, centre",
+            8 => "same as the coded frame",
+            9 => "4:3, centre",
+            10 => "16:9, centre",
+            11 => "14:9, centre",
+            13 => "4:3, protect 14:9",
+            14 => "16:9, protect 14:9",
+            15 => "16:9, protect 4:3",
+            _ => "reserved",
+        }
+    }
+
+    /// Display aspect ratio of the active image, `None` when it is the coded one or unknown
+    fn aspect(&self) -> Option<f64> {
+        match self.0 {
+            9 | 13 => Some(4.0 / 3.0),
+            2 | 10 | 14 | 15 => Some(16.0 / 9.0),
+            3 | 11 => Some(14.0 / 9.0),
+            _ => None,
+        }
+    }
+
+    /// Area of the active image in a `width`×`height` frame shown at `display_aspect`
+    ///
+    /// Images wider than the frame are letterboxed, at the top for the box formats, narrower
+    /// ones are pillarboxed. `None` for reserved codes and boxes wider than 16:9, whose size
+    /// only bar data gives.
+    pub fn active_area(
+        &self,
+        width: usize,
+        height: usize,
+        display_aspect: f64,
+    ) -> Option<ActiveArea> {
+        if self.0 == 8 {
+            return Some(ActiveArea::full(width, height));
+        }
+        let aspect = self.aspect()?;
+
+        let area = if aspect > display_aspect {
+            let active_height = (height as f64 * display_aspect / aspect).round() as usize;
+            let y = match self.0 {
+                2 | 3 => 0,
+                _ => (height - active_height) / 2,
+            };
+            ActiveArea {
+                x: 0,
+                y,
+                width,
+                height: active_height,
+            }
+        } else {
+            let active_width = (width as f64 * aspect / display_aspect).round() as usize;
+            ActiveArea {
+                x: (width - active_width) / 2,
+                y: 0,
+                width: active_width,
+                height,
+            }
+        };
+
+        Some(area)
+    }
+}
+
+impl fmt::Display for ActiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04b} ({})", self.0, self.description())
+    }
+}
+
+/// Black bars around the active image, as line and pixel numbers of the coded frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BarData {
+    /// Last line of the top bar
+    pub top: Option<u16>,
+    /// First line of the bottom bar
+    pub bottom: Option<u16>,
+    /// Last pixel of the left bar
+    pub left: Option<u16>,
+    /// First pixel of the right bar
+    pub right: Option<u16>,
+}
+
+impl BarData {
+    /// Bar data of A/53 user data, `None` for other user data
+    pub fn parse(user_data: &[u8]) -> Option<Self> {
+        let data = user_data.strip_prefix(b"GA94\x06")?;
+        let flags = *data.first()?;
+
+        let mut words = data[1..]
+            .chunks_exact(2)
+            .map(|word| u16::from_be_bytes([word[0], word[1]]) & 0x3fff);
+        let mut bar = |bit: u8| match flags & bit {
+            0 => Some(None),
+            _ => words.next().map(Some),
+        };
+
+        Some(Self {
+            top: bar(0x80)?,
+            bottom: bar(0x40)?,
+            left: bar(0x20)?,
+            right: bar(0x10)?,
+        })
+    }
+
+    /// Area between the bars in a `width`×`height` frame, `None` if it is empty
+    pub fn active_area(&self, width: usize, height: usize) -> Option<ActiveArea> {
+        let top = self.top.map_or(0, |line| usize::from(line) + 1);
+        let bottom = self.bottom.map_or(height, usize::from).min(height);
+        let left = self.left.map_or(0, |pixel| usize::from(pixel) + 1);
+        let right = self.right.map_or(width, usize::from).min(width);
+        if top >= bottom || left >= right {
+            return None;
+        }
+
+        Some(ActiveArea {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        })
+    }
+}
+
+impl fmt::Display for BarData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bars: Vec<String> = [
+            ("top", self.top),
+            ("bottom", self.bottom),
+            ("left", self.left),
+            ("right", self.right),
+        ]
+        .iter()
+        .filter_map(|(name, value)| Some(format!("{} {}", name, (*value)?)))
+        .collect();
+
+        if bars.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", bars.join(", "))
+        }
+    }
+}
+
+/// Rectangle of a frame holding the active image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveArea {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl ActiveArea {
+    pub fn full(width: usize, height: usize) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+/// What the viewer does with the area outside the active image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AfdMode {
+    /// Show the coded frame as is
+    #[default]
+    Off,
+    /// Show the active image alone
+    Crop,
+    /// Show the coded frame with black outside the active image
+    Letterbox,
+}
+
+impl AfdMode {
+    pub const ALL: [AfdMode; 3] = [AfdMode::Off, AfdMode::Crop, AfdMode::Letterbox];
+
+    /// Frame to show for `frame` whose active image covers `area`
+    pub fn apply(&self, frame: &YuvFrame, area: ActiveArea) -> YuvFrame {
+        match self {
+            AfdMode::Off => frame.clone(),
+            AfdMode::Crop => frame.crop(area.x, area.y, area.width, area.height),
+            AfdMode::Letterbox => {
+                let mut out = YuvFrame::new(frame.width(), frame.height(), frame.chroma_format);
+                let chroma = frame.chroma_format.subsampling();
+                let shifts = [(0, 0), chroma, chroma];
+
+                let planes = frame.planes().into_iter().zip(out.planes_mut());
+                for ((src, dst), (h_shift, v_shift)) in planes.zip(shifts) {
+                    // Chroma samples are kept if their top left luma sample is active
+                    for y in 0..dst.height() {
+                        for x in 0..dst.width() {
+                            if area.contains(x << h_shift, y << v_shift) {
+                                dst.set(x, y, src.get(x, y));
+                            }
+                        }
+                    }
+                }
+
+                out
+            }
+        }
+    }
+}
+
+impl fmt::Display for AfdMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AfdMode::Off => "off",
+            AfdMode::Crop => "crop",
+            AfdMode::Letterbox => "letterbox",
+        })
+    }
+}
+
+impl FromStr for AfdMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(AfdMode::Off),
+            "crop" => Ok(AfdMode::Crop),
+            "letterbox" => Ok(AfdMode::Letterbox),
+            _ => Err(format!(
+                "unknown AFD mode `{}`, expected off, crop or letterbox",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,700 @@
+// Stream report built from the raw entries of tvid.log
+//
+// Groups of pictures are delimited by GOP lines when the log has them, by SEQ lines otherwise.
+// Picture sizes are the offset deltas between consecutive PIC lines in decoding order, so they
+// include the sequence and GOP headers preceding a picture.
+
+use std::{
+    fmt::{self, Write},
+    time::Duration,
+};
+
+use crate::{
+    events::{events_json, Event},
+    flag::CodingType,
+    metadata_parser::{LogEntry, PictureEntry},
+    timing::{bitrate, displayed_fields, fields_duration, FrameRate},
+};
+
+/// Number of display-order pictures shown in the cadence pattern.
+const PATTERN_LENGTH: usize = 8;
+
+/// A picture of the stream, with what can be derived from its neighbours.
+#[derive(Debug, Clone)]
+pub struct PictureReport {
+    pub entry: PictureEntry,
+    pub decode_index: usize,
+    pub display_index: usize,
+    /// Frame period of the enclosing sequence, `None` for pictures before any SEQ line
+    pub frame_period: Option<usize>,
+    pub progressive_sequence: bool,
+    /// Coded size in bytes, unknown for the last picture
+    pub size: Option<usize>,
+}
+
+impl PictureReport {
+    /// Number of fields the picture is displayed for, as `Picture::displayed_fields`
+    pub fn displayed_fields(&self) -> u64 {
+        displayed_fields(
+            self.progressive_sequence,
+            self.entry.repeat_first_field,
+            self.entry.top_field_first,
+        )
+    }
+
+    /// Display duration, taking field and frame repetition into account
+    pub fn duration(&self) -> Option<Duration> {
+        Some(fields_duration(
+            self.frame_period? as u64,
+            self.displayed_fields(),
+        ))
+    }
+
+    /// Bits per second needed to send the picture in one frame period, as `Picture::bitrate`
+    pub fn bitrate(&self) -> Option<f64> {
+        Some(bitrate(self.size?, self.frame_period? as u64))
+    }
+
+    /// `T` or `B` for the first field, then the number of displayed fields
+    fn cadence_token(&self) -> String {
+        let first = if self.entry.top_field_first { 'T' } else { 'B' };
+        format!("{}{}", first, self.displayed_fields())
+    }
+}
+
+/// A group of pictures, in decoding order.
+#[derive(Debug, Clone)]
+pub struct GopReport {
+    /// Line of the GOP or SEQ entry starting the group, 0 if the log starts with pictures
+    pub line: usize,
+    /// Only known for groups delimited by GOP lines
+    pub closed: Option<bool>,
+    /// Range of the group in `StreamReport::pictures`
+    pub pictures: std::ops::Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// No picture of the group has this temporal reference
+    MissingTemporalReference(usize),
+    /// Several pictures of the group have this temporal reference
+    DuplicateTemporalReference(usize),
+    /// The picture offset is not after the previous one
+    OffsetNotIncreasing,
+    /// The first field of the picture has the same parity as the last field of the previous one
+    FieldOrderBreak,
+    PictureBeforeSequence,
+    /// The first picture of the group is not intra coded
+    GopWithoutIntra,
+}
+
+impl AnomalyKind {
+    fn name(&self) -> &'static str {
+        match self {
+            AnomalyKind::MissingTemporalReference(_) => "missing_temporal_reference",
+            AnomalyKind::DuplicateTemporalReference(_) => "duplicate_temporal_reference",
+            AnomalyKind::OffsetNotIncreasing => "offset_not_increasing",
+            AnomalyKind::FieldOrderBreak => "field_order_break",
+            AnomalyKind::PictureBeforeSequence => "picture_before_sequence",
+            AnomalyKind::GopWithoutIntra => "gop_without_intra",
+        }
+    }
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnomalyKind::MissingTemporalReference(temporal_reference) => write!(
+                f,
+                "temporal_reference {} missing from the group",
+                temporal_reference
+            ),
+            AnomalyKind::DuplicateTemporalReference(temporal_reference) => write!(
+                f,
+                "temporal_reference {} used more than once in the group",
+                temporal_reference
+            ),
+            AnomalyKind::OffsetNotIncreasing => {
+                write!(f, "picture offset is not after the previous one")
+            }
+            AnomalyKind::FieldOrderBreak => {
+                write!(f, "first field has the parity of the previous last field")
+            }
+            AnomalyKind::PictureBeforeSequence => write!(f, "PIC line before any SEQ"),
+            AnomalyKind::GopWithoutIntra => write!(f, "group does not start with an I picture"),
+        }
+    }
+}
+
+/// Something unexpected in the log, `line` is 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Anomaly {
+    pub line: usize,
+    pub kind: AnomalyKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CadenceKind {
+    Progressive,
+    TopFieldFirst,
+    BottomFieldFirst,
+    /// Alternating repeated fields, 24 frames per second shown as 60 fields
+    Pulldown32,
+    Mixed,
+}
+
+impl fmt::Display for CadenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CadenceKind::Progressive => "progressive",
+            CadenceKind::TopFieldFirst => "interlaced, top field first",
+            CadenceKind::BottomFieldFirst => "interlaced, bottom field first",
+            CadenceKind::Pulldown32 => "3:2 pulldown",
+            CadenceKind::Mixed => "mixed",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Field repetition and ordering of the pictures, in display order.
+#[derive(Debug, Clone)]
+pub struct Cadence {
+    pub kind: CadenceKind,
+    /// First pictures as first field and number of fields, e.g. `T3 B2 B3 T2`
+    pub pattern: String,
+    pub progressive_frames: usize,
+    pub top_field_first: usize,
+    pub repeat_first_field: usize,
+    pub field_order_breaks: usize,
+}
+
+/// Number of pictures of each coding type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeCounts {
+    pub i: usize,
+    pub p: usize,
+    pub b: usize,
+    pub d: usize,
+    /// Pictures of old logs, which do not record the coding type
+    pub unknown: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamReport {
+    pub sequences: usize,
+    /// Pictures in decoding order
+    pub pictures: Vec<PictureReport>,
+    pub gops: Vec<GopReport>,
+    pub types: TypeCounts,
+    pub cadence: Cadence,
+    pub anomalies: Vec<Anomaly>,
+    /// Black frames, freezes and scene cuts of the decoded frames, if they were looked for
+    pub events: Option<Vec<Event>>,
+}
+
+/// Builds the report of a whole log
+pub fn analyze(entries: &[LogEntry]) -> StreamReport {
+    let has_gop_lines = entries.iter().any(|e| matches!(e, LogEntry::Gop(_)));
+
+    let mut sequence = None;
+    let mut sequences = 0;
+    let mut pictures: Vec<PictureReport> = Vec::new();
+    let mut gops: Vec<GopReport> = Vec::new();
+    let mut anomalies = Vec::new();
+
+    for entry in entries {
+        let boundary = match entry {
+            LogEntry::Sequence(entry) => {
+                sequences += 1;
+                sequence = Some(entry);
+                (!has_gop_lines).then_some((entry.line, None))
+            }
+            LogEntry::Gop(entry) => Some((entry.line, Some(entry.closed))),
+            LogEntry::Picture(entry) => {
+                if sequence.is_none() {
+                    anomalies.push(Anomaly {
+                        line: entry.line,
+                        kind: AnomalyKind::PictureBeforeSequence,
+                    });
+                }
+                if gops.is_empty() {
+                    gops.push(GopReport {
+                        line: 0,
+                        closed: None,
+                        pictures: 0..0,
+                    });
+                }
+
+                if let Some(previous) = pictures.last_mut() {
+                    if entry.offset > previous.entry.offset {
+                        previous.size = Some(entry.offset - previous.entry.offset);
+                    } else {
+                        anomalies.push(Anomaly {
+                            line: entry.line,
+                            kind: AnomalyKind::OffsetNotIncreasing,
+                        });
+                    }
+                }
+
+                pictures.push(PictureReport {
+                    entry: entry.clone(),
+                    decode_index: pictures.len(),
+                    display_index: 0,
+                    frame_period: sequence.map(|sequence| sequence.frame_period),
+                    progressive_sequence: sequence.is_some_and(|s| s.progressive_sequence),
+                    size: None,
+                });
+                gops.last_mut().unwrap().pictures.end = pictures.len();
+                None
+            }
+            LogEntry::UserData(_) => None,
+        };
+
+        // Empty groups, e.g. a SEQ line repeated before the first picture, are replaced
+        if let Some((line, closed)) = boundary {
+            if gops.last().is_some_and(|gop| gop.pictures.is_empty()) {
+                gops.pop();
+            }
+            gops.push(GopReport {
+                line,
+                closed,
+                pictures: pictures.len()..pictures.len(),
+            });
+        }
+    }
+    gops.retain(|gop| !gop.pictures.is_empty());
+
+    for gop in &gops {
+        check_gop(&mut pictures[gop.pictures.clone()], &mut anomalies);
+    }
+
+    let mut types = TypeCounts::default();
+    for picture in &pictures {
+        match picture.entry.coding_type {
+            Some(CodingType::I) => types.i += 1,
+            Some(CodingType::P) => types.p += 1,
+            Some(CodingType::B) => types.b += 1,
+            Some(CodingType::D) => types.d += 1,
+            None => types.unknown += 1,
+        }
+    }
+
+    let cadence = cadence(&pictures, &mut anomalies);
+    anomalies.sort_by_key(|anomaly| anomaly.line);
+
+    StreamReport {
+        sequences,
+        pictures,
+        gops,
+        types,
+        cadence,
+        anomalies,
+        events: None,
+    }
+}
+
+/// Sets the display index of the pictures of a group and checks their temporal references
+fn check_gop(pictures: &mut [PictureReport], anomalies: &mut Vec<Anomaly>) {
+    let Some(first) = pictures.first() else {
+        return;
+    };
+    let first_line = first.entry.line;
+    let start = first.decode_index;
+
+    if first
+        .entry
+        .coding_type
+        .is_some_and(|coding_type| coding_type != CodingType::I)
+    {
+        anomalies.push(Anomaly {
+            line: first_line,
+            kind: AnomalyKind::GopWithoutIntra,
+        });
+    }
+
+    let mut order: Vec<usize> = (0..pictures.len()).collect();
+    order.sort_by_key(|i| pictures[*i].entry.temporal_reference);
+    for (display, i) in order.iter().enumerate() {
+        pictures[*i].display_index = start + display;
+    }
+
+    let max = order
+        .last()
+        .map_or(0, |i| pictures[*i].entry.temporal_reference);
+    let mut seen = vec![false; max + 1];
+    for picture in pictures.iter() {
+        let temporal_reference = picture.entry.temporal_reference;
+        if seen[temporal_reference] {
+            anomalies.push(Anomaly {
+                line: picture.entry.line,
+                kind: AnomalyKind::DuplicateTemporalReference(temporal_reference),
+            });
+        }
+        seen[temporal_reference] = true;
+    }
+    for (temporal_reference, seen) in seen.iter().enumerate() {
+        if !seen {
+            anomalies.push(Anomaly {
+                line: first_line,
+                kind: AnomalyKind::MissingTemporalReference(temporal_reference),
+            });
+        }
+    }
+}
+
+fn cadence(pictures: &[PictureReport], anomalies: &mut Vec<Anomaly>) -> Cadence {
+    let mut display: Vec<&PictureReport> = pictures.iter().collect();
+    display.sort_by_key(|picture| picture.display_index);
+
+    let count = |f: fn(&PictureEntry) -> bool| display.iter().filter(|p| f(&p.entry)).count();
+    let progressive_frames = count(|entry| entry.progressive_frame);
+    let top_field_first = count(|entry| entry.top_field_first);
+    let repeat_first_field = count(|entry| entry.repeat_first_field);
+
+    // Fields alternate parity, so a picture ends on its first parity after an odd field count
+    let mut field_order_breaks = 0;
+    for pair in display.windows(2) {
+        let (previous, current) = (&pair[0].entry, &pair[1].entry);
+        if pair[1].progressive_sequence {
+            continue;
+        }
+
+        let last_is_top = previous.top_field_first == previous.repeat_first_field;
+        if current.top_field_first == last_is_top {
+            field_order_breaks += 1;
+            anomalies.push(Anomaly {
+                line: current.line,
+                kind: AnomalyKind::FieldOrderBreak,
+            });
+        }
+    }
+
+    let total = display.len();
+    let kind = if repeat_first_field == 0 && progressive_frames == total {
+        CadenceKind::Progressive
+    } else if repeat_first_field == 0 && top_field_first == total {
+        CadenceKind::TopFieldFirst
+    } else if repeat_first_field == 0 && top_field_first == 0 {
+        CadenceKind::BottomFieldFirst
+    } else if field_order_breaks == 0
+        && display
+            .windows(2)
+            .all(|pair| pair[0].entry.repeat_first_field != pair[1].entry.repeat_first_field)
+    {
+        CadenceKind::Pulldown32
+    } else {
+        CadenceKind::Mixed
+    };
+
+    let pattern = display
+        .iter()
+        .take(PATTERN_LENGTH)
+        .map(|picture| picture.cadence_token())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Cadence {
+        kind,
+        pattern,
+        progressive_frames,
+        top_field_first,
+        repeat_first_field,
+        field_order_breaks,
+    }
+}
+
+/// Minimum, maximum and mean of a list of values
+fn stats(values: impl Iterator<Item = f64>) -> Option<(f64, f64, f64)> {
+    let mut count = 0;
+    let (mut min, mut max, mut sum) = (f64::INFINITY, f64::NEG_INFINITY, 0.0);
+    for value in values {
+        count += 1;
+        min = min.min(value);
+        max = max.max(value);
+        sum += value;
+    }
+
+    (count > 0).then(|| (min, max, sum / count as f64))
+}
+
+impl StreamReport {
+    /// Coding types of a group in decoding order, e.g. `IBBPBBP`, `?` for unknown types
+    pub fn gop_structure(&self, gop: &GopReport) -> String {
+        self.pictures[gop.pictures.clone()]
+            .iter()
+            .map(|picture| match picture.entry.coding_type {
+                Some(coding_type) => format!("{:?}", coding_type),
+                None => "?".to_string(),
+            })
+            .collect()
+    }
+
+    fn gop_length_stats(&self) -> Option<(f64, f64, f64)> {
+        stats(self.gops.iter().map(|gop| gop.pictures.len() as f64))
+    }
+
+    /// Mean bitrate over the pictures of known size, in bits per second, each sent in one
+    /// frame period as in `PictureReport::bitrate`
+    pub fn mean_bitrate(&self) -> Option<f64> {
+        let (size, ticks) = self
+            .pictures
+            .iter()
+            .filter_map(|picture| Some((picture.size?, picture.frame_period? as u64)))
+            .fold((0, 0), |(size, ticks), (s, t)| (size + s, ticks + t));
+
+        (ticks > 0).then(|| bitrate(size, ticks))
+    }
+
+    /// Mean size in bytes of the pictures of a coding type
+    fn mean_size(&self, coding_type: Option<CodingType>) -> Option<f64> {
+        stats(
+            self.pictures
+                .iter()
+                .filter(|picture| picture.entry.coding_type == coding_type)
+                .filter_map(|picture| picture.size)
+                .map(|size| size as f64),
+        )
+        .map(|(_, _, mean)| mean)
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let number = |value: Option<f64>| match value {
+            Some(value) if value.is_finite() => format!("{:.3}", value),
+            _ => "null".to_string(),
+        };
+        let integer = |value: Option<usize>| value.map_or("null".to_string(), |v| v.to_string());
+
+        json.push_str("{\n");
+        writeln!(json, "  \"sequences\": {},", self.sequences).unwrap();
+        writeln!(json, "  \"pictures\": {},", self.pictures.len()).unwrap();
+        let frame_period = self.pictures.first().and_then(|p| p.frame_period);
+        writeln!(json, "  \"frame_period\": {},", integer(frame_period)).unwrap();
+        let frame_rate = frame_period.map_or("null".to_string(), |period| {
+            format!("\"{}\"", FrameRate::from_frame_period(period as u64))
+        });
+        writeln!(json, "  \"frame_rate\": {},", frame_rate).unwrap();
+        writeln!(
+            json,
+            "  \"types\": {{\"I\": {}, \"P\": {}, \"B\": {}, \"D\": {}, \"unknown\": {}}},",
+            self.types.i, self.types.p, self.types.b, self.types.d, self.types.unknown
+        )
+        .unwrap();
+        writeln!(json, "  \"mean_bitrate\": {},", number(self.mean_bitrate())).unwrap();
+
+        let (min, max, mean) = match self.gop_length_stats() {
+            Some((min, max, mean)) => (Some(min), Some(max), Some(mean)),
+            None => (None, None, None),
+        };
+        writeln!(
+            json,
+            "  \"gop_length\": {{\"min\": {}, \"max\": {}, \"mean\": {}}},",
+            number(min),
+            number(max),
+            number(mean)
+        )
+        .unwrap();
+
+        json.push_str("  \"gops\": [");
+        for (i, gop) in self.gops.iter().enumerate() {
+            let closed = gop.closed.map_or("null".to_string(), |c| c.to_string());
+            write!(
+                json,
+                "{}\n    {{\"line\": {}, \"pictures\": {}, \"closed\": {}, \"structure\": \"{}\"}}",
+                if i == 0 { "" } else { "," },
+                gop.line,
+                gop.pictures.len(),
+                closed,
+                self.gop_structure(gop)
+            )
+            .unwrap();
+        }
+        json.push_str("\n  ],\n");
+
+        let cadence = &self.cadence;
+        writeln!(
+            json,
+            "  \"cadence\": {{\"kind\": \"{}\", \"pattern\": \"{}\", \"progressive_frames\": {}, \
+             \"top_field_first\": {}, \"repeat_first_field\": {}, \"field_order_breaks\": {}}},",
+            cadence.kind,
+            cadence.pattern,
+            cadence.progressive_frames,
+            cadence.top_field_first,
+            cadence.repeat_first_field,
+            cadence.field_order_breaks
+        )
+        .unwrap();
+
+        json.push_str("  \"frames\": [");
+        for (i, picture) in self.pictures.iter().enumerate() {
+            let entry = &picture.entry;
+            let coding_type = entry
+                .coding_type
+                .map_or("null".to_string(), |c| format!("\"{:?}\"", c));
+            write!(
+                json,
+                "{}\n    {{\"line\": {}, \"decode_index\": {}, \"display_index\": {}, \
+                 \"offset\": {}, \"temporal_reference\": {}, \"type\": {}, \"bytes\": {}, \
+                 \"bitrate\": {}, \"progressive_frame\": {}, \"top_field_first\": {}, \
+                 \"repeat_first_field\": {}}}",
+                if i == 0 { "" } else { "," },
+                entry.line,
+                picture.decode_index,
+                picture.display_index,
+                entry.offset,
+                entry.temporal_reference,
+                coding_type,
+                integer(picture.size),
+                number(picture.bitrate()),
+                entry.progressive_frame,
+                entry.top_field_first,
+                entry.repeat_first_field
+            )
+            .unwrap();
+        }
+        json.push_str("\n  ],\n");
+
+        json.push_str("  \"anomalies\": [");
+        for (i, anomaly) in self.anomalies.iter().enumerate() {
+            write!(
+                json,
+                "{}\n    {{\"line\": {}, \"kind\": \"{}\", \"message\": \"{}\"}}",
+                if i == 0 { "" } else { "," },
+                anomaly.line,
+                anomaly.kind.name(),
+                anomaly.kind
+            )
+            .unwrap();
+        }
+        json.push_str(if self.anomalies.is_empty() {
+            "]"
+        } else {
+            "\n  ]"
+        });
+        if let Some(events) = &self.events {
+            write!(json, ",\n  \"events\": {}", events_json(events)).unwrap();
+        }
+        json.push_str("\n}\n");
+
+        json
+    }
+}
+
+impl fmt::Display for StreamReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Sequence headers: {}", self.sequences)?;
+        writeln!(f, "Pictures:         {}", self.pictures.len())?;
+        if let Some(frame_period) = self.pictures.first().and_then(|p| p.frame_period) {
+            let frame_rate = FrameRate::from_frame_period(frame_period as u64);
+            writeln!(
+                f,
+                "Frame rate:       {} ({:.3} fps)",
+                frame_rate,
+                frame_rate.as_f64()
+            )?;
+        }
+
+        let types = &self.types;
+        writeln!(
+            f,
+            "Coding types:     I {}  P {}  B {}  D {}  unknown {}",
+            types.i, types.p, types.b, types.d, types.unknown
+        )?;
+
+        writeln!(f)?;
+        writeln!(f, "Groups of pictures: {}", self.gops.len())?;
+        if let Some((min, max, mean)) = self.gop_length_stats() {
+            writeln!(f, "  length min {} max {} mean {:.1}", min, max, mean)?;
+        }
+        // Coding types are only worth printing if the log has them
+        let known_types = self.types.unknown < self.pictures.len();
+        // Identical consecutive groups are only printed once
+        let mut previous: Option<(String, Option<bool>)> = None;
+        let mut repeat = 0;
+        for gop in &self.gops {
+            let current = (self.gop_structure(gop), gop.closed);
+            if previous.as_ref() == Some(&current) {
+                repeat += 1;
+                continue;
+            }
+            if repeat > 0 {
+                writeln!(f, "    (x{})", repeat + 1)?;
+            }
+            repeat = 0;
+
+            let closed = match gop.closed {
+                Some(true) => " closed",
+                Some(false) => " open",
+                None => "",
+            };
+            write!(
+                f,
+                "  line {:>5}: {:>3} pictures{}",
+                gop.line,
+                gop.pictures.len(),
+                closed
+            )?;
+            if known_types {
+                write!(f, " {}", current.0)?;
+            }
+            writeln!(f)?;
+            previous = Some(current);
+        }
+        if repeat > 0 {
+            writeln!(f, "    (x{})", repeat + 1)?;
+        }
+
+        writeln!(f)?;
+        match self.mean_bitrate() {
+            Some(bitrate) => writeln!(f, "Mean bitrate: {:.0} kbit/s", bitrate / 1000.0)?,
+            None => writeln!(f, "Mean bitrate: unknown")?,
+        }
+        if let Some((min, max, _)) = stats(
+            self.pictures
+                .iter()
+                .filter_map(|p| p.size)
+                .map(|s| s as f64),
+        ) {
+            writeln!(f, "  picture size min {} max {} bytes", min, max)?;
+        }
+        for (name, coding_type) in [
+            ("I", Some(CodingType::I)),
+            ("P", Some(CodingType::P)),
+            ("B", Some(CodingType::B)),
+            ("unknown", None),
+        ] {
+            if let Some(mean) = self.mean_size(coding_type) {
+                writeln!(f, "  mean {} picture size {:.0} bytes", name, mean)?;
+            }
+        }
+
+        let cadence = &self.cadence;
+        writeln!(f)?;
+        writeln!(f, "Cadence: {}", cadence.kind)?;
+        writeln!(f, "  pattern {}", cadence.pattern)?;
+        writeln!(
+            f,
+            "  PROG {}  TFF {}  RFF {}  field order breaks {}",
+            cadence.progressive_frames,
+            cadence.top_field_first,
+            cadence.repeat_first_field,
+            cadence.field_order_breaks
+        )?;
+
+        writeln!(f)?;
+        writeln!(f, "Anomalies: {}", self.anomalies.len())?;
+        for anomaly in &self.anomalies {
+            writeln!(f, "  line {:>5}: {}", anomaly.line, anomaly.kind)?;
+        }
+
+        if let Some(events) = &self.events {
+            writeln!(f)?;
+            writeln!(f, "Events: {}", events.len())?;
+            for event in events {
+                writeln!(f, "  {}", event)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+// Sample and display aspect ratios
+//
+// MPEG-2 sequences give the display aspect ratio of the sequence_display_extension
+// size, or of the coded size without one. `mpeg2dec` turns it into the sample
+// aspect ratio of the coded frame, the shape of one pixel, which tvid.log reports.
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    scale::{resize, Kernel},
+    timing::gcd,
+    yuv::YuvFrame,
+};
+
+/// Aspect ratio as a reduced fraction of width over height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AspectRatio {
+    num: u64,
+    den: u64,
+}
+
+impl AspectRatio {
+    /// # Panics
+    ///
+    /// If `num` or `den` is zero.
+    pub fn new(num: u64, den: u64) -> Self {
+        assert!(num != 0 && den != 0, "aspect ratio with a zero term");
+
+        let divisor = gcd(num, den);
+        Self {
+            num: num / divisor,
+            den: den / divisor,
+        }
+    }
+
+    pub fn num(&self) -> u64 {
+        self.num
+    }
+
+    pub fn den(&self) -> u64 {
+        self.den
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Display aspect ratio of `width`×`height` samples of this sample aspect ratio
+    pub fn display_aspect(&self, width: usize, height: usize) -> AspectRatio {
+        AspectRatio::new(
+            self.num * width.max(1) as u64,
+            self.den * height.max(1) as u64,
+        )
+    }
+
+    /// Sample aspect ratio showing `width`×`height` samples at this display aspect ratio
+    pub fn sample_aspect(&self, width: usize, height: usize) -> AspectRatio {
+        AspectRatio::new(
+            self.num * height.max(1) as u64,
+            self.den * width.max(1) as u64,
+        )
+    }
+
+    /// Width of `width` samples of this sample aspect ratio, in square pixels
+    pub fn square_width(&self, width: usize) -> usize {
+        ((width as u64 * self.num + self.den / 2) / self.den).max(1) as usize
+    }
+}
+
+impl fmt::Display for AspectRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.num, self.den)
+    }
+}
+
+impl FromStr for AspectRatio {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = s
+            .split_once([':', '/'])
+            .and_then(|(num, den)| Some((num.trim().parse().ok()?, den.trim().parse().ok()?)))
+            .filter(|(num, den)| *num != 0 && *den != 0);
+
+        match parsed {
+            Some((num, den)) => Ok(AspectRatio::new(num, den)),
+            None => Err(format!(
+                "invalid aspect ratio `{}`, expected WIDTH:HEIGHT such as 16:9",
+                s
+            )),
+        }
+    }
+}
+
+/// Copy of a frame stretched horizontally to square pixels
+pub fn to_square_pixels(frame: &YuvFrame, sample_aspect: AspectRatio, kernel: Kernel) -> YuvFrame {
+    if frame.is_empty() {
+        return frame.clone();
+    }
+    // Whole chroma samples
+    let (h_shift, _) = frame.chroma_format.subsampling();
+    let chroma_width = (sample_aspect.square_width(frame.width()) + (1 << h_shift) - 1) >> h_shift;
+
+    resize(frame, chroma_width << h_shift, frame.height(), kernel)
+}
@@ -0,0 +1,261 @@
+// Audio of a transport stream aligned with its video
+//
+// Layer II frames are placed at their presentation time relative to the first video time
+// stamp: the audio before the video is cut, and a late start, gaps and overlaps between the
+// time stamps and the samples written are filled with silence or cut. Time stamps drifting
+// more than a second are discontinuities, as at a splice: the audio goes on without a gap and
+// later time stamps are shifted to follow.
+
+use std::io::Read;
+
+use crate::{
+    mp2::{Mp2Decoder, SAMPLES_PER_FRAME},
+    timing::PTS_CLOCK_HZ,
+    ts::{pts_delta, PesPacket, StreamKind, TsDemuxer},
+    wav::WavWriter,
+    Error,
+};
+
+/// Drift between the audio time stamps and the samples written that is left uncorrected
+const SYNC_TOLERANCE_MS: u64 = 5;
+
+/// Drift from which the audio time stamps are taken to jump rather than to leave a gap or an
+/// overlap
+const DISCONTINUITY_MS: u64 = 1000;
+
+/// Destination of aligned 16-bit samples.
+pub trait AudioSink {
+    /// Number of samples written per channel
+    fn frames_written(&self) -> u64;
+
+    /// Appends interleaved samples, a whole number of samples per channel
+    fn write_samples(&mut self, samples: &[i16]) -> Result<(), Error>;
+
+    /// Appends `frames` samples of silence per channel
+    fn write_silence(&mut self, frames: u64) -> Result<(), Error>;
+}
+
+impl AudioSink for WavWriter {
+    fn frames_written(&self) -> u64 {
+        WavWriter::frames_written(self)
+    }
+
+    fn write_samples(&mut self, samples: &[i16]) -> Result<(), Error> {
+        WavWriter::write_samples(self, samples)
+    }
+
+    fn write_silence(&mut self, frames: u64) -> Result<(), Error> {
+        WavWriter::write_silence(self, frames)
+    }
+}
+
+/// Interleaved 16-bit samples in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcmBuffer {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<i16>,
+}
+
+impl PcmBuffer {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            samples: Vec::new(),
+        }
+    }
+}
+
+impl AudioSink for PcmBuffer {
+    fn frames_written(&self) -> u64 {
+        (self.samples.len() / usize::from(self.channels.max(1))) as u64
+    }
+
+    fn write_samples(&mut self, samples: &[i16]) -> Result<(), Error> {
+        self.samples.extend(samples);
+        Ok(())
+    }
+
+    fn write_silence(&mut self, frames: u64) -> Result<(), Error> {
+        let len = self.samples.len() + frames as usize * usize::from(self.channels);
+        self.samples.resize(len, 0);
+        Ok(())
+    }
+}
+
+/// How the audio was found and corrected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AudioReport {
+    /// PID of the decoded stream
+    pub pid: u16,
+    /// Whether the audio is aligned on a video time stamp, or else on its own first one
+    pub video_start: bool,
+    /// Frames of another sample rate or channel count than the first one
+    pub ignored: usize,
+    /// Time stamp jumps the audio went on through
+    pub discontinuities: usize,
+    /// Bytes skipped looking for frame headers
+    pub skipped: usize,
+}
+
+/// Places decoded frames in a sink created on the first one
+struct Aligner<S, F> {
+    decoder: Mp2Decoder,
+    /// Time stamp of the first sample
+    origin: u64,
+    create: F,
+    sink: Option<S>,
+    /// Sample rate and channel count of the sink
+    format: (u32, usize),
+    /// End of the last frame, in samples from the origin
+    position: Option<i64>,
+    /// Added to the time stamps since the last discontinuity, in samples
+    shift: i64,
+    ignored: usize,
+    discontinuities: usize,
+}
+
+impl<S: AudioSink, F: FnMut(u32, u16) -> Result<S, Error>> Aligner<S, F> {
+    fn push(&mut self, packet: &PesPacket) -> Result<(), Error> {
+        self.decoder.push(&packet.payload, packet.pts);
+
+        while let Some(frame) = self.decoder.next_frame() {
+            let header = frame.header;
+            let format = (header.sample_rate, header.channels());
+            let sink = match &mut self.sink {
+                Some(sink) => sink,
+                None => {
+                    self.format = format;
+                    let sink = (self.create)(header.sample_rate, header.channels() as u16)?;
+                    self.sink.insert(sink)
+                }
+            };
+            // The format is fixed by the first frame
+            if format != self.format {
+                self.ignored += 1;
+                continue;
+            }
+
+            // Frames without a time stamp follow the previous one
+            let rate = i64::from(header.sample_rate);
+            let written = sink.frames_written() as i64;
+            let mut start = match frame.pts {
+                Some(pts) => pts_delta(pts, self.origin) * rate / PTS_CLOCK_HZ as i64 + self.shift,
+                None => self.position.unwrap_or(written),
+            };
+            let mut drift = start - written;
+            if drift.unsigned_abs() > rate as u64 * DISCONTINUITY_MS / 1000 {
+                self.shift -= drift;
+                start -= drift;
+                drift = 0;
+                self.discontinuities += 1;
+            }
+            self.position = Some(start + SAMPLES_PER_FRAME as i64);
+
+            let mut samples = &frame.samples[..];
+            let tolerance = rate * SYNC_TOLERANCE_MS as i64 / 1000;
+            if drift > tolerance {
+                sink.write_silence(drift as u64)?;
+            } else if drift < -tolerance {
+                let skip = ((-drift) as usize).min(SAMPLES_PER_FRAME);
+                samples = &samples[skip * header.channels()..];
+            }
+            sink.write_samples(samples)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes the Layer II audio of a transport stream, aligned with its video
+///
+/// The stream of PID `pid` is decoded, or else the first MPEG audio stream. `create` makes the
+/// sink at the first frame, from its sample rate and channel count. Audio packets are only kept
+/// until the first video time stamp is found, then decoded as they are read.
+pub fn decode_audio<R: Read, S: AudioSink>(
+    demuxer: &mut TsDemuxer<R>,
+    pid: Option<u16>,
+    create: impl FnMut(u32, u16) -> Result<S, Error>,
+) -> Result<(S, AudioReport), Error> {
+    let mut audio_pid = pid;
+    let mut video_pts = None;
+    let mut pending = Vec::new();
+    let mut aligner: Option<Aligner<S, _>> = None;
+    let mut create = Some(create);
+
+    let new_aligner = |origin, create| Aligner {
+        decoder: Mp2Decoder::new(),
+        origin,
+        create,
+        sink: None,
+        format: (0, 0),
+        position: None,
+        shift: 0,
+        ignored: 0,
+        discontinuities: 0,
+    };
+
+    while let Some(packet) = demuxer.next_pes()? {
+        let kind = demuxer
+            .streams()
+            .find(|stream| stream.pid == packet.pid)
+            .map(|stream| stream.kind());
+
+        if kind == Some(StreamKind::Video) && video_pts.is_none() {
+            video_pts = packet.pts;
+        }
+        if audio_pid.is_none() && kind == Some(StreamKind::MpegAudio) {
+            audio_pid = Some(packet.pid);
+        }
+        if Some(packet.pid) == audio_pid {
+            match &mut aligner {
+                Some(aligner) => aligner.push(&packet)?,
+                None => pending.push(packet),
+            }
+        }
+
+        if let (None, Some(origin)) = (&aligner, video_pts) {
+            let aligner = aligner.insert(new_aligner(origin, create.take().unwrap()));
+            for packet in pending.drain(..) {
+                aligner.push(&packet)?;
+            }
+        }
+    }
+
+    let no_audio = |reason| Error::TransportStream {
+        path: demuxer.path().to_path_buf(),
+        reason,
+    };
+    let audio_pid = audio_pid.ok_or_else(|| no_audio("no MPEG audio stream"))?;
+    let mut aligner = match aligner {
+        Some(aligner) => aligner,
+        None => {
+            // Without video, the audio starts at its own first time stamp
+            let origin = pending
+                .iter()
+                .find_map(|packet| packet.pts)
+                .ok_or_else(|| no_audio("no presentation time stamp"))?;
+            let mut aligner = new_aligner(origin, create.take().unwrap());
+            for packet in pending.drain(..) {
+                aligner.push(&packet)?;
+            }
+            aligner
+        }
+    };
+
+    let sink = aligner
+        .sink
+        .take()
+        .ok_or_else(|| no_audio("no Layer II audio frame"))?;
+    Ok((
+        sink,
+        AudioReport {
+            pid: audio_pid,
+            video_start: video_pts.is_some(),
+            ignored: aligner.ignored,
+            discontinuities: aligner.discontinuities,
+            skipped: aligner.decoder.skipped(),
+        },
+    ))
+}
@@ -0,0 +1,812 @@
+// Closed captions carried in picture user data, see ATSC A/53, CEA-608 and CEA-708
+//
+// user_data: "GA94" 0x03 <flags: process_cc_data_flag, cc_count> <em_data>
+//            cc_count times <marker, cc_valid, cc_type> <cc_data_1> <cc_data_2>
+//
+// cc_type 0 and 1 carry CEA-608 byte pairs of field 1 (CC1, CC2) and field 2
+// (CC3, CC4). cc_type 3 starts a CEA-708 DTVCC packet and cc_type 2 continues
+// it, the packet holding service blocks of caption commands and text.
+//
+// Roll-up and paint-on text appears character by character, it is taken into
+// the captions when the next control code or service block comes.
+
+use std::{collections::BTreeMap, fmt, str::FromStr, time::Duration};
+
+use crate::{
+    metadata_parser::{user_data_chunks, Picture},
+    timing::{fields_duration, FrameRate},
+};
+
+/// Caption stream: a CEA-608 channel or a CEA-708 service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CaptionChannel {
+    /// CEA-608 channel CC1 to CC4
+    Cc(u8),
+    /// CEA-708 service 1 to 63
+    Service(u8),
+}
+
+impl fmt::Display for CaptionChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptionChannel::Cc(channel) => write!(f, "CC{}", channel),
+            CaptionChannel::Service(service) => write!(f, "SERVICE{}", service),
+        }
+    }
+}
+
+impl FromStr for CaptionChannel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        let parsed = if let Some(channel) = upper.strip_prefix("CC") {
+            channel
+                .parse()
+                .ok()
+                .filter(|channel| (1..=4).contains(channel))
+                .map(CaptionChannel::Cc)
+        } else if let Some(service) = upper.strip_prefix("SERVICE") {
+            service
+                .parse()
+                .ok()
+                .filter(|service| (1..=63).contains(service))
+                .map(CaptionChannel::Service)
+        } else {
+            None
+        };
+
+        parsed.ok_or_else(|| {
+            format!(
+                "unknown caption channel `{}`, expected CC1 to CC4 or SERVICE1 to SERVICE63",
+                s
+            )
+        })
+    }
+}
+
+/// Text shown on screen between two instants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caption {
+    pub channel: CaptionChannel,
+    pub start: Duration,
+    pub end: Duration,
+    /// Rows separated by line feeds
+    pub text: String,
+}
+
+/// A caption being shown, not ended yet.
+#[derive(Default)]
+struct Cue {
+    text: String,
+    start: Duration,
+}
+
+impl Cue {
+    /// Ends the shown caption if `text` differs, and starts showing `text`
+    fn update(
+        &mut self,
+        channel: CaptionChannel,
+        text: String,
+        now: Duration,
+        out: &mut Vec<Caption>,
+    ) {
+        if text == self.text {
+            return;
+        }
+
+        let previous = std::mem::replace(self, Cue { text, start: now });
+        if !previous.text.is_empty() && now > previous.start {
+            out.push(Caption {
+                channel,
+                start: previous.start,
+                end: now,
+                text: previous.text,
+            });
+        }
+    }
+}
+
+/// CEA-608 byte pair or CEA-708 packet bytes of A/53 caption data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CcData {
+    /// 0 and 1 for CEA-608 fields 1 and 2, 2 and 3 for DTVCC packet data and packet start
+    pub cc_type: u8,
+    pub valid: bool,
+    pub data: [u8; 2],
+}
+
+/// Caption data of ATSC A/53 picture user data, empty for other user data
+pub fn a53_cc_data(user_data: &[u8]) -> Vec<CcData> {
+    if !user_data.starts_with(b"GA94") || user_data.get(4) != Some(&0x03) {
+        return Vec::new();
+    }
+    let Some(flags) = user_data.get(5) else {
+        return Vec::new();
+    };
+    // process_cc_data_flag
+    if flags & 0x40 == 0 {
+        return Vec::new();
+    }
+    let count = usize::from(flags & 0x1f);
+
+    user_data
+        .get(7..)
+        .unwrap_or_default()
+        .chunks_exact(3)
+        .take(count)
+        .map(|cc| CcData {
+            cc_type: cc[0] & 0x03,
+            valid: cc[0] & 0x04 != 0,
+            data: [cc[1], cc[2]],
+        })
+        .collect()
+}
+
+const ROWS: usize = 15;
+const COLUMNS: usize = 32;
+
+/// Memory of the characters on screen, or of a pop-on caption being composed.
+#[derive(Clone)]
+struct Screen([[char; COLUMNS]; ROWS]);
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen([[' '; COLUMNS]; ROWS])
+    }
+}
+
+impl Screen {
+    fn text(&self) -> String {
+        self.0
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim().to_string())
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    PopOn,
+    RollUp(usize),
+    PaintOn,
+    /// Text service data, not captions
+    Text,
+}
+
+/// State of a CEA-608 caption channel.
+struct Cea608Channel {
+    mode: Mode,
+    displayed: Screen,
+    non_displayed: Screen,
+    row: usize,
+    column: usize,
+    cue: Cue,
+}
+
+impl Default for Cea608Channel {
+    fn default() -> Self {
+        Self {
+            mode: Mode::PopOn,
+            displayed: Screen::default(),
+            non_displayed: Screen::default(),
+            row: ROWS - 1,
+            column: 0,
+            cue: Cue::default(),
+        }
+    }
+}
+
+impl Cea608Channel {
+    /// Memory written by characters in the current mode
+    fn memory(&mut self) -> &mut Screen {
+        match self.mode {
+            Mode::PopOn => &mut self.non_displayed,
+            _ => &mut self.displayed,
+        }
+    }
+
+    fn write(&mut self, c: char) {
+        if self.mode == Mode::Text {
+            return;
+        }
+        let (row, column) = (self.row, self.column.min(COLUMNS - 1));
+        self.memory().0[row][column] = c;
+        self.column = (column + 1).min(COLUMNS - 1);
+    }
+
+    fn backspace(&mut self) {
+        if self.column > 0 {
+            self.column -= 1;
+            let (row, column) = (self.row, self.column);
+            self.memory().0[row][column] = ' ';
+        }
+    }
+
+    fn control(&mut self, code: u8) {
+        match code {
+            // Resume caption loading
+            0x20 => self.mode = Mode::PopOn,
+            0x21 => self.backspace(),
+            // Delete to end of row
+            0x24 => {
+                let (row, column) = (self.row, self.column);
+                self.memory().0[row][column..].fill(' ');
+            }
+            // Roll-up captions of 2, 3 or 4 rows
+            0x25..=0x27 => {
+                if !matches!(self.mode, Mode::RollUp(_)) {
+                    self.displayed = Screen::default();
+                    self.non_displayed = Screen::default();
+                    self.row = ROWS - 1;
+                }
+                self.mode = Mode::RollUp(usize::from(code - 0x23));
+                self.column = 0;
+            }
+            // Resume direct captioning
+            0x29 => self.mode = Mode::PaintOn,
+            // Text restart, resume text display
+            0x2a | 0x2b => self.mode = Mode::Text,
+            // Erase displayed memory
+            0x2c => self.displayed = Screen::default(),
+            // Carriage return
+            0x2d => {
+                if let Mode::RollUp(rows) = self.mode {
+                    let top = (self.row + 1).saturating_sub(rows);
+                    for row in top..self.row {
+                        self.displayed.0[row] = self.displayed.0[row + 1];
+                    }
+                    self.displayed.0[self.row] = [' '; COLUMNS];
+                } else if self.row + 1 < ROWS {
+                    self.row += 1;
+                }
+                self.column = 0;
+            }
+            // Erase non-displayed memory
+            0x2e => self.non_displayed = Screen::default(),
+            // End of caption, flip memories
+            0x2f => {
+                std::mem::swap(&mut self.displayed, &mut self.non_displayed);
+                self.mode = Mode::PopOn;
+            }
+            _ => (),
+        }
+    }
+
+    /// Preamble address code, moving the cursor to a row and an indentation
+    fn preamble(&mut self, first: u8, second: u8) {
+        let row = match (first & 0x07, second & 0x20 != 0) {
+            (0x1, false) => 1,
+            (0x1, true) => 2,
+            (0x2, false) => 3,
+            (0x2, true) => 4,
+            (0x5, false) => 5,
+            (0x5, true) => 6,
+            (0x6, false) => 7,
+            (0x6, true) => 8,
+            (0x7, false) => 9,
+            (0x7, true) => 10,
+            (0x0, _) => 11,
+            (0x3, false) => 12,
+            (0x3, true) => 13,
+            (0x4, false) => 14,
+            _ => 15,
+        } - 1;
+
+        // In roll-up mode the address moves the whole caption to the new base row
+        if let Mode::RollUp(rows) = self.mode {
+            if row != self.row {
+                let mut moved = Screen::default();
+                for i in 0..rows.min(row + 1).min(self.row + 1) {
+                    moved.0[row - i] = self.displayed.0[self.row - i];
+                }
+                self.displayed = moved;
+            }
+        }
+
+        self.row = row;
+        self.column = if second & 0x10 != 0 {
+            usize::from(second & 0x0e) * 2
+        } else {
+            0
+        };
+    }
+}
+
+/// Standard characters that differ from ASCII
+fn basic_char(byte: u8) -> char {
+    match byte {
+        0x2a => 'á',
+        0x5c => 'é',
+        0x5e => 'í',
+        0x5f => 'ó',
+        0x60 => 'ú',
+        0x7b => 'ç',
+        0x7c => '÷',
+        0x7d => 'Ñ',
+        0x7e => 'ñ',
+        0x7f => '█',
+        _ => char::from(byte),
+    }
+}
+
+const SPECIAL_CHARS: [char; 16] = [
+    '®', '°', '½', '¿', '™', '¢', '£', '♪', 'à', ' ', 'è', 'â', 'ê', 'î', 'ô', 'û',
+];
+
+/// Extended characters of the first bytes 0x12 and 0x13, from second byte 0x20
+const EXTENDED_CHARS: [[char; 32]; 2] = [
+    [
+        'Á', 'É', 'Ó', 'Ú', 'Ü', 'ü', '‘', '¡', '*', '\'', '─', '©', '℠', '•', '“', '”', 'À', 'Â',
+        'Ç', 'È', 'Ê', 'Ë', 'ë', 'Î', 'Ï', 'ï', 'Ô', 'Ù', 'ù', 'Û', '«', '»',
+    ],
+    [
+        'Ã', 'ã', 'Í', 'Ì', 'ì', 'Ò', 'ò', 'Õ', 'õ', '{', '}', '\\', '^', '_', '|', '~', 'Ä', 'ä',
+        'Ö', 'ö', 'ß', '¥', '¤', '│', 'Å', 'å', 'Ø', 'ø', '┌', '┐', '└', '┘',
+    ],
+];
+
+/// CEA-608 decoder of one field, which carries two channels.
+#[derive(Default)]
+struct Cea608Field {
+    channels: [Cea608Channel; 2],
+    /// Channel of the last control code, which characters go to
+    current: usize,
+    /// Control codes are sent twice, the repetition is ignored
+    last_control: Option<[u8; 2]>,
+}
+
+impl Cea608Field {
+    /// Decodes a byte pair, `true` after a control code
+    fn decode(&mut self, pair: [u8; 2]) -> bool {
+        let [first, second] = pair.map(|byte| byte & 0x7f);
+
+        if (0x10..0x20).contains(&first) {
+            if self.last_control == Some([first, second]) {
+                self.last_control = None;
+                return false;
+            }
+            self.last_control = Some([first, second]);
+
+            self.current = usize::from(first & 0x08 != 0);
+            let channel = &mut self.channels[self.current];
+            match (first & 0xf7, second) {
+                (0x14 | 0x15, 0x20..=0x2f) => channel.control(second),
+                // Tab offsets
+                (0x17, 0x21..=0x23) => {
+                    channel.column = (channel.column + usize::from(second - 0x20)).min(COLUMNS - 1)
+                }
+                // Mid-row codes change the style and show as a space
+                (0x11, 0x20..=0x2f) => channel.write(' '),
+                (0x11, 0x30..=0x3f) => channel.write(SPECIAL_CHARS[usize::from(second - 0x30)]),
+                // Extended characters replace the standard one sent before for older decoders
+                (0x12 | 0x13, 0x20..=0x3f) => {
+                    channel.backspace();
+                    let table = usize::from(first & 0xf7 == 0x13);
+                    channel.write(EXTENDED_CHARS[table][usize::from(second - 0x20)]);
+                }
+                (0x10..=0x17, 0x40..=0x7f) => channel.preamble(first, second),
+                _ => (),
+            }
+            return true;
+        }
+
+        self.last_control = None;
+        let channel = &mut self.channels[self.current];
+        for byte in [first, second] {
+            if byte >= 0x20 {
+                channel.write(basic_char(byte));
+            }
+        }
+        false
+    }
+}
+
+/// Caption window of a CEA-708 service.
+#[derive(Default)]
+struct Window {
+    visible: bool,
+    rows: Vec<String>,
+}
+
+impl Window {
+    fn current_row(&mut self) -> &mut String {
+        if self.rows.is_empty() {
+            self.rows.push(String::new());
+        }
+        self.rows.last_mut().unwrap()
+    }
+}
+
+/// State of a CEA-708 service, a simplified model keeping the text of every window.
+#[derive(Default)]
+struct Service {
+    windows: [Option<Window>; 8],
+    current: usize,
+    cue: Cue,
+}
+
+impl Service {
+    fn window(&mut self) -> &mut Window {
+        self.windows[self.current].get_or_insert_with(Window::default)
+    }
+
+    fn text(&self) -> String {
+        self.windows
+            .iter()
+            .flatten()
+            .filter(|window| window.visible)
+            .flat_map(|window| &window.rows)
+            .map(|row| row.trim())
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Applies `f` to the windows selected by a bitmap
+    fn windows_in(&mut self, bitmap: u8, f: impl Fn(&mut Option<Window>)) {
+        for (i, window) in self.windows.iter_mut().enumerate() {
+            if bitmap & (1 << i) != 0 {
+                f(window);
+            }
+        }
+    }
+
+    /// Decodes a service block
+    fn decode(&mut self, block: &[u8]) {
+        let mut i = 0;
+        while i < block.len() {
+            let code = block[i];
+            i += 1;
+            let start = i;
+            let param = |n: usize| block.get(start + n).copied().unwrap_or(0);
+
+            match code {
+                // Backspace, form feed, carriage return, horizontal carriage return
+                0x08 => {
+                    self.window().current_row().pop();
+                }
+                0x0c => self.window().rows.clear(),
+                0x0d => self.window().rows.push(String::new()),
+                0x0e => self.window().current_row().clear(),
+                // Extended code set, only G2 characters are kept
+                0x10 => {
+                    let extended = param(0);
+                    i += 1 + match extended {
+                        0x00..=0x07 => 0,
+                        0x08..=0x0f => 1,
+                        0x10..=0x17 => 2,
+                        0x18..=0x1f => 3,
+                        0x80..=0x87 => 4,
+                        0x88..=0x8f => 5,
+                        0x90..=0x9f => 1 + usize::from(param(1) & 0x3f),
+                        _ => 0,
+                    };
+                    if let Some(c) = g2_char(extended) {
+                        self.window().current_row().push(c);
+                    }
+                }
+                0x11..=0x17 => i += 1,
+                0x18..=0x1f => i += 2,
+                0x00..=0x1f => (),
+                0x7f => self.window().current_row().push('♪'),
+                0x20..=0x7e | 0xa0..=0xff => self.window().current_row().push(char::from(code)),
+                // Set current window
+                0x80..=0x87 => self.current = usize::from(code - 0x80),
+                // Clear, display, hide, toggle and delete windows
+                0x88..=0x8c => {
+                    let bitmap = param(0);
+                    i += 1;
+                    match code {
+                        0x88 => self.windows_in(bitmap, |w| {
+                            if let Some(w) = w {
+                                w.rows.clear()
+                            }
+                        }),
+                        0x89 => self.windows_in(bitmap, |w| {
+                            if let Some(w) = w {
+                                w.visible = true
+                            }
+                        }),
+                        0x8a => self.windows_in(bitmap, |w| {
+                            if let Some(w) = w {
+                                w.visible = false
+                            }
+                        }),
+                        0x8b => self.windows_in(bitmap, |w| {
+                            if let Some(w) = w {
+                                w.visible = !w.visible
+                            }
+                        }),
+                        _ => self.windows_in(bitmap, |w| *w = None),
+                    }
+                }
+                // Delay
+                0x8d => i += 1,
+                // Reset
+                0x8f => {
+                    self.windows = Default::default();
+                    self.current = 0;
+                }
+                // Pen attributes and color
+                0x90 | 0x92 => {
+                    // The pen location starts a new row when it moves down
+                    if code == 0x92 {
+                        let row = usize::from(param(0) & 0x0f);
+                        let window = self.window();
+                        while window.rows.len() <= row {
+                            window.rows.push(String::new());
+                        }
+                        window.rows.truncate(row + 1);
+                    }
+                    i += 2;
+                }
+                0x91 => i += 3,
+                // Window attributes
+                0x97 => i += 4,
+                // Define window, its visibility is in the first parameter
+                0x98..=0x9f => {
+                    self.current = usize::from(code - 0x98);
+                    let visible = param(0) & 0x20 != 0;
+                    self.window().visible = visible;
+                    i += 6;
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Characters of the G2 extended set
+fn g2_char(code: u8) -> Option<char> {
+    Some(match code {
+        0x20 | 0x21 => ' ',
+        0x25 => '…',
+        0x2a => 'Š',
+        0x2c => 'Œ',
+        0x30 => '█',
+        0x31 => '‘',
+        0x32 => '’',
+        0x33 => '“',
+        0x34 => '”',
+        0x35 => '•',
+        0x39 => '™',
+        0x3a => 'š',
+        0x3c => 'œ',
+        0x3d => '℠',
+        0x3f => 'Ÿ',
+        0x76 => '⅛',
+        0x77 => '⅜',
+        0x78 => '⅝',
+        0x79 => '⅞',
+        0x7a => '│',
+        0x7b => '┐',
+        0x7c => '└',
+        0x7d => '─',
+        0x7e => '┘',
+        0x7f => '┌',
+        _ => return None,
+    })
+}
+
+/// Decodes the captions of the pictures of a stream, in display order.
+#[derive(Default)]
+pub struct CaptionDecoder {
+    fields: [Cea608Field; 2],
+    /// DTVCC packet being collected
+    packet: Vec<u8>,
+    services: BTreeMap<u8, Service>,
+    captions: Vec<Caption>,
+    /// Field 1 byte pairs and the time of their picture
+    field1: Vec<(Duration, [u8; 2])>,
+}
+
+impl CaptionDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the captions of pictures in display order, until the end of the last one
+    pub fn from_pictures(pictures: &[Picture]) -> Self {
+        let mut decoder = Self::new();
+        for picture in pictures {
+            decoder.push(picture.pts, &picture.user_data);
+        }
+
+        let end = pictures.last().map_or(Duration::ZERO, |last| {
+            last.pts + fields_duration(last.frame_period as u64, last.displayed_fields())
+        });
+        decoder.finish(end);
+        decoder
+    }
+
+    /// Decodes the user data of a picture presented at `pts`
+    pub fn push(&mut self, pts: Duration, user_data: &[u8]) {
+        let cc_data = user_data_chunks(user_data).flat_map(a53_cc_data);
+        for cc in cc_data {
+            match cc.cc_type {
+                0 | 1 if cc.valid => {
+                    let field = usize::from(cc.cc_type);
+                    if field == 0 {
+                        self.field1.push((pts, cc.data));
+                    }
+                    if self.fields[field].decode(cc.data) {
+                        self.update_cea608(field, pts);
+                    }
+                }
+                3 => {
+                    self.finish_packet(pts);
+                    if cc.valid {
+                        self.packet.extend(cc.data);
+                    }
+                }
+                2 if cc.valid && !self.packet.is_empty() => {
+                    self.packet.extend(cc.data);
+                    // Packets are complete once they reach the size of their header
+                    if self.packet.len() >= dtvcc_packet_size(self.packet[0]) {
+                        self.finish_packet(pts);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn update_cea608(&mut self, field: usize, now: Duration) {
+        for (i, channel) in self.fields[field].channels.iter_mut().enumerate() {
+            let text = channel.displayed.text();
+            let id = CaptionChannel::Cc((2 * field + i + 1) as u8);
+            channel.cue.update(id, text, now, &mut self.captions);
+        }
+    }
+
+    /// Decodes the service blocks of the collected DTVCC packet
+    fn finish_packet(&mut self, now: Duration) {
+        let packet = std::mem::take(&mut self.packet);
+        let Some(header) = packet.first() else {
+            return;
+        };
+        let end = dtvcc_packet_size(*header).min(packet.len());
+
+        let mut i = 1;
+        while i < end {
+            let mut service = packet[i] >> 5;
+            let size = usize::from(packet[i] & 0x1f);
+            i += 1;
+            if service == 0 || size == 0 {
+                break;
+            }
+            // Extended service numbers
+            if service == 7 {
+                service = packet.get(i).map_or(0, |byte| byte & 0x3f);
+                i += 1;
+            }
+            let block = &packet[i.min(end)..(i + size).min(end)];
+            i += size;
+
+            let state = self.services.entry(service).or_default();
+            state.decode(block);
+            let text = state.text();
+            state.cue.update(
+                CaptionChannel::Service(service),
+                text,
+                now,
+                &mut self.captions,
+            );
+        }
+    }
+
+    /// Ends the captions still shown at `end`, the end of the last picture
+    pub fn finish(&mut self, end: Duration) {
+        self.finish_packet(end);
+        for field in 0..2 {
+            for (i, channel) in self.fields[field].channels.iter_mut().enumerate() {
+                let id = CaptionChannel::Cc((2 * field + i + 1) as u8);
+                channel
+                    .cue
+                    .update(id, String::new(), end, &mut self.captions);
+            }
+        }
+        for (service, state) in self.services.iter_mut() {
+            let id = CaptionChannel::Service(*service);
+            state.cue.update(id, String::new(), end, &mut self.captions);
+        }
+        self.captions
+            .sort_by_key(|caption| (caption.start, caption.channel));
+    }
+
+    /// Captions ended so far, all of them in order of appearance after `finish`
+    pub fn captions(&self) -> &[Caption] {
+        &self.captions
+    }
+
+    /// Byte pairs of field 1, with parity, and the presentation time of their picture
+    pub fn field1_pairs(&self) -> &[(Duration, [u8; 2])] {
+        &self.field1
+    }
+}
+
+/// Size of a DTVCC packet, header included, from its header byte
+fn dtvcc_packet_size(header: u8) -> usize {
+    match header & 0x3f {
+        0 => 128,
+        code => 2 * usize::from(code),
+    }
+}
+
+/// Captions as SubRip subtitles
+pub fn to_srt(captions: &[Caption]) -> String {
+    let time = |t: Duration| {
+        let ms = t.as_millis();
+        format!(
+            "{:02}:{:02}:{:02},{:03}",
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1000 % 60,
+            ms % 1000
+        )
+    };
+
+    let mut out = String::new();
+    for (i, caption) in captions.iter().enumerate() {
+        out += &format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            time(caption.start),
+            time(caption.end),
+            caption.text
+        );
+    }
+    out
+}
+
+/// Field 1 byte pairs in the Scenarist SCC format
+///
+/// Each line gives the time code of the first of consecutive non-null pairs. Time codes are
+/// drop-frame at 30000/1001 frames per second, non-drop-frame at other rates.
+pub fn to_scc(pairs: &[(Duration, [u8; 2])], frame_rate: FrameRate) -> String {
+    let drop_frame = frame_rate == FrameRate::new(30000, 1001);
+    let fps = frame_rate.as_f64().round().max(1.0) as u64;
+    let timecode = |t: Duration| {
+        let mut frame = (t.as_secs_f64() * frame_rate.as_f64()).round() as u64;
+        if drop_frame {
+            // Frame numbers 0 and 1 are skipped every minute, except every tenth minute
+            let (tens, rest) = (frame / 17982, frame % 17982);
+            frame += 18 * tens + if rest < 2 { 0 } else { 2 * ((rest - 2) / 1798) };
+        }
+        format!(
+            "{:02}:{:02}:{:02}{}{:02}",
+            frame / (3600 * fps),
+            frame / (60 * fps) % 60,
+            frame / fps % 60,
+            if drop_frame { ';' } else { ':' },
+            frame % fps
+        )
+    };
+
+    let mut out = String::from("Scenarist_SCC V1.0\n");
+    let mut line: Option<String> = None;
+    for (t, pair) in pairs {
+        if pair.map(|byte| byte & 0x7f) == [0, 0] {
+            if let Some(line) = line.take() {
+                out += &format!("\n{}\n", line);
+            }
+            continue;
+        }
+
+        let word = format!("{:02x}{:02x}", pair[0], pair[1]);
+        match &mut line {
+            Some(line) => {
+                line.push(' ');
+                line.push_str(&word);
+            }
+            None => line = Some(format!("{}\t{}", timecode(*t), word)),
+        }
+    }
+    if let Some(line) = line {
+        out += &format!("\n{}\n", line);
+    }
+    out
+}
@@ -0,0 +1,264 @@
+// Animated clips of a range of frames
+//
+// Frames are deinterlaced like in the viewer, one image per frame on its first field, or with
+// `field_rate` one image per displayed field, repeated first fields included. Durations come
+// from the picture metadata and are counted in periods of a 54 MHz clock, in which every field
+// of every MPEG-2 frame rate lasts a whole number of periods. Animated PNG frame delays are
+// fractions of a second and keep them exact when small enough, GIF delays are hundredths of a
+// second and are rounded so that the clip keeps its length. Players slow down GIF delays under
+// two hundredths, so images closer than that to the previous one are left out of a GIF.
+
+use std::{fs::File, io::BufWriter, ops::Range, path::Path};
+
+use crate::{
+    deinterlace::Deinterlacer,
+    flag::FrameMode,
+    gif::{median_cut, GifWriter, PALETTE_SIZE},
+    image::{Rgb, RgbImage},
+    scale::{resize, Kernel},
+    source::FrameSource,
+    timing::{gcd, SYSTEM_CLOCK_HZ},
+    yuv::{Field, YuvFrame},
+    Error,
+};
+
+/// Frequency of the clock of clip frame durations, in which a field lasts one frame period of
+/// the 27 MHz system clock
+pub const CLIP_CLOCK_HZ: u64 = 2 * SYSTEM_CLOCK_HZ;
+
+/// Field duration without stream metadata, frames lasting 40 ms
+const DEFAULT_FIELD_TICKS: u64 = CLIP_CLOCK_HZ / 50;
+
+/// Pixels sampled in every frame to build the GIF palette
+const PALETTE_SAMPLES: usize = 16384;
+
+/// Shortest GIF delay players honor, in hundredths of a second
+const MIN_GIF_DELAY: u64 = 2;
+
+/// An image of a clip and how long it is shown.
+pub struct ClipFrame {
+    /// Index of the source frame
+    pub frame: usize,
+    pub image: RgbImage,
+    /// Periods of the 54 MHz clip clock
+    pub duration: u64,
+}
+
+/// Reads the frames of `range` of a source as a clip, deinterlacing interlaced frames
+///
+/// Frames are scaled to the size of the first one. With `field_rate`, interlaced frames give
+/// one image per field they are displayed for, progressive frames still give one image.
+pub fn read_clip(
+    source: &mut dyn FrameSource,
+    range: Range<usize>,
+    deinterlacer: Deinterlacer,
+    field_rate: bool,
+) -> Result<Vec<ClipFrame>, Error> {
+    // The adaptive deinterlacer compares with the previous frame
+    let mut prev = None;
+    if range.start > 0 {
+        source.seek(range.start - 1)?;
+        prev = source.next_frame()?.map(|prev| prev.frame);
+    } else {
+        source.seek(0)?;
+    }
+
+    let mut size = None;
+    let mut clip = Vec::new();
+    for index in range {
+        let Some(frame) = source.next_frame()? else {
+            break;
+        };
+
+        let picture = frame.picture.as_ref();
+        let (fields, field_ticks) = match picture {
+            Some(picture) => (picture.displayed_fields(), picture.frame_period as u64),
+            None => (2, DEFAULT_FIELD_TICKS),
+        };
+        let interlaced = picture.is_some_and(|picture| picture.picture_type != FrameMode::PROG);
+        let first_field = if picture.is_some_and(|picture| picture.top_field_first) {
+            Field::Top
+        } else {
+            Field::Bottom
+        };
+
+        let images = if !interlaced {
+            vec![(frame.frame.clone(), fields)]
+        } else if field_rate {
+            // The first field again after the second when repeated
+            (0..fields)
+                .map(|i| {
+                    let field = if i % 2 == 0 {
+                        first_field
+                    } else {
+                        first_field.opposite()
+                    };
+                    (deinterlacer.apply(&frame.frame, prev.as_ref(), field), 1)
+                })
+                .collect()
+        } else {
+            vec![(
+                deinterlacer.apply(&frame.frame, prev.as_ref(), first_field),
+                fields,
+            )]
+        };
+
+        for (image, fields) in images {
+            let (width, height) = *size.get_or_insert((image.width(), image.height()));
+            clip.push(ClipFrame {
+                frame: index,
+                image: to_rgb(&image, width, height),
+                duration: fields * field_ticks,
+            });
+        }
+        prev = Some(frame.frame);
+    }
+
+    Ok(clip)
+}
+
+fn to_rgb(frame: &YuvFrame, width: usize, height: usize) -> RgbImage {
+    let mut image = RgbImage::with_capacity(0, 0);
+    if (frame.width(), frame.height()) == (width, height) || frame.is_empty() {
+        frame.to_rgb(&mut image);
+    } else {
+        resize(frame, width, height, Kernel::default()).to_rgb(&mut image);
+    }
+    image
+}
+
+/// Delays of the frames in `1 / unit_hz` seconds, rounding the end of every frame so that
+/// errors do not add up
+fn rounded_delays(clip: &[ClipFrame], unit_hz: u64) -> Vec<u16> {
+    let mut end = 0;
+    let mut rounded_end = 0;
+    clip.iter()
+        .map(|frame| {
+            end += frame.duration;
+            let start = rounded_end;
+            rounded_end = (end * unit_hz + CLIP_CLOCK_HZ / 2) / CLIP_CLOCK_HZ;
+            (rounded_end - start).min(u64::from(u16::MAX)) as u16
+        })
+        .collect()
+}
+
+/// Images of a GIF with their delays in hundredths of a second, keeping the clip length
+///
+/// An image starting less than [`MIN_GIF_DELAY`] after the previous one is left out, the
+/// previous one lasting until the next.
+fn gif_delays(clip: &[ClipFrame]) -> Vec<(&ClipFrame, u16)> {
+    let hundredths = |ticks: u64| (ticks * 100 + CLIP_CLOCK_HZ / 2) / CLIP_CLOCK_HZ;
+
+    let mut shown: Vec<(&ClipFrame, u64)> = Vec::new();
+    let mut end = 0;
+    for frame in clip {
+        let start = hundredths(end);
+        end += frame.duration;
+        if shown
+            .last()
+            .is_none_or(|(_, last)| start >= last + MIN_GIF_DELAY)
+        {
+            shown.push((frame, start));
+        }
+    }
+    let end = hundredths(end);
+    // The previous image also takes the end of the clip when the last one is too short
+    if shown.len() > 1
+        && shown
+            .last()
+            .is_some_and(|(_, last)| end < last + MIN_GIF_DELAY)
+    {
+        shown.pop();
+    }
+
+    let ends: Vec<u64> = shown
+        .iter()
+        .skip(1)
+        .map(|(_, start)| *start)
+        .chain([end])
+        .collect();
+    shown
+        .iter()
+        .zip(ends)
+        .map(|((frame, start), end)| (*frame, (end - start).min(u64::from(u16::MAX)) as u16))
+        .collect()
+}
+
+/// Duration of `ticks` of the clip clock as a reduced fraction of a second, if it fits in the
+/// frame delay of an animated PNG
+fn exact_delay(ticks: u64) -> Option<(u16, u16)> {
+    let divisor = gcd(ticks, CLIP_CLOCK_HZ).max(1);
+    let num = u16::try_from(ticks / divisor).ok()?;
+    let den = u16::try_from(CLIP_CLOCK_HZ / divisor).ok()?;
+    Some((num, den))
+}
+
+/// Writes a clip as an animated PNG, looping forever
+///
+/// Delays are exact fractions of a second, or milliseconds when one does not fit.
+pub fn write_apng(path: &Path, clip: &[ClipFrame]) -> Result<(), Error> {
+    let Some(first) = clip.first() else {
+        return Err(Error::NoFrames);
+    };
+    let delays: Vec<(u16, u16)> = match clip
+        .iter()
+        .map(|frame| exact_delay(frame.duration))
+        .collect()
+    {
+        Some(delays) => delays,
+        None => rounded_delays(clip, 1000)
+            .into_iter()
+            .map(|delay| (delay, 1000))
+            .collect(),
+    };
+
+    let file = File::create(path).map_err(|e| Error::io(path, e))?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        first.image.width() as u32,
+        first.image.height() as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let write = || {
+        encoder.set_animated(clip.len() as u32, 0)?;
+        let mut writer = encoder.write_header()?;
+        for (frame, (num, den)) in clip.iter().zip(delays) {
+            writer.set_frame_delay(num, den)?;
+            writer.write_image_data(&frame.image.get_rgb())?;
+        }
+        writer.finish()
+    };
+    write().map_err(|e| Error::png(path, e))
+}
+
+/// Writes a clip as an animated GIF, looping forever
+///
+/// The 256 colors of the palette are shared by all frames, from the median cut of pixels
+/// sampled in each. Images shown for less than two hundredths of a second, as fields often
+/// are, are merged into the previous one.
+pub fn write_gif(path: &Path, clip: &[ClipFrame]) -> Result<(), Error> {
+    let Some(first) = clip.first() else {
+        return Err(Error::NoFrames);
+    };
+
+    let mut samples: Vec<Rgb> = Vec::new();
+    for frame in clip {
+        let data = frame.image.get_data();
+        let step = (data.len() / PALETTE_SAMPLES).max(1);
+        samples.extend(data.iter().step_by(step));
+    }
+    let palette = median_cut(&samples, PALETTE_SIZE);
+
+    let mut writer = GifWriter::create(
+        path,
+        first.image.width().min(usize::from(u16::MAX)) as u16,
+        first.image.height().min(usize::from(u16::MAX)) as u16,
+        palette,
+    )?;
+    for (frame, delay) in gif_delays(clip) {
+        writer.write_frame(&frame.image, delay)?;
+    }
+    writer.finish()
+}
@@ -0,0 +1,227 @@
+// Contact sheets of thumbnails
+//
+// Thumbnails are laid out in rows, each over a caption strip giving its frame number and
+// presentation time. Captions use a built-in 5x7 font drawn at twice its size, which only has
+// digits and the few signs of the captions.
+
+use std::time::Duration;
+
+use crate::{
+    aspect::AspectRatio,
+    deinterlace::Deinterlacer,
+    events::timestamp,
+    flag::FrameMode,
+    image::{Rgb, RgbImage},
+    scale::{resize, Kernel},
+    source::FrameSource,
+    yuv::{Field, YuvFrame},
+    Error,
+};
+
+/// Space around and between the thumbnails
+const MARGIN: usize = 8;
+
+/// Size of the pixels of the font
+const GLYPH_SCALE: usize = 2;
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+/// Space around the caption text
+const CAPTION_PADDING: usize = 4;
+const CAPTION_HEIGHT: usize = GLYPH_HEIGHT * GLYPH_SCALE + 2 * CAPTION_PADDING;
+
+const BACKGROUND: Rgb = Rgb {
+    r: 32,
+    g: 32,
+    b: 32,
+};
+
+/// Rows of a character, the leftmost pixel in bit 4, blank for characters the font lacks
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c {
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        '#' => [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+/// Draws `text` with its top left corner at `x`, `y`, clipped to the image and to `max_x`
+fn draw_text(image: &mut RgbImage, text: &str, x: usize, y: usize, max_x: usize, color: Rgb) {
+    let max_x = max_x.min(image.width());
+    let advance = (GLYPH_WIDTH + 1) * GLYPH_SCALE;
+
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i * advance;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0x10 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..GLYPH_SCALE {
+                    let py = y + row * GLYPH_SCALE + dy;
+                    if py >= image.height() {
+                        continue;
+                    }
+                    for dx in 0..GLYPH_SCALE {
+                        let px = left + column * GLYPH_SCALE + dx;
+                        if px < max_x {
+                            image[py][px] = color;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A scaled down frame and where it comes from.
+pub struct Thumbnail {
+    /// Index of the frame in its source
+    pub frame: usize,
+    pub pts: Option<Duration>,
+    pub image: RgbImage,
+}
+
+impl Thumbnail {
+    /// `#<frame>`, then the presentation time if known
+    pub fn caption(&self) -> String {
+        match self.pts {
+            Some(pts) => format!("#{} {}", self.frame, timestamp(pts)),
+            None => format!("#{}", self.frame),
+        }
+    }
+}
+
+/// `count` indices spread evenly over `0..len`, starting with 0
+pub fn evenly_spaced(len: usize, count: usize) -> Vec<usize> {
+    let count = count.min(len);
+    (0..count).map(|i| i * len / count).collect()
+}
+
+/// Reads frame `index` of a source, deinterlaced, as a thumbnail `width` pixels wide
+///
+/// Interlaced frames show their first field. The height keeps the display aspect ratio of the
+/// frame, `aspect` if given, that of the stream otherwise.
+pub fn read_thumbnail(
+    source: &mut dyn FrameSource,
+    index: usize,
+    deinterlacer: Deinterlacer,
+    width: usize,
+    aspect: Option<AspectRatio>,
+) -> Result<Option<Thumbnail>, Error> {
+    // The adaptive deinterlacer compares with the previous frame
+    let prev = match deinterlacer {
+        Deinterlacer::Adaptive { .. } if index > 0 => {
+            source.seek(index - 1)?;
+            source.next_frame()?.map(|prev| prev.frame)
+        }
+        _ => {
+            source.seek(index)?;
+            None
+        }
+    };
+    let Some(frame) = source.next_frame()? else {
+        return Ok(None);
+    };
+
+    let picture = frame.picture.as_ref();
+    let interlaced = picture.is_some_and(|picture| picture.picture_type != FrameMode::PROG);
+    let image = if interlaced {
+        let first_field = if picture.is_some_and(|picture| picture.top_field_first) {
+            Field::Top
+        } else {
+            Field::Bottom
+        };
+        deinterlacer.apply(&frame.frame, prev.as_ref(), first_field)
+    } else {
+        frame.frame
+    };
+
+    let (frame_width, frame_height) = (image.width(), image.height());
+    let sample_aspect = match aspect {
+        Some(aspect) => Some(aspect.sample_aspect(frame_width, frame_height)),
+        None => picture.and_then(|picture| picture.sample_aspect),
+    };
+    let square_width = match sample_aspect {
+        Some(sample_aspect) => sample_aspect.square_width(frame_width),
+        None => frame_width,
+    };
+    let height = (frame_height * width + square_width / 2) / square_width.max(1);
+
+    let mut thumbnail = RgbImage::with_capacity(0, 0);
+    scale_down(&image, width, height.max(1)).to_rgb(&mut thumbnail);
+
+    Ok(Some(Thumbnail {
+        frame: index,
+        pts: frame.pts,
+        image: thumbnail,
+    }))
+}
+
+fn scale_down(frame: &YuvFrame, width: usize, height: usize) -> YuvFrame {
+    if frame.is_empty() {
+        return frame.clone();
+    }
+    resize(frame, width, height, Kernel::default())
+}
+
+/// Tiles thumbnails in rows of `columns`, each above its caption
+pub fn contact_sheet(thumbnails: &[Thumbnail], columns: usize) -> RgbImage {
+    let columns = columns.clamp(1, thumbnails.len().max(1));
+    let rows = thumbnails.len().div_ceil(columns);
+    let cell_width = thumbnails
+        .iter()
+        .map(|thumbnail| thumbnail.image.width())
+        .max()
+        .unwrap_or(0);
+    let cell_height = thumbnails
+        .iter()
+        .map(|thumbnail| thumbnail.image.height())
+        .max()
+        .unwrap_or(0)
+        + CAPTION_HEIGHT;
+
+    let mut sheet = RgbImage::with_capacity(
+        MARGIN + columns * (cell_width + MARGIN),
+        MARGIN + rows * (cell_height + MARGIN),
+    );
+    for y in 0..sheet.height() {
+        sheet[y].fill(BACKGROUND);
+    }
+
+    for (i, thumbnail) in thumbnails.iter().enumerate() {
+        let left = MARGIN + i % columns * (cell_width + MARGIN);
+        let top = MARGIN + i / columns * (cell_height + MARGIN);
+
+        let image = &thumbnail.image;
+        for y in 0..image.height() {
+            sheet[top + y][left..left + image.width()].copy_from_slice(&image[y]);
+        }
+
+        let strip = top + image.height();
+        for y in strip..strip + CAPTION_HEIGHT {
+            sheet[y][left..left + cell_width].fill(Rgb::new(0, 0, 0));
+        }
+        draw_text(
+            &mut sheet,
+            &thumbnail.caption(),
+            left + CAPTION_PADDING,
+            strip + CAPTION_PADDING,
+            left + cell_width,
+            Rgb::new(255, 255, 255),
+        );
+    }
+
+    sheet
+}
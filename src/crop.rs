@@ -0,0 +1,237 @@
+// Cropping of frames, before deinterlacing and export
+//
+// Crop rectangles keep the field structure of interlaced frames: the top
+// offset and the height are even, so that the top field stays on even lines,
+// and multiples of 4 in 4:2:0 where chroma lines also alternate between
+// fields. Horizontal offsets and widths are whole chroma samples.
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    flag::ChromaFormat,
+    metadata_parser::Picture,
+    source::{FrameSource, SourceFrame},
+    yuv::{Plane, YuvFrame, BLACK_LEVEL},
+    Error,
+};
+
+/// Frames sampled by default to detect black borders
+pub const DEFAULT_DETECT_FRAMES: usize = 25;
+
+/// Part of a line or column that can be brighter than black, for noise and logos
+const BRIGHT_TOLERANCE: f32 = 0.05;
+
+/// Rectangle of a frame to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CropRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl CropRect {
+    /// The rectangle shrunk to keep fields and chroma samples whole, within `width`×`height`
+    pub fn aligned(&self, chroma_format: ChromaFormat, width: usize, height: usize) -> CropRect {
+        let (h_shift, v_shift) = chroma_format.subsampling();
+        let h_align = 1 << h_shift;
+        let v_align = 2 << v_shift;
+
+        let x = self.x.min(width).next_multiple_of(h_align).min(width);
+        let y = self.y.min(height).next_multiple_of(v_align).min(height);
+        // A rectangle reaching past usize ends at the frame edge all the same
+        let right = self
+            .x
+            .checked_add(self.width)
+            .map_or(width, |right| right.min(width));
+        let bottom = self
+            .y
+            .checked_add(self.height)
+            .map_or(height, |bottom| bottom.min(height));
+
+        CropRect {
+            x,
+            y,
+            width: right.saturating_sub(x) / h_align * h_align,
+            height: bottom.saturating_sub(y) / v_align * v_align,
+        }
+    }
+
+    /// Cropped copy of `frame`, the rectangle being aligned first
+    pub fn apply(&self, frame: &YuvFrame) -> YuvFrame {
+        let rect = self.aligned(frame.chroma_format, frame.width(), frame.height());
+        frame.crop(rect.x, rect.y, rect.width, rect.height)
+    }
+
+    /// Smallest rectangle holding both
+    fn union(&self, other: &CropRect) -> CropRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+
+        CropRect {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        }
+    }
+}
+
+impl fmt::Display for CropRect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}+{}+{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+impl FromStr for CropRect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = || {
+            let (size, offsets) = s.split_once('+')?;
+            let (width, height) = size.split_once('x')?;
+            let (x, y) = offsets.split_once('+')?;
+
+            Some(CropRect {
+                x: x.parse().ok()?,
+                y: y.parse().ok()?,
+                width: width.parse().ok()?,
+                height: height.parse().ok()?,
+            })
+        };
+
+        parse().ok_or_else(|| format!("invalid crop rectangle `{}`, expected WxH+X+Y", s))
+    }
+}
+
+/// Manual crop rectangle, or black borders to detect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crop {
+    Rect(CropRect),
+    /// Borders that stay black over this many frames, sampled evenly
+    Auto(usize),
+}
+
+impl FromStr for Crop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("auto") {
+            Some("") => Ok(Crop::Auto(DEFAULT_DETECT_FRAMES)),
+            Some(frames) => frames
+                .strip_prefix(':')
+                .and_then(|frames| frames.parse().ok())
+                .filter(|frames| *frames > 0)
+                .map(Crop::Auto)
+                .ok_or_else(|| format!("invalid crop `{}`, expected auto:FRAMES", s)),
+            None => s.parse().map(Crop::Rect),
+        }
+    }
+}
+
+/// Whether samples are black, but for a few
+fn is_black<'a>(samples: impl Iterator<Item = &'a u8>) -> bool {
+    let mut count = 0;
+    let mut bright = 0;
+    for sample in samples {
+        count += 1;
+        bright += usize::from(*sample > BLACK_LEVEL);
+    }
+
+    bright as f32 <= count as f32 * BRIGHT_TOLERANCE
+}
+
+/// Area of a luma plane inside its black borders, `None` if the whole plane is black
+fn content(plane: &Plane) -> Option<CropRect> {
+    let black_row = |y: usize| is_black(plane.row(y).iter());
+    let black_column = |x: usize| is_black(plane.rows().map(|row| &row[x]));
+
+    let top = (0..plane.height()).find(|y| !black_row(*y))?;
+    let bottom = (0..plane.height()).rev().find(|y| !black_row(*y))?;
+    let left = (0..plane.width()).find(|x| !black_column(*x))?;
+    let right = (0..plane.width()).rev().find(|x| !black_column(*x))?;
+
+    Some(CropRect {
+        x: left,
+        y: top,
+        width: right + 1 - left,
+        height: bottom + 1 - top,
+    })
+}
+
+/// Smallest rectangle holding everything but the black borders of all frames
+pub fn detect_borders<'a>(frames: impl IntoIterator<Item = &'a YuvFrame>) -> Option<CropRect> {
+    frames
+        .into_iter()
+        .filter_map(|frame| content(&frame.y))
+        .reduce(|rect, other| rect.union(&other))
+}
+
+/// Detects the black borders of `frames` frames of a source, spread over it when its length is
+/// known, then rewinds it
+pub fn detect_source_borders(
+    source: &mut dyn FrameSource,
+    frames: usize,
+) -> Result<Option<CropRect>, Error> {
+    let mut sampled = Vec::with_capacity(frames);
+    match source.frame_count() {
+        Some(count) => {
+            for i in 0..frames.min(count) {
+                source.seek(i * count / frames.min(count))?;
+                if let Some(frame) = source.next_frame()? {
+                    sampled.push(frame.frame);
+                }
+            }
+        }
+        None => {
+            while sampled.len() < frames {
+                match source.next_frame()? {
+                    Some(frame) => sampled.push(frame.frame),
+                    None => break,
+                }
+            }
+        }
+    }
+    source.seek(0)?;
+
+    Ok(detect_borders(&sampled))
+}
+
+/// Source whose frames are cropped to a rectangle.
+pub struct CropSource {
+    inner: Box<dyn FrameSource>,
+    rect: CropRect,
+}
+
+impl CropSource {
+    pub fn new(inner: Box<dyn FrameSource>, rect: CropRect) -> Self {
+        Self { inner, rect }
+    }
+
+    pub fn rect(&self) -> CropRect {
+        self.rect
+    }
+}
+
+impl FrameSource for CropSource {
+    fn frame_count(&self) -> Option<usize> {
+        self.inner.frame_count()
+    }
+
+    fn seek(&mut self, index: usize) -> Result<(), Error> {
+        self.inner.seek(index)
+    }
+
+    fn next_frame(&mut self) -> Result<Option<SourceFrame>, Error> {
+        Ok(self.inner.next_frame()?.map(|frame| SourceFrame {
+            frame: self.rect.apply(&frame.frame),
+            ..frame
+        }))
+    }
+
+    fn pictures(&self) -> Option<&[Picture]> {
+        self.inner.pictures()
+    }
+}
@@ -0,0 +1,181 @@
+use std::{fmt, str::FromStr};
+
+use ndarray::Array2;
+
+use crate::yuv::{Field, YuvFrame};
+
+/// Size of the luma blocks compared between two frames to detect motion.
+pub const BLOCK_SIZE: usize = 8;
+
+/// How to build a progressive frame out of one field of an interlaced frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Deinterlacer {
+    /// Keep both fields as they are
+    Weave,
+    /// Line-double the field
+    Bob,
+    /// Line-double the field, but weave the blocks that did not move since the previous frame
+    Adaptive { threshold: f32 },
+}
+
+impl Deinterlacer {
+    /// Block error up to which the adaptive deinterlacer weaves
+    pub const DEFAULT_THRESHOLD: f32 = 0.05;
+
+    /// Progressive frame displayed for `field` of `curr`
+    pub fn apply(&self, curr: &YuvFrame, prev: Option<&YuvFrame>, field: Field) -> YuvFrame {
+        match self {
+            Deinterlacer::Weave => curr.clone(),
+            Deinterlacer::Bob => bob(curr, field),
+            Deinterlacer::Adaptive { threshold } => {
+                let mut frame = bob(curr, field);
+                if let Some(prev) = prev.filter(|prev| same_size(prev, curr)) {
+                    weave_static_blocks(
+                        &mut frame,
+                        prev,
+                        curr,
+                        &block_errors(prev, curr),
+                        *threshold,
+                    );
+                }
+                frame
+            }
+        }
+    }
+}
+
+impl fmt::Display for Deinterlacer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Deinterlacer::Weave => f.write_str("weave"),
+            Deinterlacer::Bob => f.write_str("bob"),
+            Deinterlacer::Adaptive { threshold } => write!(f, "adaptive:{}", threshold),
+        }
+    }
+}
+
+impl FromStr for Deinterlacer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, threshold) = match s.split_once(':') {
+            Some((name, threshold)) => (name, Some(threshold)),
+            None => (s, None),
+        };
+
+        match (name.to_ascii_lowercase().as_str(), threshold) {
+            ("weave", None) => Ok(Deinterlacer::Weave),
+            ("bob", None) => Ok(Deinterlacer::Bob),
+            ("adaptive", None) => Ok(Deinterlacer::Adaptive {
+                threshold: Deinterlacer::DEFAULT_THRESHOLD,
+            }),
+            ("adaptive", Some(threshold)) => threshold
+                .parse()
+                .ok()
+                .filter(|threshold: &f32| (0.0..=1.0).contains(threshold))
+                .map(|threshold| Deinterlacer::Adaptive { threshold })
+                .ok_or_else(|| {
+                    format!(
+                        "invalid adaptive threshold `{}`, expected 0 to 1",
+                        threshold
+                    )
+                }),
+            _ => Err(format!(
+                "unknown deinterlacer `{}`, expected weave, bob or adaptive[:THRESHOLD]",
+                s
+            )),
+        }
+    }
+}
+
+fn same_size(a: &YuvFrame, b: &YuvFrame) -> bool {
+    a.width() == b.width() && a.height() == b.height() && a.chroma_format == b.chroma_format
+}
+
+/// Line doubling: every line of the other field is replaced by the line above or below it
+pub fn bob(frame: &YuvFrame, field: Field) -> YuvFrame {
+    let mut out = frame.clone();
+
+    for plane in out.planes_mut() {
+        let other = field.opposite().parity();
+        for y in (other..plane.height()).step_by(2) {
+            // The top field line above, or the bottom field line below
+            let source = match field {
+                Field::Top => y - 1,
+                Field::Bottom if y + 1 < plane.height() => y + 1,
+                Field::Bottom => continue,
+            };
+            let line = plane.row(source).to_vec();
+            plane.row_mut(y).copy_from_slice(&line);
+        }
+    }
+
+    out
+}
+
+/// Mean absolute luma difference between the fields of two frames, normalized to `0..=1`.
+///
+/// One value per `BLOCK_SIZE` block, the largest of the top and bottom field differences.
+pub fn block_errors(prev: &YuvFrame, curr: &YuvFrame) -> Array2<f32> {
+    let rows = curr.height() / BLOCK_SIZE;
+    let cols = curr.width() / BLOCK_SIZE;
+    let mut errors = Array2::zeros((rows, cols));
+
+    for field in [Field::Top, Field::Bottom] {
+        for ((i, j), error) in errors.indexed_iter_mut() {
+            let mut sum = 0u32;
+            for y in (i * BLOCK_SIZE + field.parity()..(i + 1) * BLOCK_SIZE).step_by(2) {
+                let prev = &prev.y.row(y)[j * BLOCK_SIZE..(j + 1) * BLOCK_SIZE];
+                let curr = &curr.y.row(y)[j * BLOCK_SIZE..(j + 1) * BLOCK_SIZE];
+                sum += prev
+                    .iter()
+                    .zip(curr)
+                    .map(|(p, c)| u32::from(p.abs_diff(*c)))
+                    .sum::<u32>();
+            }
+
+            let field_error = sum as f32 / ((BLOCK_SIZE / 2 * BLOCK_SIZE) as f32 * 255f32);
+            *error = f32::max(*error, field_error);
+        }
+    }
+
+    errors
+}
+
+/// Weaves the current top field with the previous bottom field in every block whose error is
+/// at most `threshold`
+pub fn weave_static_blocks(
+    out: &mut YuvFrame,
+    prev: &YuvFrame,
+    curr: &YuvFrame,
+    errors: &Array2<f32>,
+    threshold: f32,
+) {
+    let (h_shift, v_shift) = curr.chroma_format.subsampling();
+    let shifts = [(0, 0), (h_shift, v_shift), (h_shift, v_shift)];
+
+    let planes = out
+        .planes_mut()
+        .into_iter()
+        .zip(prev.planes())
+        .zip(curr.planes())
+        .zip(shifts);
+
+    for (((out, prev), curr), (h_shift, v_shift)) in planes {
+        let block_width = BLOCK_SIZE >> h_shift;
+        let block_height = BLOCK_SIZE >> v_shift;
+
+        for ((i, j), error) in errors.indexed_iter() {
+            if *error > threshold {
+                continue;
+            }
+
+            let columns = j * block_width..(j + 1) * block_width;
+            for y in i * block_height..(i + 1) * block_height {
+                // Weave T(current) + B(previous)
+                let source = if y % 2 == 0 { curr } else { prev };
+                out.row_mut(y)[columns.clone()].copy_from_slice(&source.row(y)[columns.clone()]);
+            }
+        }
+    }
+}
@@ -1,15 +1,39 @@
 use std::{
-    ops::Div,
     path::PathBuf,
     time::{Duration, Instant},
 };
 
-use crate::{decode, flag::FrameMode, metadata_parser::Picture, RgbImage};
+use crate::{
+    afd::AfdMode,
+    aspect::AspectRatio,
+    captions::{Caption, CaptionDecoder},
+    deinterlace::Deinterlacer,
+    events::{Event, EventKind},
+    flag::FrameMode,
+    metadata_parser::Picture,
+    playback::AudioPlayback,
+    scale::{resize, Kernel},
+    scopes::{self, Histogram},
+    source::{FrameSource, SourceFrame},
+    timing::{displayed_fields, fields_duration, DEFAULT_FRAME_DURATION},
+    yuv::{Field, YuvFrame},
+    Error, RgbImage,
+};
 use eframe::{
-    egui::{self, ColorImage, Context},
+    egui::{
+        self,
+        plot::{Line, Plot, PlotPoints, Points, VLine},
+        Align2, Color32, ColorImage, Context, FontId, Rect, Rounding, Sense, Stroke,
+    },
     Frame,
 };
-use ndarray::{s, Array2};
+use ndarray::Array2;
+
+/// Height of the strip of events under the image
+const TIMELINE_HEIGHT: f32 = 24.0;
+
+/// How late a frame can be before playback gives up catching up and restarts its clock.
+const MAX_LATENESS: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum AppState {
@@ -24,6 +48,10 @@ struct MpegFrame {
     pub id: usize,
     pub mode: FrameMode,
     pub duration: Duration,
+    /// Time the frame stays on screen, repeated fields included
+    pub display_duration: Duration,
+    /// Presentation time relative to the first frame
+    pub pts: Duration,
 }
 
 impl MpegFrame {
@@ -45,10 +73,12 @@ impl MpegFrame {
 }
 
 pub struct MyApp {
-    pathfile: Vec<PathBuf>,
+    source: Box<dyn FrameSource>,
+    frame_count: Option<usize>,
+    /// Index of the frame the source will read next
+    source_index: usize,
     mode: Option<FrameMode>,
     threshold: f32,
-    meta: Option<Vec<Picture>>,
 
     index: usize,
     loaded_frame: MpegFrame,
@@ -59,14 +89,55 @@ pub struct MyApp {
     texture_2: egui::TextureHandle,
 
     state: AppState,
+    /// Instant at which the loaded frame was due, so that delays do not accumulate
     last_update: Instant,
+    /// Instant at which the frame with the given presentation time was due
+    playback_origin: Option<(Instant, Duration)>,
     refresh_rate: Option<Duration>,
 
     last_fps_update: (Instant, usize),
     last_fps: f64,
+    decode_time: Duration,
+    load_error: Option<Error>,
 
     rgb_image: RgbImage,
-    prev_pixels: Array2<u8>,
+    frame: YuvFrame,
+    picture: Option<Picture>,
+    frame_path: Option<PathBuf>,
+    frame_pts: Option<Duration>,
+    prev_frame: Option<YuvFrame>,
+
+    show_scopes: bool,
+    waveform_texture: egui::TextureHandle,
+    vectorscope_texture: egui::TextureHandle,
+    histogram: Option<Histogram>,
+
+    show_bitrate: bool,
+    /// Bitrate of every frame in kbit/s, empty without stream metadata
+    bitrates: Vec<[f64; 2]>,
+
+    show_captions: bool,
+    /// Captions of the first channel found in the picture user data
+    captions: Vec<Caption>,
+
+    afd_mode: AfdMode,
+    /// Whether any picture has an active format or bar data
+    has_afd: bool,
+
+    /// Display aspect ratio overriding the one of the stream
+    aspect: Option<AspectRatio>,
+    /// Sample aspect ratio of the loaded frame, `None` if unknown
+    sample_aspect: Option<AspectRatio>,
+
+    zoom: f32,
+    /// Kernel resampling frames to square pixels and to the zoom
+    kernel: Kernel,
+
+    /// Black frames, freezes and scene cuts, `None` if they were not looked for
+    events: Option<Vec<Event>>,
+
+    /// Audio output following the frames
+    audio: Option<AudioPlayback>,
 }
 
 impl MyApp {
@@ -79,27 +150,56 @@ impl MyApp {
 
     pub fn new(
         cc: &eframe::CreationContext<'_>,
-        files: Vec<PathBuf>,
+        source: Box<dyn FrameSource>,
         img_per_second: Option<u64>,
         mode: Option<String>,
         threshold: Option<f32>,
-        meta: Option<Vec<Picture>>,
+        afd_mode: AfdMode,
+        aspect: Option<AspectRatio>,
     ) -> Self {
         let default_texture_size = [480, 680];
+        let bitrates = source
+            .pictures()
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, picture)| Some([index as f64, picture.bitrate()? / 1000.0]))
+            .collect();
+
+        let has_afd = source
+            .pictures()
+            .unwrap_or_default()
+            .iter()
+            .any(|picture| picture.active_format.is_some() || picture.bar_data.is_some());
+
+        // Decoded captions are sorted by channel, CC1 first
+        let decoder = CaptionDecoder::from_pictures(source.pictures().unwrap_or_default());
+        let captions = match decoder.captions().first() {
+            Some(first) => decoder
+                .captions()
+                .iter()
+                .filter(|caption| caption.channel == first.channel)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
 
         MyApp {
-            pathfile: files,
-            mode: mode
-                .map(|m| FrameMode::from(m.trim().split_whitespace().collect::<Vec<_>>().iter())),
+            // An empty source would wrap the index around zero frames
+            frame_count: source.frame_count().filter(|count| *count > 0),
+            source,
+            source_index: 0,
+            mode: mode.map(|m| FrameMode::from(m.split_whitespace().collect::<Vec<_>>().iter())),
 
-            threshold: threshold.unwrap_or(0.05),
-            meta,
+            threshold: threshold.unwrap_or(Deinterlacer::DEFAULT_THRESHOLD),
 
             index: 0,
             loaded_frame: MpegFrame {
                 id: 0,
                 mode: FrameMode::PROG,
                 duration: Duration::from_millis(0),
+                display_duration: Duration::ZERO,
+                pts: Duration::ZERO,
             },
 
             field_display_idx: 0,
@@ -117,218 +217,563 @@ impl MyApp {
 
             state: AppState::Play,
             last_update: Instant::now(),
+            playback_origin: None,
             refresh_rate: img_per_second
                 .map(|img_per_second| Duration::from_nanos(1_000_000_000 / img_per_second)),
 
             last_fps_update: (Instant::now(), 0),
             last_fps: 0f64,
+            decode_time: Duration::ZERO,
+            load_error: None,
 
             rgb_image: RgbImage::with_capacity(0, 0),
-            prev_pixels: Array2::zeros((0, 0)),
+            frame: YuvFrame::empty(),
+            picture: None,
+            frame_path: None,
+            frame_pts: None,
+            prev_frame: None,
+
+            show_scopes: false,
+            waveform_texture: cc.egui_ctx.load_texture(
+                "waveform",
+                ColorImage::new([scopes::LEVELS, scopes::LEVELS], Color32::BLACK),
+                Default::default(),
+            ),
+            vectorscope_texture: cc.egui_ctx.load_texture(
+                "vectorscope",
+                ColorImage::new([scopes::LEVELS, scopes::LEVELS], Color32::BLACK),
+                Default::default(),
+            ),
+            histogram: None,
+
+            show_bitrate: false,
+            bitrates,
+
+            show_captions: false,
+            captions,
+
+            afd_mode,
+            has_afd,
+
+            aspect,
+            sample_aspect: None,
+
+            zoom: 1.0,
+            // Cheap enough to resample every frame during playback
+            kernel: Kernel::Bilinear,
+
+            events: None,
+            audio: None,
+        }
+    }
+
+    /// Shows events on a timeline under the image
+    pub fn with_events(mut self, events: Vec<Event>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Plays audio in step with the frames
+    pub fn with_audio(mut self, audio: AudioPlayback) -> Self {
+        self.audio = Some(audio);
+        self
+    }
+
+    /// Crops or letterboxes a frame to the active image of the loaded picture
+    ///
+    /// Without a known aspect ratio, frames wider than 14:9 are taken as 16:9 and the others
+    /// as 4:3.
+    fn apply_afd(&self, frame: YuvFrame) -> YuvFrame {
+        let (width, height) = (frame.width(), frame.height());
+        let display_aspect = match self.sample_aspect {
+            Some(sample_aspect) => sample_aspect.display_aspect(width, height).as_f64(),
+            None if width * 9 > height * 14 => 16.0 / 9.0,
+            None => 4.0 / 3.0,
+        };
+        let area = self
+            .picture
+            .as_ref()
+            .and_then(|picture| picture.active_area(width, height, display_aspect));
+
+        match area {
+            Some(area) if self.afd_mode != AfdMode::Off => self.afd_mode.apply(&frame, area),
+            _ => frame,
+        }
+    }
+
+    /// Resamples a frame to square pixels, then to the zoom
+    fn scale_for_display(&self, frame: YuvFrame) -> YuvFrame {
+        let width = match self.sample_aspect {
+            Some(sample_aspect) => sample_aspect.square_width(frame.width()),
+            None => frame.width(),
+        };
+        let width = ((width as f32 * self.zoom).round() as usize).max(1);
+        let height = ((frame.height() as f32 * self.zoom).round() as usize).max(1);
+
+        if frame.is_empty() || (width, height) == (frame.width(), frame.height()) {
+            frame
+        } else {
+            resize(&frame, width, height, self.kernel)
+        }
+    }
+
+    /// Loads the shown frame again, for display settings to apply while paused
+    fn reload(&mut self) {
+        if self.state != AppState::Play {
+            self.index = self.loaded_frame.id;
+            self.loaded_frame.id = usize::MAX;
+            self.state = AppState::Next;
+        }
+    }
+
+    /// Draws the caption shown at the current frame at the bottom of the image
+    fn paint_caption(&self, ui: &egui::Ui, image: Rect) {
+        let Some(pts) = self.frame_pts else {
+            return;
+        };
+        let Some(caption) = self
+            .captions
+            .iter()
+            .find(|caption| caption.start <= pts && pts < caption.end)
+        else {
+            return;
+        };
+
+        let painter = ui.painter_at(image);
+        let galley = painter.layout_no_wrap(
+            caption.text.clone(),
+            FontId::monospace(16.0),
+            Color32::WHITE,
+        );
+        let position = image.center_bottom() - egui::vec2(0.0, 16.0);
+        let text = Align2::CENTER_BOTTOM.anchor_rect(Rect::from_min_size(position, galley.size()));
+
+        painter.rect_filled(text.expand(4.0), Rounding::none(), Color32::BLACK);
+        painter.galley(text.min, galley);
+    }
+
+    /// Draws the events over all frames, clicking or dragging seeks to the frame under the pointer
+    fn show_timeline(&mut self, ui: &mut egui::Ui) {
+        let Some(events) = &self.events else {
+            return;
+        };
+        let last = events.iter().map(|event| event.frame + event.frames).max();
+        let frames = self
+            .frame_count
+            .or(last)
+            .unwrap_or(0)
+            .max(self.loaded_frame.id + 1);
+
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), TIMELINE_HEIGHT),
+            Sense::click_and_drag(),
+        );
+        let x = |frame: usize| rect.left() + rect.width() * frame as f32 / frames as f32;
+        let frame_at = |x: f32| {
+            let frame = (x - rect.left()) / rect.width() * frames as f32;
+            (frame.max(0.0) as usize).min(frames - 1)
+        };
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, Rounding::none(), Color32::from_gray(60));
+        for event in events {
+            match event.kind {
+                EventKind::SceneCut => painter.vline(
+                    x(event.frame),
+                    rect.y_range(),
+                    Stroke::new(1.0, Color32::RED),
+                ),
+                EventKind::Black | EventKind::Freeze => {
+                    let color = if event.kind == EventKind::Black {
+                        Color32::BLACK
+                    } else {
+                        Color32::LIGHT_BLUE
+                    };
+                    let start = x(event.frame);
+                    let end = x(event.frame + event.frames).max(start + 1.0);
+                    let span = Rect::from_x_y_ranges(start..=end, rect.y_range());
+                    painter.rect_filled(span, Rounding::none(), color);
+                }
+            }
+        }
+        painter.vline(
+            x(self.loaded_frame.id),
+            rect.y_range(),
+            Stroke::new(2.0, Color32::YELLOW),
+        );
+
+        let count = |kind| events.iter().filter(|event| event.kind == kind).count();
+        ui.label(format!(
+            "{} black, {} frozen, {} scene cuts",
+            count(EventKind::Black),
+            count(EventKind::Freeze),
+            count(EventKind::SceneCut)
+        ));
+
+        let seek = response
+            .interact_pointer_pos()
+            .filter(|_| response.clicked() || response.dragged())
+            .map(|pointer| frame_at(pointer.x));
+        if let Some(pointer) = response.hover_pos() {
+            let frame = frame_at(pointer.x);
+            let mut text = format!("Frame {}", frame);
+            for event in events.iter().filter(|event| event.contains(frame)) {
+                text += &format!("\n{}", event);
+            }
+            response.on_hover_text_at_pointer(text);
+        }
+
+        if let Some(frame) = seek.filter(|frame| *frame != self.index) {
+            self.index = frame;
+            self.field_display_idx = 0;
+            self.state = AppState::Next;
+            self.last_fps_update = (Instant::now(), frame);
         }
     }
 
     pub fn incr_index(&mut self) {
-        self.index = (self.index + 1) % self.pathfile.len();
+        self.index = match self.frame_count {
+            Some(frame_count) => (self.index + 1) % frame_count,
+            None => self.index + 1,
+        };
     }
     pub fn decr_index(&mut self) {
-        self.index = if self.index == 0 {
-            self.pathfile.len() - 1
-        } else {
-            self.index - 1
+        self.index = match (self.index, self.frame_count) {
+            (0, Some(frame_count)) => frame_count - 1,
+            (0, None) => 0,
+            (index, _) => index - 1,
         };
     }
+
+    /// Reads the frame at `self.index`, going back to the first frame at the end of the source
+    fn read_frame(&mut self) -> Result<SourceFrame, Error> {
+        if self.source_index != self.index {
+            self.source.seek(self.index)?;
+        }
+
+        let frame = match self.source.next_frame()? {
+            Some(frame) => frame,
+            None => {
+                // Now that the end was reached, the frame count is known
+                self.frame_count = Some(self.index.max(1));
+                self.index = 0;
+                self.source.seek(0)?;
+                self.source.next_frame()?.ok_or(Error::NoFrames)?
+            }
+        };
+        self.source_index = self.index + 1;
+
+        Ok(frame)
+    }
+
+    /// Instant at which the loaded frame is due, from its presentation time
+    ///
+    /// The clock restarts when playback starts, after a seek, or when too far behind.
+    fn schedule(&mut self) -> Instant {
+        let now = Instant::now();
+        let pts = self.loaded_frame.pts;
+
+        if self.state == AppState::Play {
+            if let Some((origin, origin_pts)) = self.playback_origin {
+                if let Some(offset) = pts.checked_sub(origin_pts) {
+                    let scheduled = origin + offset;
+                    let late = now.saturating_duration_since(scheduled);
+                    let early = scheduled.saturating_duration_since(now);
+                    if late <= MAX_LATENESS && early <= MAX_LATENESS {
+                        return scheduled;
+                    }
+                }
+            }
+            self.playback_origin = Some((now, pts));
+        } else {
+            self.playback_origin = None;
+        }
+
+        now
+    }
+
+    /// Collapsible table of everything known about the loaded frame
+    fn show_frame_metadata(&self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Frame metadata").show(ui, |ui| {
+            egui::Grid::new("frame-metadata")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    let meta = self.picture.as_ref();
+
+                    let mut row = |name: &str, value: String| {
+                        ui.label(name);
+                        ui.label(value);
+                        ui.end_row();
+                    };
+
+                    row("Frame", self.loaded_frame.id.to_string());
+                    row("Mode", format!("{:?}", self.loaded_frame.mode));
+                    row("Chroma", format!("{:?}", self.frame.chroma_format));
+                    if let Some(sample_aspect) = self.sample_aspect {
+                        let display_aspect =
+                            sample_aspect.display_aspect(self.frame.width(), self.frame.height());
+                        row(
+                            "Aspect ratio",
+                            format!(
+                                "SAR {}, DAR {} ({:.3}){}",
+                                sample_aspect,
+                                display_aspect,
+                                display_aspect.as_f64(),
+                                if self.aspect.is_some() {
+                                    ", forced"
+                                } else {
+                                    ""
+                                }
+                            ),
+                        );
+                    }
+                    if let Some(events) = &self.events {
+                        let current: Vec<String> = events
+                            .iter()
+                            .filter(|event| event.contains(self.loaded_frame.id))
+                            .map(|event| event.kind.to_string())
+                            .collect();
+                        if !current.is_empty() {
+                            row("Events", current.join(", "));
+                        }
+                    }
+                    row("Duration", format!("{:?}", self.loaded_frame.duration));
+                    row("PTS", format!("{:?}", self.loaded_frame.pts));
+
+                    if let Some(meta) = meta {
+                        row(
+                            "Picture type",
+                            meta.coding_type
+                                .map_or("unknown".to_string(), |t| format!("{:?}", t)),
+                        );
+                        row("Temporal reference", meta.temporal_reference.to_string());
+                        row("Frame period", format!("{} (27 MHz)", meta.frame_period));
+                        let frame_rate = meta.frame_rate();
+                        row(
+                            "Frame rate",
+                            format!("{} ({:.3} fps)", frame_rate, frame_rate.as_f64()),
+                        );
+                        row("Offset", format!("{:#010x}", meta.offset));
+                        if let Some((width, height)) = meta.coded_size {
+                            row("Sequence size", format!("{}x{}", width, height));
+                        }
+                        if let Some((width, height)) = meta.display_size {
+                            row("Display size", format!("{}x{}", width, height));
+                        }
+                        if let Some(display_aspect) = meta.display_aspect() {
+                            row("Sequence DAR", display_aspect.to_string());
+                        }
+                        if let (Some(size), Some(bitrate)) = (meta.size, meta.bitrate()) {
+                            row(
+                                "Size",
+                                format!("{} bytes ({:.0} kbit/s)", size, bitrate / 1000.0),
+                            );
+                        }
+                        row("PROG", meta.progressive_frame.to_string());
+                        row("RFF", meta.repeat_first_field.to_string());
+                        row("TFF", meta.top_field_first.to_string());
+                        if let Some(active_format) = meta.active_format {
+                            row("AFD", active_format.to_string());
+                        }
+                        if let Some(bar_data) = meta.bar_data {
+                            row("Bar data", bar_data.to_string());
+                        }
+                    } else {
+                        row("Metadata", "none".to_string());
+                    }
+
+                    if let Some(path) = &self.frame_path {
+                        row("Source", path.display().to_string());
+                    }
+                    row("Decode time", format!("{:?}", self.decode_time));
+                });
+        });
+    }
+
+    /// Recompute the scopes of the currently loaded frame
+    fn update_scopes(&mut self) {
+        if self.frame.is_empty() {
+            return;
+        }
+
+        let waveform = scopes::waveform(&self.frame);
+        self.waveform_texture
+            .set(scope_image(&waveform, Color32::GREEN), Default::default());
+
+        let vectorscope = scopes::vectorscope(&self.frame);
+        let mut image = scope_image(&vectorscope, Color32::WHITE);
+        draw_bar_targets(&mut image);
+        self.vectorscope_texture.set(image, Default::default());
+
+        self.frame.to_rgb(&mut self.rgb_image);
+        self.histogram = Some(scopes::histogram(&self.frame, &self.rgb_image));
+    }
+}
+
+const BAR_TARGET_COLOR: Color32 = Color32::from_rgb(220, 180, 0);
+
+/// Converts scope counts to an image, using a logarithmic intensity scale
+fn scope_image(counts: &Array2<u32>, tint: Color32) -> ColorImage {
+    let (height, width) = counts.dim();
+    let max = counts.iter().copied().max().unwrap_or(0) as f32;
+    let norm = (1f32 + max).ln().max(f32::EPSILON);
+
+    let mut image = ColorImage::new([width, height], Color32::BLACK);
+    for ((i, j), count) in counts.indexed_iter() {
+        let intensity = (1f32 + *count as f32).ln() / norm;
+        image.pixels[i * width + j] = Color32::from_rgb(
+            (tint.r() as f32 * intensity) as u8,
+            (tint.g() as f32 * intensity) as u8,
+            (tint.b() as f32 * intensity) as u8,
+        );
+    }
+
+    image
+}
+
+/// Draws the graticule and the 75% color bars target boxes on a vectorscope image
+fn draw_bar_targets(image: &mut ColorImage) {
+    const BOX_RADIUS: usize = 4;
+    let graticule = Color32::from_gray(80);
+
+    let [width, height] = image.size;
+    for i in 0..width {
+        image.pixels[height / 2 * width + i] = graticule;
+        image.pixels[i * width + width / 2] = graticule;
+    }
+
+    for bar in scopes::bar_targets_75() {
+        let row = scopes::LEVELS - 1 - bar.cr as usize;
+        let col = bar.cb as usize;
+
+        let top = row.saturating_sub(BOX_RADIUS);
+        let bottom = (row + BOX_RADIUS).min(height - 1);
+        let left = col.saturating_sub(BOX_RADIUS);
+        let right = (col + BOX_RADIUS).min(width - 1);
+
+        for j in left..=right {
+            image.pixels[top * width + j] = BAR_TARGET_COLOR;
+            image.pixels[bottom * width + j] = BAR_TARGET_COLOR;
+        }
+        for i in top..=bottom {
+            image.pixels[i * width + left] = BAR_TARGET_COLOR;
+            image.pixels[i * width + right] = BAR_TARGET_COLOR;
+        }
+    }
 }
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
-        let nb_img = self.pathfile.len();
+        let nb_img = self.frame_count;
 
         let (last_update, last_index) = self.last_fps_update;
         let last_update = last_update.elapsed().as_secs_f64();
         if self.state == AppState::Play && last_update >= 1f64 {
-            self.last_fps_update = (Instant::now(), self.index as usize);
-            self.last_fps = (self.index - last_index) as f64 / last_update;
+            self.last_fps_update = (Instant::now(), self.index);
+            // The index goes back when looping to the first frame
+            self.last_fps = self.index.saturating_sub(last_index) as f64 / last_update;
             if self.loaded_frame.interlaced() {
                 self.last_fps *= 2f64;
             }
         }
 
         let load_new_frame = match self.state {
-            AppState::Play => {
-                if self.loaded_frame.repeat_first_field() {
-                    // 1.5 refresh duration
-                    self.last_update.elapsed() >= self.loaded_frame.duration.mul_f32(1.5)
-                } else {
-                    // 1.0 refresh duration
-                    self.last_update.elapsed() >= self.loaded_frame.duration
-                }
-            }
+            AppState::Play => self.last_update.elapsed() >= self.loaded_frame.display_duration,
             AppState::Next | AppState::Previous => self.index != self.loaded_frame.id,
             AppState::Pause => false,
         };
 
         if load_new_frame {
-            // Update the last_update time
-            self.last_update = Instant::now();
-
-            // Retrieve the right image path to load
-            let path = &self.pathfile[self.index];
+            // Load the image and convert to RGBA pixels
+            let decode_start = Instant::now();
+            // On error, keep displaying the previous frame
+            match self.read_frame() {
+                Ok(frame) => {
+                    self.frame = frame.frame;
+                    self.picture = frame.picture;
+                    self.frame_path = frame.path;
+                    self.frame_pts = frame.pts;
+                    self.load_error = None;
+
+                    self.sample_aspect = match self.aspect {
+                        Some(aspect) => {
+                            Some(aspect.sample_aspect(self.frame.width(), self.frame.height()))
+                        }
+                        None => self.picture.as_ref().and_then(|p| p.sample_aspect),
+                    };
+                }
+                Err(err) => self.load_error = Some(err),
+            }
+            self.decode_time = decode_start.elapsed();
 
-            let meta = self
-                .meta
-                .as_ref()
-                .map_or(None, |meta| Some(&meta[self.index]));
+            // Print the path of the loaded image (only in debug mode)
+            #[cfg(debug_assertions)]
+            dbg!(&self.frame_path);
+
+            let meta = self.picture.as_ref();
+            let duration = self
+                .refresh_rate
+                .unwrap_or(meta.map_or(DEFAULT_FRAME_DURATION, |meta| meta.duration));
+            let mode = self
+                .mode
+                .unwrap_or(meta.map_or(FrameMode::PROG, |meta| meta.picture_type));
+            let display_duration = match meta.filter(|_| self.refresh_rate.is_none()) {
+                Some(meta) => fields_duration(meta.frame_period as u64, meta.displayed_fields()),
+                // Without stream timing, count the fields of the requested mode
+                None => {
+                    let repeat = matches!(mode, FrameMode::RFF_TFF | FrameMode::RFF_BFF);
+                    duration * displayed_fields(false, repeat, false) as u32 / 2
+                }
+            };
             self.loaded_frame = MpegFrame {
                 id: self.index,
-                mode: self
-                    .mode
-                    .unwrap_or(meta.map_or(FrameMode::PROG, |meta| meta.picture_type)),
-                duration: self
-                    .refresh_rate
-                    .unwrap_or(meta.map_or(Duration::from_nanos(40_000_000), |meta| meta.duration)),
+                mode,
+                duration,
+                display_duration,
+                // A requested frame rate overrides the stream timestamps
+                pts: match (self.refresh_rate, self.frame_pts) {
+                    (None, Some(pts)) => pts,
+                    _ => duration * self.index as u32,
+                },
             };
+            self.last_update = self.schedule();
 
-            // Print the path of the image to load (only in debug mode)
-            #[cfg(debug_assertions)]
-            dbg!(&path);
-
-            // Load the image and convert to RGBA pixels
-            decode(path, &mut self.rgb_image).unwrap();
-
-            let img = &self.rgb_image;
-
-            let mut pixels =
-                Array2::from_shape_vec((img.height(), img.width() * 4), img.get_rgba()).unwrap();
-
-            // [height / 2, width * 4]
-            let mut top_field = pixels.clone();
-            top_field
-                .slice_mut(s![1isize..;2, ..])
-                .assign(&pixels.slice(s![..;2, ..]));
-
-            let mut bot_field = pixels.clone();
-            bot_field
-                .slice_mut(s![..;2, ..])
-                .assign(&pixels.slice(s![1isize..;2, ..]));
-
-            if self.loaded_frame.interlaced() && !self.prev_pixels.is_empty() {
-                let curr_top_field = pixels.slice(s![..;2, ..]);
-                let curr_bot_field = pixels.slice(s![1isize..;2, ..]);
-
-                let prev_top_field = self.prev_pixels.slice(s![..;2, ..]);
-                let prev_bot_field = self.prev_pixels.slice(s![1isize..;2, ..]);
-
-                const BLOCK_SIZE: usize = 8;
-                const CHUNK_SIZE: (usize, usize) = (BLOCK_SIZE / 2, BLOCK_SIZE * 4);
-
-                let error_size = (img.height() / BLOCK_SIZE, img.width() / BLOCK_SIZE);
-
-                let errors_vec = prev_top_field
-                    .exact_chunks(CHUNK_SIZE)
-                    .into_iter()
-                    .zip(curr_top_field.exact_chunks(CHUNK_SIZE))
-                    .map(|(prev, curr)| {
-                        prev.iter()
-                            .zip(curr.iter())
-                            .map(|(prev, curr)| (*prev as f32 - *curr as f32).abs())
-                            .sum::<f32>()
-                            .div(CHUNK_SIZE.0 as f32 * CHUNK_SIZE.1 as f32 * 255f32)
-                    })
-                    .collect::<Vec<f32>>();
-
-                let mut error = Array2::from_shape_vec(error_size, errors_vec).unwrap();
-
-                let errors_vec = prev_bot_field
-                    .exact_chunks(CHUNK_SIZE)
-                    .into_iter()
-                    .zip(curr_bot_field.exact_chunks(CHUNK_SIZE))
-                    .map(|(prev, curr)| {
-                        prev.iter()
-                            .zip(curr.iter())
-                            .map(|(prev, curr)| (*prev as f32 - *curr as f32).abs())
-                            .sum::<f32>()
-                            .div(CHUNK_SIZE.0 as f32 * CHUNK_SIZE.1 as f32 * 255f32)
-                    })
-                    .collect();
-
-                let error_bot = Array2::from_shape_vec(error_size, errors_vec).unwrap();
-
-                error.zip_mut_with(&error_bot, |e_top, e_bot| {
-                    *e_top = e_top.max(*e_bot);
-                });
-
-                error.indexed_iter().for_each(|((i, j), err)| {
-                    // Weave zone if error is low enough
-                    if *err <= self.threshold {
-                        let row_start = j * CHUNK_SIZE.1;
-                        let row_end = (j + 1) * CHUNK_SIZE.1;
-
-                        let line_start = i * CHUNK_SIZE.0;
-                        let line_end = (i + 1) * CHUNK_SIZE.0;
-
-                        let prev_bot =
-                            prev_bot_field.slice(s![line_start..line_end, row_start..row_end]);
-                        let curr_top =
-                            curr_bot_field.slice(s![line_start..line_end, row_start..row_end]);
-
-                        let line_s = i * BLOCK_SIZE;
-                        let line_e = (i + 1) * BLOCK_SIZE;
-
-                        // Weave T(current) + B(previous)
-                        top_field
-                            .slice_mut(s![line_s..line_e;2, row_start..row_end])
-                            .assign(&curr_top);
-                        top_field
-                            .slice_mut(s![(line_s + 1)..line_e;2, row_start..row_end])
-                            .assign(&prev_bot);
-
-                        bot_field
-                            .slice_mut(s![line_s..line_e;2, row_start..row_end])
-                            .assign(&curr_top);
-                        bot_field
-                            .slice_mut(s![(line_s + 1)..line_e;2, row_start..row_end])
-                            .assign(&prev_bot)
-                    }
-                });
+            if self.show_scopes {
+                self.update_scopes();
             }
 
-            // Convert the image to a ColorImage
-            let image = epaint::ColorImage::from_rgba_unmultiplied(
-                [img.width(), img.height()],
-                match self.loaded_frame.mode {
-                    FrameMode::PROG => &pixels,
-                    FrameMode::RFF_TFF => &top_field,
-                    FrameMode::RFF_BFF => &bot_field,
-                    FrameMode::TFF => &top_field,
-                    FrameMode::BFF => &bot_field,
+            // Deinterlace the native YUV frame, RGBA is only needed for display
+            let deinterlacer = if self.loaded_frame.interlaced() {
+                Deinterlacer::Adaptive {
+                    threshold: self.threshold,
                 }
-                .as_standard_layout()
-                .as_slice()
-                .unwrap(),
-            );
+            } else {
+                Deinterlacer::Weave
+            };
+            let first_field = match self.loaded_frame.mode {
+                FrameMode::RFF_BFF | FrameMode::BFF => Field::Bottom,
+                _ => Field::Top,
+            };
 
+            let first = deinterlacer.apply(&self.frame, self.prev_frame.as_ref(), first_field);
+            let first = self.scale_for_display(self.apply_afd(first));
+            let size = [first.width(), first.height()];
+            let image = ColorImage::from_rgba_unmultiplied(size, &first.to_rgba());
             self.texture_1.set(image, Default::default());
 
             if self.loaded_frame.interlaced() {
-                let image = epaint::ColorImage::from_rgba_unmultiplied(
-                    [img.width(), img.height()],
-                    match self.loaded_frame.mode {
-                        FrameMode::RFF_TFF => &bot_field,
-                        FrameMode::RFF_BFF => &top_field,
-                        FrameMode::TFF => &bot_field,
-                        FrameMode::BFF => &top_field,
-                        _ => unreachable!(),
-                    }
-                    .as_standard_layout()
-                    .as_slice()
-                    .unwrap(),
+                let second = deinterlacer.apply(
+                    &self.frame,
+                    self.prev_frame.as_ref(),
+                    first_field.opposite(),
                 );
-
+                let second = self.scale_for_display(self.apply_afd(second));
+                let image = ColorImage::from_rgba_unmultiplied(size, &second.to_rgba());
                 self.texture_2.set(image, Default::default());
             }
 
-            std::mem::swap(&mut pixels, &mut self.prev_pixels);
+            self.prev_frame = Some(self.frame.clone());
 
             if self.state == AppState::Play {
                 self.incr_index();
@@ -355,6 +800,19 @@ impl eframe::App for MyApp {
             }
         }
 
+        // The audio follows the time of the frame on screen
+        if let Some(audio) = &self.audio {
+            let playing = self.state == AppState::Play;
+            let elapsed = if playing {
+                self.last_update
+                    .elapsed()
+                    .min(self.loaded_frame.display_duration)
+            } else {
+                Duration::ZERO
+            };
+            audio.sync(self.loaded_frame.pts + elapsed, playing);
+        }
+
         match self.state {
             // Request a repaint after the refresh rate (takes into account the time it took to load the image)
             AppState::Play => {
@@ -366,7 +824,7 @@ impl eframe::App for MyApp {
                         .unwrap_or_default()
                 } else {
                     self.loaded_frame
-                        .duration
+                        .display_duration
                         .checked_sub(self.last_update.elapsed())
                         .unwrap_or_default()
                 };
@@ -376,6 +834,99 @@ impl eframe::App for MyApp {
             _ => (),
         }
 
+        if self.show_scopes {
+            egui::SidePanel::right("scopes").show(ctx, |ui| {
+                let width = ui.available_width();
+
+                ui.heading("Waveform");
+                ui.image(&self.waveform_texture, [width, width * 0.75]);
+
+                ui.heading("Vectorscope");
+                let vectorscope = ui.image(&self.vectorscope_texture, [width, width]);
+                let scale = vectorscope.rect.width() / scopes::LEVELS as f32;
+                for bar in scopes::bar_targets_75() {
+                    let position = vectorscope.rect.min
+                        + egui::vec2(
+                            bar.cb as f32 * scale + 6.0,
+                            (scopes::LEVELS - 1 - bar.cr as usize) as f32 * scale - 6.0,
+                        );
+                    ui.painter().text(
+                        position,
+                        egui::Align2::LEFT_BOTTOM,
+                        bar.name,
+                        egui::FontId::monospace(10.0),
+                        BAR_TARGET_COLOR,
+                    );
+                }
+
+                ui.heading("Histogram");
+                if let Some(histogram) = &self.histogram {
+                    let channels = [
+                        (&histogram.luma, Color32::WHITE, "Y"),
+                        (&histogram.red, Color32::RED, "R"),
+                        (&histogram.green, Color32::GREEN, "G"),
+                        (&histogram.blue, Color32::LIGHT_BLUE, "B"),
+                    ];
+                    let max = histogram.max().max(1) as f64;
+
+                    Plot::new("histogram")
+                        .height(width * 0.6)
+                        .allow_drag(false)
+                        .allow_zoom(false)
+                        .allow_scroll(false)
+                        .include_y(0.0)
+                        .include_y(1.0)
+                        .show(ui, |plot_ui| {
+                            for (channel, color, name) in channels {
+                                let points = channel
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(level, count)| [level as f64, *count as f64 / max])
+                                    .collect::<PlotPoints>();
+                                plot_ui.line(Line::new(points).color(color).name(name));
+                            }
+                        });
+                }
+            });
+        }
+
+        if self.show_bitrate {
+            egui::TopBottomPanel::bottom("bitrate")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    let current = self.loaded_frame.id as f64;
+                    let highlight = self
+                        .bitrates
+                        .iter()
+                        .find(|[index, _]| *index == current)
+                        .copied();
+
+                    Plot::new("bitrate")
+                        .height(ui.available_height().max(120.0))
+                        .include_y(0.0)
+                        .x_axis_formatter(|index, _| format!("{}", index as usize))
+                        .label_formatter(|_, point| {
+                            format!("frame {}\n{:.0} kbit/s", point.x as usize, point.y)
+                        })
+                        .show(ui, |plot_ui| {
+                            let points = PlotPoints::new(self.bitrates.clone());
+                            plot_ui
+                                .line(Line::new(points).color(Color32::LIGHT_BLUE).name("kbit/s"));
+
+                            plot_ui.vline(VLine::new(current).color(Color32::YELLOW));
+                            if let Some(point) = highlight {
+                                plot_ui.points(
+                                    Points::new(vec![point]).radius(4.0).color(Color32::YELLOW),
+                                );
+                            }
+                        });
+                });
+        }
+
+        if self.events.is_some() {
+            egui::TopBottomPanel::bottom("timeline").show(ctx, |ui| self.show_timeline(ui));
+        }
+
         // Display the image
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
@@ -389,11 +940,10 @@ impl eframe::App for MyApp {
 
                 let next = ui.button("Next");
 
-                ui.add(egui::Label::new(format!(
-                    "Frame {}/{}",
-                    (self.index + 1) % nb_img,
-                    nb_img,
-                )));
+                ui.add(egui::Label::new(match nb_img {
+                    Some(nb_img) => format!("Frame {}/{}", (self.index + 1) % nb_img, nb_img),
+                    None => format!("Frame {}/?", self.index + 1),
+                }));
 
                 if self.state == AppState::Play {
                     ui.add(egui::Label::new(format!("{:.2} fps", self.last_fps,)));
@@ -404,7 +954,51 @@ impl eframe::App for MyApp {
                     self.loaded_frame.mode
                 )));
 
+                if ui.checkbox(&mut self.show_scopes, "Scopes").changed() && self.show_scopes {
+                    self.update_scopes();
+                }
+
+                if !self.bitrates.is_empty() {
+                    ui.checkbox(&mut self.show_bitrate, "Bitrate");
+                }
+
+                if !self.captions.is_empty() {
+                    ui.checkbox(&mut self.show_captions, "Captions");
+                }
+
+                if self.has_afd {
+                    let afd_mode = self.afd_mode;
+                    egui::ComboBox::from_label("AFD")
+                        .selected_text(self.afd_mode.to_string())
+                        .show_ui(ui, |ui| {
+                            for mode in AfdMode::ALL {
+                                ui.selectable_value(&mut self.afd_mode, mode, mode.to_string());
+                            }
+                        });
+                    if self.afd_mode != afd_mode {
+                        self.reload();
+                    }
+                }
+
+                let (zoom, kernel) = (self.zoom, self.kernel);
+                ui.add(
+                    egui::Slider::new(&mut self.zoom, 0.25..=4.0)
+                        .logarithmic(true)
+                        .text("Zoom"),
+                );
+                egui::ComboBox::from_label("Scaling")
+                    .selected_text(self.kernel.to_string())
+                    .show_ui(ui, |ui| {
+                        for kernel in Kernel::ALL {
+                            ui.selectable_value(&mut self.kernel, kernel, kernel.to_string());
+                        }
+                    });
+                if (self.zoom, self.kernel) != (zoom, kernel) {
+                    self.reload();
+                }
+
                 if play_pause.clicked() {
+                    self.playback_origin = None;
                     self.state = match self.state {
                         AppState::Play => AppState::Pause,
                         AppState::Pause => AppState::Play,
@@ -444,14 +1038,22 @@ impl eframe::App for MyApp {
                 }
             });
 
-            if !self.loaded_frame.interlaced() {
-                ui.image(&self.texture_1, self.texture_1.size_vec2());
+            if let Some(err) = &self.load_error {
+                ui.colored_label(Color32::RED, err.to_string());
+            }
+
+            self.show_frame_metadata(ui);
+
+            let image = if !self.loaded_frame.interlaced()
+                || self.field_display_idx < self.loaded_frame.second_field_display_idx()
+            {
+                ui.image(&self.texture_1, self.texture_1.size_vec2())
             } else {
-                if self.field_display_idx < self.loaded_frame.second_field_display_idx() {
-                    ui.image(&self.texture_1, self.texture_1.size_vec2());
-                } else {
-                    ui.image(&self.texture_2, self.texture_2.size_vec2());
-                }
+                ui.image(&self.texture_2, self.texture_2.size_vec2())
+            };
+
+            if self.show_captions {
+                self.paint_caption(ui, image.rect);
             }
         });
     }
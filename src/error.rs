@@ -0,0 +1,134 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Errors returned by the library when reading frames and stream metadata.
+#[derive(Debug)]
+pub enum Error {
+    /// A file or directory could not be opened or read.
+    Io { path: PathBuf, source: io::Error },
+    /// The PGM header is malformed, `offset` is the byte at which parsing failed.
+    PgmHeader {
+        path: PathBuf,
+        offset: u64,
+        reason: &'static str,
+    },
+    /// The file size does not match the size announced by the Netpbm header.
+    PgmSize {
+        path: PathBuf,
+        expected: u64,
+        actual: u64,
+    },
+    /// A line of `tvid.log` is malformed, `line` is 1-based.
+    LogLine {
+        path: PathBuf,
+        line: usize,
+        reason: &'static str,
+    },
+    /// The number of frames does not match the number of pictures in `tvid.log`.
+    FrameCount { frames: usize, pictures: usize },
+    /// The frame source does not contain any frame.
+    NoFrames,
+    /// Two frames compared with each other do not have the same dimensions.
+    FrameSize {
+        index: usize,
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+    /// The transport stream does not carry what was asked for.
+    TransportStream { path: PathBuf, reason: &'static str },
+    /// An image could not be encoded.
+    Encode { path: PathBuf, reason: String },
+    /// Writing more would exceed the largest file the format can describe.
+    FileTooLarge { path: PathBuf, limit: u64 },
+    /// Audio could not be played.
+    AudioOutput { reason: String },
+}
+
+impl Error {
+    pub(crate) fn io(path: &Path, source: io::Error) -> Self {
+        Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub(crate) fn png(path: &Path, error: png::EncodingError) -> Self {
+        match error {
+            png::EncodingError::IoError(source) => Error::io(path, source),
+            error => Error::Encode {
+                path: path.to_path_buf(),
+                reason: error.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::PgmHeader {
+                path,
+                offset,
+                reason,
+            } => write!(
+                f,
+                "{}: malformed PGM header at byte {}: {}",
+                path.display(),
+                offset,
+                reason
+            ),
+            Error::PgmSize {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: header announces {} bytes but the file has {}",
+                path.display(),
+                expected,
+                actual
+            ),
+            Error::LogLine { path, line, reason } => {
+                write!(f, "{}:{}: {}", path.display(), line, reason)
+            }
+            Error::FrameCount { frames, pictures } => write!(
+                f,
+                "found {} frames but `tvid.log` describes {} pictures",
+                frames, pictures
+            ),
+            Error::NoFrames => write!(f, "the frame source does not contain any frame"),
+            Error::FrameSize {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "frame {} is {}x{} but its reference is {}x{}",
+                index, actual.0, actual.1, expected.0, expected.1
+            ),
+            Error::TransportStream { path, reason } => write!(f, "{}: {}", path.display(), reason),
+            Error::Encode { path, reason } => {
+                write!(f, "{}: cannot encode the image: {}", path.display(), reason)
+            }
+            Error::FileTooLarge { path, limit } => write!(
+                f,
+                "{}: the file would exceed {} bytes, the limit of its format",
+                path.display(),
+                limit
+            ),
+            Error::AudioOutput { reason } => write!(f, "audio output: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
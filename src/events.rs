@@ -0,0 +1,340 @@
+// Black frame, freeze frame and scene cut detection
+//
+// Frames are compared to the previous one with the block errors of the adaptive deinterlacer:
+// the mean absolute luma difference of every 8x8 block, the largest of its two fields. A frame
+// is frozen when no block changed more than a threshold, and starts a new scene when the mean
+// of its block errors exceeds another. Black frames are counted on their luma alone.
+
+use std::{
+    fmt::{self, Write},
+    time::Duration,
+};
+
+use crate::{
+    deinterlace::block_errors,
+    source::FrameSource,
+    timing::{fields_duration, DEFAULT_FRAME_DURATION},
+    yuv::{YuvFrame, BLACK_LEVEL},
+    Error,
+};
+
+/// Thresholds of the detector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Luma up to which a sample is black, black being 16
+    pub black_level: u8,
+    /// Part of the samples of a frame that must be black for the frame to be black
+    pub black_ratio: f32,
+    /// Block error, from 0 to 1, up to which nothing moved between two frames
+    pub freeze: f32,
+    /// Mean block error, from 0 to 1, from which a frame starts a new scene
+    pub scene: f32,
+    /// Shortest run of black frames reported
+    pub min_black: Duration,
+    /// Shortest run of frozen frames reported
+    pub min_freeze: Duration,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            black_level: BLACK_LEVEL,
+            black_ratio: 0.98,
+            freeze: 0.01,
+            scene: 0.12,
+            min_black: Duration::ZERO,
+            min_freeze: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Black,
+    Freeze,
+    SceneCut,
+}
+
+impl EventKind {
+    fn name(&self) -> &'static str {
+        match self {
+            EventKind::Black => "black",
+            EventKind::Freeze => "freeze",
+            EventKind::SceneCut => "scene_cut",
+        }
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EventKind::Black => "black",
+            EventKind::Freeze => "freeze",
+            EventKind::SceneCut => "scene cut",
+        })
+    }
+}
+
+/// Run of black or frozen frames, or the first frame of a scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    /// Index of the first frame
+    pub frame: usize,
+    /// Number of frames, 1 for scene cuts
+    pub frames: usize,
+    /// Presentation time of the first frame
+    pub start: Duration,
+    /// Until the end of the last frame
+    pub duration: Duration,
+    /// Smallest black ratio of black frames, largest block error of frozen frames, mean block
+    /// error of scene cuts
+    pub score: f32,
+}
+
+impl Event {
+    /// Whether the event covers frame `index`
+    pub fn contains(&self, index: usize) -> bool {
+        (self.frame..self.frame + self.frames).contains(&index)
+    }
+}
+
+/// `HH:MM:SS.mmm`
+pub(crate) fn timestamp(t: Duration) -> String {
+    let ms = t.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frame {:>6}  {}  {:<9}",
+            self.frame,
+            timestamp(self.start),
+            self.kind.to_string()
+        )?;
+        match self.kind {
+            EventKind::SceneCut => write!(f, "  score {:.3}", self.score),
+            _ => write!(
+                f,
+                "  {} frames, {:.3} s, score {:.3}",
+                self.frames,
+                self.duration.as_secs_f64(),
+                self.score
+            ),
+        }
+    }
+}
+
+/// Events as a JSON array
+pub fn events_json(events: &[Event]) -> String {
+    let mut json = String::from("[");
+    for (i, event) in events.iter().enumerate() {
+        write!(
+            json,
+            "{}\n    {{\"kind\": \"{}\", \"frame\": {}, \"frames\": {}, \"start\": {:.3}, \
+             \"duration\": {:.3}, \"score\": {:.4}}}",
+            if i == 0 { "" } else { "," },
+            event.kind.name(),
+            event.frame,
+            event.frames,
+            event.start.as_secs_f64(),
+            event.duration.as_secs_f64(),
+            event.score
+        )
+        .unwrap();
+    }
+    json.push_str(if events.is_empty() { "]" } else { "\n  ]" });
+    json
+}
+
+/// Black or frozen frames so far
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    frame: usize,
+    start: Duration,
+    score: f32,
+}
+
+/// Finds events in frames pushed in display order.
+#[derive(Debug, Clone)]
+pub struct EventDetector {
+    thresholds: Thresholds,
+    prev: Option<YuvFrame>,
+    /// Presentation time of the previous frame
+    prev_pts: Duration,
+    /// Index of the next frame
+    index: usize,
+    /// End of the last frame
+    end: Duration,
+    black: Option<Run>,
+    freeze: Option<Run>,
+    events: Vec<Event>,
+}
+
+impl EventDetector {
+    pub fn new(thresholds: Thresholds) -> Self {
+        Self {
+            thresholds,
+            prev: None,
+            prev_pts: Duration::ZERO,
+            index: 0,
+            end: Duration::ZERO,
+            black: None,
+            freeze: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// Part of the luma samples of a frame that are black
+    pub fn black_ratio(&self, frame: &YuvFrame) -> f32 {
+        let samples = frame.width() * frame.height();
+        if samples == 0 {
+            return 1.0;
+        }
+
+        let level = self.thresholds.black_level;
+        let black: usize = frame
+            .y
+            .rows()
+            .map(|row| row.iter().filter(|sample| **sample <= level).count())
+            .sum();
+        black as f32 / samples as f32
+    }
+
+    /// Adds the next frame, presented at `pts` for `duration`
+    pub fn push(&mut self, frame: &YuvFrame, pts: Duration, duration: Duration) {
+        let index = self.index;
+        let black_ratio = self.black_ratio(frame);
+        let black = black_ratio >= self.thresholds.black_ratio;
+
+        // Block errors of frames of a new size are unknown
+        let errors = self
+            .prev
+            .as_ref()
+            .filter(|prev| {
+                (prev.width(), prev.height(), prev.chroma_format)
+                    == (frame.width(), frame.height(), frame.chroma_format)
+            })
+            .map(|prev| block_errors(prev, frame))
+            .filter(|errors| !errors.is_empty());
+
+        if black {
+            let run = self.black.get_or_insert(Run {
+                frame: index,
+                start: pts,
+                score: 1.0,
+            });
+            run.score = run.score.min(black_ratio);
+        } else {
+            self.end_black(pts);
+        }
+
+        // Still black frames are only black
+        let frozen = match &errors {
+            Some(errors) if !black => {
+                let max = errors.iter().copied().fold(0.0, f32::max);
+                (max <= self.thresholds.freeze).then_some(max)
+            }
+            _ => None,
+        };
+        match frozen {
+            Some(error) => {
+                // Frozen since the previous frame
+                let run = self.freeze.get_or_insert(Run {
+                    frame: index - 1,
+                    start: self.prev_pts,
+                    score: 0.0,
+                });
+                run.score = run.score.max(error);
+            }
+            None => self.end_freeze(pts),
+        }
+
+        if let Some(errors) = &errors {
+            let mean = errors.mean().unwrap_or(0.0);
+            if mean >= self.thresholds.scene {
+                self.events.push(Event {
+                    kind: EventKind::SceneCut,
+                    frame: index,
+                    frames: 1,
+                    start: pts,
+                    duration,
+                    score: mean,
+                });
+            }
+        }
+
+        self.prev = Some(frame.clone());
+        self.prev_pts = pts;
+        self.index += 1;
+        self.end = pts + duration;
+    }
+
+    fn end_black(&mut self, end: Duration) {
+        if let Some(run) = self.black.take() {
+            self.end_run(EventKind::Black, run, end, self.thresholds.min_black);
+        }
+    }
+
+    fn end_freeze(&mut self, end: Duration) {
+        if let Some(run) = self.freeze.take() {
+            self.end_run(EventKind::Freeze, run, end, self.thresholds.min_freeze);
+        }
+    }
+
+    fn end_run(&mut self, kind: EventKind, run: Run, end: Duration, min: Duration) {
+        let duration = end.saturating_sub(run.start);
+        if duration >= min {
+            self.events.push(Event {
+                kind,
+                frame: run.frame,
+                frames: self.index - run.frame,
+                start: run.start,
+                duration,
+                score: run.score,
+            });
+        }
+    }
+
+    /// Ends the runs of the last frames, then returns all events by first frame
+    pub fn finish(mut self) -> Vec<Event> {
+        self.end_black(self.end);
+        self.end_freeze(self.end);
+
+        self.events
+            .sort_by_key(|event| (event.frame, event.kind.name()));
+        self.events
+    }
+}
+
+/// Reads a whole source to find its events, then rewinds it
+///
+/// Frames without a presentation time follow the previous one. Frames last as long as their
+/// picture is displayed, repeated fields included, or 40 ms without stream metadata.
+pub fn detect_events(
+    source: &mut dyn FrameSource,
+    thresholds: Thresholds,
+) -> Result<Vec<Event>, Error> {
+    let mut detector = EventDetector::new(thresholds);
+    while let Some(frame) = source.next_frame()? {
+        let duration = frame
+            .picture
+            .as_ref()
+            .map_or(DEFAULT_FRAME_DURATION, |picture| {
+                fields_duration(picture.frame_period as u64, picture.displayed_fields())
+            });
+        let pts = frame.pts.unwrap_or(detector.end);
+        detector.push(&frame.frame, pts, duration);
+    }
+    source.seek(0)?;
+
+    Ok(detector.finish())
+}
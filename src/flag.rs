@@ -1,4 +1,4 @@
-use std::slice::Iter;
+use std::{slice::Iter, str::FromStr};
 
 #[derive(PartialEq)]
 enum Flag {
@@ -9,7 +9,7 @@ enum Flag {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
-#[allow(non_camel_case_types)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 pub enum FrameMode {
     PROG,
     RFF_TFF,
@@ -48,3 +48,60 @@ impl<'a> From<Iter<'_, &'a str>> for FrameMode {
         }
     }
 }
+
+/// Picture coding type, as logged after the field flags of a PIC line.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+pub enum CodingType {
+    I,
+    P,
+    B,
+    D,
+}
+
+impl CodingType {
+    pub fn from_flags(mut it: Iter<&str>) -> Option<Self> {
+        it.find_map(|w| match *w {
+            "I" => Some(CodingType::I),
+            "P" => Some(CodingType::P),
+            "B" => Some(CodingType::B),
+            "D" => Some(CodingType::D),
+            _ => None,
+        })
+    }
+}
+
+/// Chroma subsampling of a sequence, as logged at the end of a SEQ line.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+pub enum ChromaFormat {
+    Yuv420,
+    Yuv422,
+    Yuv444,
+}
+
+impl ChromaFormat {
+    pub fn from_flags(mut it: Iter<&str>) -> Option<Self> {
+        it.find_map(|w| w.parse().ok())
+    }
+
+    /// Horizontal and vertical chroma subsampling, as right shifts of the luma dimensions
+    pub fn subsampling(&self) -> (usize, usize) {
+        match self {
+            ChromaFormat::Yuv420 => (1, 1),
+            ChromaFormat::Yuv422 => (1, 0),
+            ChromaFormat::Yuv444 => (0, 0),
+        }
+    }
+}
+
+impl FromStr for ChromaFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "4:2:0" | "420" => Ok(ChromaFormat::Yuv420),
+            "4:2:2" | "422" => Ok(ChromaFormat::Yuv422),
+            "4:4:4" | "444" => Ok(ChromaFormat::Yuv444),
+            _ => Err(format!("unknown chroma format `{}`", s)),
+        }
+    }
+}
@@ -0,0 +1,292 @@
+// Animated GIF89a files
+//
+// Header, logical screen with a global color table of 256 entries and a NETSCAPE2.0
+// extension to loop forever, then for every frame a graphic control extension giving its
+// delay in hundredths of a second and an image of palette indices, LZW compressed into
+// sub-blocks of at most 255 bytes. Colors are mapped to the nearest palette entry, cached
+// for every color of 5 bits per channel.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    image::{Rgb, RgbImage},
+    Error,
+};
+
+/// Entries of the color table
+pub const PALETTE_SIZE: usize = 256;
+
+/// Bits of the palette indices, the initial LZW code size
+const MIN_CODE_SIZE: u8 = 8;
+/// Codes are at most 12 bits
+const MAX_CODES: u16 = 4096;
+
+/// Box of colors split by the median cut
+struct ColorBox {
+    colors: Vec<Rgb>,
+}
+
+impl ColorBox {
+    /// Channel of the widest range and that range
+    fn widest_channel(&self) -> (usize, u8) {
+        (0..3)
+            .map(|channel| {
+                let values = self
+                    .colors
+                    .iter()
+                    .map(|color| channel_value(*color, channel));
+                let min = values.clone().min().unwrap_or(0);
+                let max = values.max().unwrap_or(0);
+                (channel, max - min)
+            })
+            .max_by_key(|(_, range)| *range)
+            .unwrap_or((0, 0))
+    }
+
+    fn mean(&self) -> Rgb {
+        let count = self.colors.len().max(1) as u64;
+        let sum = |channel| {
+            let total: u64 = self
+                .colors
+                .iter()
+                .map(|color| u64::from(channel_value(*color, channel)))
+                .sum();
+            ((total + count / 2) / count) as u8
+        };
+        Rgb::new(sum(0), sum(1), sum(2))
+    }
+}
+
+fn channel_value(color: Rgb, channel: usize) -> u8 {
+    match channel {
+        0 => color.r,
+        1 => color.g,
+        _ => color.b,
+    }
+}
+
+/// Palette of at most `size` colors for `samples`, splitting the box of colors with the widest
+/// channel range at its median until there are `size` boxes, each giving its mean color
+pub fn median_cut(samples: &[Rgb], size: usize) -> Vec<Rgb> {
+    if samples.is_empty() || size == 0 {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![ColorBox {
+        colors: samples.to_vec(),
+    }];
+    while boxes.len() < size {
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, color_box)| (index, color_box.widest_channel()))
+            .filter(|(_, (_, range))| *range > 0)
+            .max_by_key(|(_, (_, range))| *range)
+            .map(|(index, (channel, _))| (index, channel))
+        else {
+            // Every box holds a single color
+            break;
+        };
+
+        let mut colors = std::mem::take(&mut boxes[index].colors);
+        colors.sort_unstable_by_key(|color| channel_value(*color, channel));
+        let upper = colors.split_off(colors.len() / 2);
+        boxes[index].colors = colors;
+        boxes.push(ColorBox { colors: upper });
+    }
+
+    boxes.iter().map(ColorBox::mean).collect()
+}
+
+/// Packs codes of variable size, least significant bit first
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= u32::from(code) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// LZW compression of palette indices, as GIF image data
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << MIN_CODE_SIZE;
+    let end = clear + 1;
+
+    let mut out = BitWriter::default();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut size = MIN_CODE_SIZE + 1;
+    out.write(clear, size);
+
+    let mut prefix: Option<u16> = None;
+    for &index in indices {
+        let Some(code) = prefix else {
+            prefix = Some(u16::from(index));
+            continue;
+        };
+        if let Some(&longer) = table.get(&(code, index)) {
+            prefix = Some(longer);
+            continue;
+        }
+
+        out.write(code, size);
+        if next == MAX_CODES {
+            // The table is full, start again
+            out.write(clear, size);
+            table.clear();
+            next = end + 1;
+            size = MIN_CODE_SIZE + 1;
+        } else {
+            // Decoders widen codes one entry later, as they add entries after reading a code
+            if next >= 1 << size {
+                size += 1;
+            }
+            table.insert((code, index), next);
+            next += 1;
+        }
+        prefix = Some(u16::from(index));
+    }
+    if let Some(code) = prefix {
+        out.write(code, size);
+    }
+    out.write(end, size);
+
+    out.finish()
+}
+
+/// Writes frames to an animated GIF file, looping forever.
+pub struct GifWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    palette: Vec<Rgb>,
+    /// Palette index of every color of 5 bits per channel, `u16::MAX` until needed
+    cache: Vec<u16>,
+}
+
+impl GifWriter {
+    /// Creates a `width`×`height` animation, `palette` having at most 256 colors
+    pub fn create(
+        path: &Path,
+        width: u16,
+        height: u16,
+        mut palette: Vec<Rgb>,
+    ) -> Result<Self, Error> {
+        palette.truncate(PALETTE_SIZE);
+        if palette.is_empty() {
+            palette.push(Rgb::new(0, 0, 0));
+        }
+
+        let file = File::create(path).map_err(|e| Error::io(path, e))?;
+        let mut writer = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            palette,
+            cache: vec![u16::MAX; 1 << 15],
+        };
+
+        let mut header = Vec::with_capacity(13 + 3 * PALETTE_SIZE + 19);
+        header.extend(b"GIF89a");
+        header.extend(width.to_le_bytes());
+        header.extend(height.to_le_bytes());
+        // Global color table of 2^(7 + 1) entries, 8 bits per channel
+        header.push(0xf7);
+        // Background color and pixel aspect ratio
+        header.extend([0, 0]);
+        for i in 0..PALETTE_SIZE {
+            let color = writer.palette.get(i).copied().unwrap_or(Rgb::new(0, 0, 0));
+            header.extend([color.r, color.g, color.b]);
+        }
+        // Loop forever
+        header.extend([0x21, 0xff, 0x0b]);
+        header.extend(b"NETSCAPE2.0");
+        header.extend([0x03, 0x01, 0x00, 0x00, 0x00]);
+        writer.write(&header)?;
+
+        Ok(writer)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.writer
+            .write_all(bytes)
+            .map_err(|e| Error::io(&self.path, e))
+    }
+
+    /// Palette index of the color nearest to `color`
+    fn index(&mut self, color: Rgb) -> u8 {
+        let key = usize::from(color.r >> 3) << 10
+            | usize::from(color.g >> 3) << 5
+            | usize::from(color.b >> 3);
+        if self.cache[key] == u16::MAX {
+            // Center of the cached cell
+            let center = [color.r | 4, color.g | 4, color.b | 4].map(i32::from);
+            let nearest = self
+                .palette
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, entry)| {
+                    let entry = [entry.r, entry.g, entry.b].map(i32::from);
+                    (0..3).map(|i| (entry[i] - center[i]).pow(2)).sum::<i32>()
+                })
+                .map_or(0, |(index, _)| index);
+            self.cache[key] = nearest as u16;
+        }
+        self.cache[key] as u8
+    }
+
+    /// Adds a frame shown for `delay` hundredths of a second
+    pub fn write_frame(&mut self, image: &RgbImage, delay: u16) -> Result<(), Error> {
+        let indices: Vec<u8> = image
+            .get_data()
+            .iter()
+            .map(|color| self.index(*color))
+            .collect();
+
+        let mut frame = Vec::new();
+        // Graphic control extension, the frame stays until replaced
+        frame.extend([0x21, 0xf9, 0x04, 0x04]);
+        frame.extend(delay.to_le_bytes());
+        frame.extend([0x00, 0x00]);
+        // Image descriptor at the origin, without local color table nor interlacing
+        frame.extend([0x2c, 0, 0, 0, 0]);
+        frame.extend((image.width() as u16).to_le_bytes());
+        frame.extend((image.height() as u16).to_le_bytes());
+        frame.push(0x00);
+
+        frame.push(MIN_CODE_SIZE);
+        for block in lzw_encode(&indices).chunks(255) {
+            frame.push(block.len() as u8);
+            frame.extend(block);
+        }
+        frame.push(0x00);
+
+        self.write(&frame)
+    }
+
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.write(&[0x3b])?;
+        self.writer.flush().map_err(|e| Error::io(&self.path, e))
+    }
+}
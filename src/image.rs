@@ -1,7 +1,13 @@
-use std::ops::{Index, IndexMut};
+use std::{
+    fs::File,
+    io::BufWriter,
+    ops::{Index, IndexMut},
+    path::Path,
+};
 
-#[repr(C)]
-#[derive(Clone)]
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rgb {
     pub r: u8,
     pub g: u8,
@@ -15,14 +21,14 @@ impl Rgb {
 }
 
 pub struct RgbImage {
-    data: Vec<(u8, u8, u8)>,
+    data: Vec<Rgb>,
     width: usize,
     height: usize,
 }
 
 impl RgbImage {
     pub fn with_capacity(width: usize, height: usize) -> Self {
-        let data = vec![(0, 0, 0); width * height];
+        let data = vec![Rgb::new(0, 0, 0); width * height];
 
         Self {
             data,
@@ -46,7 +52,7 @@ impl RgbImage {
 
         // # Body
         let mut column = 0;
-        for (r, g, b) in &self.data {
+        for Rgb { r, g, b } in &self.data {
             let str = format!("{} {} {} ", r, g, b);
             if column + str.len() > 70 {
                 s.push('\n');
@@ -60,18 +66,45 @@ impl RgbImage {
         s
     }
 
+    /// Writes the image as an 8-bit RGB PNG
+    pub fn write_png(&self, path: &Path) -> Result<(), Error> {
+        let file = File::create(path).map_err(|e| Error::io(path, e))?;
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(file), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.get_rgb()))
+            .map_err(|e| Error::png(path, e))
+    }
+
+    /// Writes the image as PPM if the extension of `path` is `.ppm`, as PNG otherwise
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let ppm = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("ppm"));
+
+        if ppm {
+            std::fs::write(path, self.to_ppm()).map_err(|e| Error::io(path, e))
+        } else {
+            self.write_png(path)
+        }
+    }
+
     pub fn get_rgb(&self) -> Vec<u8> {
-        self.data.iter().flat_map(|p| [p.0, p.1, p.2]).collect()
+        self.data.iter().flat_map(|p| [p.r, p.g, p.b]).collect()
     }
 
     pub fn get_rgba(&self) -> Vec<u8> {
         self.data
             .iter()
-            .flat_map(|p| [p.0, p.1, p.2, 255])
+            .flat_map(|p| [p.r, p.g, p.b, 255])
             .collect()
     }
 
-    pub fn get_data(&self) -> &Vec<(u8, u8, u8)> {
+    pub fn get_data(&self) -> &[Rgb] {
         &self.data
     }
 
@@ -86,6 +119,10 @@ impl RgbImage {
     pub fn len(&self) -> usize {
         self.data.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
 }
 
 impl Index<usize> for RgbImage {
@@ -93,20 +130,13 @@ impl Index<usize> for RgbImage {
 
     fn index(&self, index: usize) -> &Self::Output {
         let start = index * self.width;
-        unsafe {
-            std::slice::from_raw_parts(self.data.as_ptr().add(start) as *const Rgb, self.width)
-        }
+        &self.data[start..start + self.width]
     }
 }
 
 impl IndexMut<usize> for RgbImage {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
         let start = index * self.width;
-        unsafe {
-            std::slice::from_raw_parts_mut(
-                self.data.as_mut_ptr().add(start) as *mut Rgb,
-                self.width,
-            )
-        }
+        &mut self.data[start..start + self.width]
     }
 }
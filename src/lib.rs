@@ -1,47 +1,130 @@
+mod ac3;
+mod afd;
+mod analyze;
+mod aspect;
+mod audio;
+mod captions;
+mod clip;
+mod contactsheet;
+mod crop;
+mod deinterlace;
 mod display;
+mod error;
+mod events;
 mod flag;
+mod gif;
 mod image;
 mod metadata_parser;
+mod metrics;
+mod mp2;
+mod netpbm;
 mod pgm_parser;
+mod playback;
+mod rawyuv;
+mod scale;
+mod scopes;
+mod source;
+mod timing;
+mod ts;
+mod wav;
+mod yuv;
 
 use std::{fs, path::PathBuf};
 
 use regex::Regex;
 
-pub use crate::metadata_parser::meta_decode;
-pub use crate::pgm_parser::decode;
+pub use crate::ac3::{Ac3Frame, Ac3Header, Ac3Parser, AC3_SAMPLES_PER_FRAME};
+pub use crate::afd::{ActiveArea, ActiveFormat, AfdMode, BarData};
+pub use crate::analyze::{
+    analyze, Anomaly, AnomalyKind, Cadence, CadenceKind, GopReport, PictureReport, StreamReport,
+    TypeCounts,
+};
+pub use crate::aspect::{to_square_pixels, AspectRatio};
+pub use crate::audio::{decode_audio, AudioReport, AudioSink, PcmBuffer};
+pub use crate::captions::{
+    a53_cc_data, to_scc, to_srt, Caption, CaptionChannel, CaptionDecoder, CcData,
+};
+pub use crate::clip::{read_clip, write_apng, write_gif, ClipFrame, CLIP_CLOCK_HZ};
+pub use crate::contactsheet::{contact_sheet, evenly_spaced, read_thumbnail, Thumbnail};
+pub use crate::crop::{
+    detect_borders, detect_source_borders, Crop, CropRect, CropSource, DEFAULT_DETECT_FRAMES,
+};
+pub use crate::deinterlace::Deinterlacer;
+pub use crate::error::Error;
+pub use crate::events::{detect_events, events_json, Event, EventDetector, EventKind, Thresholds};
+pub use crate::flag::{ChromaFormat, CodingType, FrameMode};
+pub use crate::gif::{median_cut, GifWriter, PALETTE_SIZE};
+pub use crate::metadata_parser::{
+    meta_decode, read_log, user_data_chunks, GopEntry, LogEntry, Picture, PictureEntry,
+    SequenceEntry, UserDataEntry,
+};
+pub use crate::metrics::{
+    first_mismatch, max_abs_error, ms_ssim, mse, psnr, ssim, FrameComparison, Quality,
+    MACROBLOCK_SIZE,
+};
+pub use crate::mp2::{AudioFrame, AudioHeader, ChannelMode, Mp2Decoder, SAMPLES_PER_FRAME};
+pub use crate::netpbm::{read_netpbm, Netpbm, NetpbmFormat};
+pub use crate::pgm_parser::{
+    decode, guess_chroma_format, read_frame, read_frame_guessing_chroma, write_frame,
+};
+pub use crate::playback::{AudioPlayback, PlaybackState};
+pub use crate::rawyuv::{PixelFormat, RawYuvSource, RawYuvWriter};
+pub use crate::scale::{resize, scale_plane, Kernel};
+pub use crate::source::{FrameSource, PgmSource, SourceFrame};
+pub use crate::timing::{
+    bitrate, displayed_fields, duration_from_27mhz, duration_from_90khz, fields_duration,
+    FrameRate, PTS_CLOCK_HZ, SYSTEM_CLOCK_HZ,
+};
+pub use crate::ts::{
+    pts_delta, Descriptor, ElementaryStream, PesPacket, Program, StreamKind, TsDemuxer, PACKET_SIZE,
+};
+pub use crate::wav::WavWriter;
+pub use crate::yuv::{Field, Plane, YuvFrame};
 
 pub use display::MyApp;
-pub use image::RgbImage;
+pub use image::{Rgb, RgbImage};
 
-pub fn read_files(dir: &String) -> Vec<PathBuf> {
-    // Retrieve image paths from directory
-    let mut files = fs::read_dir(dir)
-        .unwrap()
-        .map(|res| res.map(|e| e.path()))
-        .filter(|path| path.as_ref().unwrap().extension() == Some("pgm".as_ref()))
-        .collect::<Result<Vec<_>, _>>()
-        .unwrap();
+/// Numbered `.pgm` frames of `dir` in order, failing with `Error::NoFrames` if there are none
+pub fn read_files(dir: &str) -> Result<Vec<PathBuf>, Error> {
+    let dir_path = PathBuf::from(dir);
 
     // Regex to extract the number of the image
-    let basename_regex = Regex::new(r"(\d+).pgm").unwrap();
+    let basename_regex = Regex::new(r"^(\d+)\.pgm$").unwrap();
 
-    // Files were sorted by name, we want to sort them by number
-    files.sort_by(|a, b| {
-        // Converts PathBuf to str
-        let a = a.to_str().unwrap();
-        let b = b.to_str().unwrap();
+    // Retrieve numbered image paths from directory
+    let mut files = Vec::new();
+    for entry in fs::read_dir(&dir_path).map_err(|e| Error::io(&dir_path, e))? {
+        let path = entry.map_err(|e| Error::io(&dir_path, e))?.path();
+
+        let number = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| basename_regex.captures(name))
+            .and_then(|captures| captures[1].parse::<usize>().ok());
+
+        if let Some(number) = number {
+            files.push((number, path));
+        }
+    }
 
-        // Extract the number as string from path
-        let a = basename_regex.captures(a).unwrap().get(1).unwrap().as_str();
-        let b = basename_regex.captures(b).unwrap().get(1).unwrap().as_str();
+    if files.is_empty() {
+        return Err(Error::NoFrames);
+    }
 
-        // Convert the number to usize
-        let a = a.parse::<usize>().unwrap();
-        let b = b.parse::<usize>().unwrap();
+    // Files were sorted by name, we want to sort them by number
+    files.sort_by_key(|(number, _)| *number);
+
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
 
-        a.cmp(&b)
-    });
+/// Checks that `tvid.log` describes at least as many pictures as there are frames
+pub fn check_frame_count(files: &[PathBuf], meta: &[Picture]) -> Result<(), Error> {
+    if meta.len() < files.len() {
+        return Err(Error::FrameCount {
+            frames: files.len(),
+            pictures: meta.len(),
+        });
+    }
 
-    files
+    Ok(())
 }
@@ -1,15 +1,30 @@
-use std::path::PathBuf;
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use mpeg2::read_files;
+use mpeg2::{
+    contact_sheet, decode_audio, detect_events, detect_source_borders, evenly_spaced, read_clip,
+    read_files, read_thumbnail, resize, to_square_pixels, write_apng, write_gif, Ac3Header,
+    Ac3Parser, AfdMode, AspectRatio, AudioPlayback, AudioSink, CaptionChannel, CaptionDecoder,
+    ChromaFormat, Crop, CropRect, CropSource, Deinterlacer, Event, EventKind, Field,
+    FrameComparison, FrameMode, FrameRate, FrameSource, Kernel, PcmBuffer, PgmSource, PixelFormat,
+    Quality, RawYuvSource, RawYuvWriter, StreamKind, Thresholds, TsDemuxer, WavWriter, YuvFrame,
+    AC3_SAMPLES_PER_FRAME,
+};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 /// MPEG2 Decoder
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    #[arg(short, long, default_value_t = mpeg2::MyApp::DEFAULT_PATH.to_string())]
-    pathdir: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    input: InputArgs,
 
     #[arg(short, long)]
     fps: Option<u64>,
@@ -19,44 +34,1207 @@ struct Args {
 
     #[arg(short, long)]
     threshold: Option<f32>,
+
+    /// Crop or letterbox frames to their active image (off, crop or letterbox), from the AFD
+    /// and bar data of the picture user data
+    #[arg(long, default_value = "off")]
+    afd: AfdMode,
+
+    #[command(flatten)]
+    detect: DetectArgs,
+
+    /// Transport stream whose MPEG audio is played with the frames, aligned on its first video
+    /// time stamp (needs the `playback` feature)
+    #[arg(long)]
+    audio: Option<PathBuf>,
+
+    /// PID of the audio stream to play, the first MPEG audio stream by default
+    #[arg(long, value_parser = parse_pid, requires = "audio")]
+    audio_pid: Option<u16>,
+}
+
+/// Where to read frames from
+#[derive(clap::Args, Debug)]
+struct InputArgs {
+    #[arg(short, long, default_value_t = mpeg2::MyApp::DEFAULT_PATH.to_string())]
+    pathdir: String,
+
+    /// Chroma format of the PGM frames (4:2:0, 4:2:2 or 4:4:4), read from tvid.log by default
+    #[arg(short, long)]
+    chroma: Option<ChromaFormat>,
+
+    /// Tell 4:4:4 frames from 4:2:0 ones by their PGM layout when tvid.log does not give the
+    /// chroma format, which black right halves can fool
+    #[arg(long, conflicts_with = "chroma")]
+    guess_chroma: bool,
+
+    /// Headerless raw YUV file to read instead of the PGM folder
+    #[arg(long, requires = "size")]
+    raw: Option<PathBuf>,
+
+    /// Dimensions of the raw YUV frames, as WIDTHxHEIGHT
+    #[arg(long, value_parser = parse_size)]
+    size: Option<(usize, usize)>,
+
+    /// Pixel format of the raw YUV frames (i420, nv12, yuy2 or uyvy)
+    #[arg(long, default_value = "i420")]
+    pix_fmt: PixelFormat,
+
+    /// Display aspect ratio of the frames, such as 16:9, instead of the one of tvid.log
+    #[arg(long)]
+    aspect: Option<AspectRatio>,
+
+    /// Crop frames to WxH+X+Y, or to their black borders detected over N frames with auto[:N]
+    #[arg(long)]
+    crop: Option<Crop>,
+}
+
+/// Black frame, freeze and scene cut detection
+#[derive(clap::Args, Debug)]
+struct DetectArgs {
+    /// Read every frame to find black frames, frozen video and scene cuts
+    #[arg(long)]
+    events: bool,
+
+    /// Luma up to which a sample is black
+    #[arg(long, default_value_t = Thresholds::default().black_level)]
+    black_level: u8,
+
+    /// Part of the samples that must be black for a frame to be black
+    #[arg(long, default_value_t = Thresholds::default().black_ratio)]
+    black_ratio: f32,
+
+    /// Block error, from 0 to 1, up to which a frame is frozen
+    #[arg(long, default_value_t = Thresholds::default().freeze)]
+    freeze_threshold: f32,
+
+    /// Mean block error, from 0 to 1, from which a frame starts a new scene
+    #[arg(long, default_value_t = Thresholds::default().scene)]
+    scene_threshold: f32,
+
+    /// Shortest run of black frames reported, in seconds
+    #[arg(long, default_value_t = Thresholds::default().min_black.as_secs_f64())]
+    min_black: f64,
+
+    /// Shortest run of frozen frames reported, in seconds
+    #[arg(long, default_value_t = Thresholds::default().min_freeze.as_secs_f64())]
+    min_freeze: f64,
+}
+
+impl DetectArgs {
+    fn thresholds(&self) -> Thresholds {
+        Thresholds {
+            black_level: self.black_level,
+            black_ratio: self.black_ratio,
+            freeze: self.freeze_threshold,
+            scene: self.scene_threshold,
+            min_black: Duration::from_secs_f64(self.min_black.max(0.0)),
+            min_freeze: Duration::from_secs_f64(self.min_freeze.max(0.0)),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Convert frames to a raw YUV file or to a folder of PGM frames
+    Convert {
+        #[command(flatten)]
+        input: InputArgs,
+
+        /// Raw YUV file to write, or folder of PGM frames if --to is omitted
+        output: PathBuf,
+
+        /// Pixel format of the raw YUV output (i420, nv12, yuy2 or uyvy)
+        #[arg(long)]
+        to: Option<PixelFormat>,
+
+        /// Stretch frames to square pixels, so that they show at their display aspect ratio
+        #[arg(long)]
+        square_pixels: bool,
+
+        /// Resize frames to WIDTHxHEIGHT
+        #[arg(long, value_parser = parse_size, conflicts_with = "square_pixels")]
+        resize: Option<(usize, usize)>,
+
+        /// Resampling kernel of --resize and --square-pixels (bilinear, bicubic or lanczos)
+        #[arg(long, default_value = "lanczos")]
+        kernel: Kernel,
+    },
+
+    /// Compare frames to the output of mpeg2dec, frame by frame
+    Compare {
+        #[command(flatten)]
+        input: InputArgs,
+
+        /// Folder of reference frames written by `mpeg2dec -o pgm`
+        #[arg(short, long)]
+        reference: PathBuf,
+    },
+
+    /// Compute PSNR, SSIM and MS-SSIM of frames against reference frames, as CSV
+    Metrics {
+        #[command(flatten)]
+        input: InputArgs,
+
+        /// Reference frames: a folder of PGM frames, or a raw YUV file with the input size
+        #[arg(short, long)]
+        reference: PathBuf,
+
+        /// Pixel format of a raw YUV reference, the input one by default
+        #[arg(long)]
+        reference_pix_fmt: Option<PixelFormat>,
+
+        /// CSV file to write, standard output by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Deinterlacer of interlaced frames, of both sources, before comparing them (weave, bob
+        /// or adaptive[:THRESHOLD])
+        #[arg(long, default_value = "weave")]
+        deinterlacer: Deinterlacer,
+    },
+
+    /// Report the GOP structure, bitrate and cadence of the stream described by tvid.log
+    Analyze {
+        #[command(flatten)]
+        input: InputArgs,
+
+        /// Log to read instead of `<PATHDIR>/tvid.log`
+        #[arg(short, long)]
+        log: Option<PathBuf>,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        #[command(flatten)]
+        detect: DetectArgs,
+    },
+
+    /// Decode the MPEG audio of a transport stream to a WAV file aligned with the video
+    Audio {
+        /// MPEG transport stream to read
+        input: PathBuf,
+
+        /// WAV file to write
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// PID of the audio stream, in decimal or 0x hexadecimal, the first MPEG audio one by default
+        #[arg(long, value_parser = parse_pid)]
+        pid: Option<u16>,
+    },
+
+    /// Copy the valid AC-3 frames of a transport stream to a raw .ac3 file and report their format
+    Ac3 {
+        /// MPEG transport stream to read
+        input: PathBuf,
+
+        /// Raw AC-3 file to write
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// PID of the AC-3 stream, in decimal or 0x hexadecimal, the first AC-3 one by default
+        #[arg(long, value_parser = parse_pid)]
+        pid: Option<u16>,
+    },
+
+    /// Extract the closed captions of the picture user data in tvid.log, as SRT or SCC
+    Captions {
+        #[arg(short, long, default_value_t = mpeg2::MyApp::DEFAULT_PATH.to_string())]
+        pathdir: String,
+
+        /// Log to read instead of `<PATHDIR>/tvid.log`
+        #[arg(short, long)]
+        log: Option<PathBuf>,
+
+        /// Caption channel, CC1 to CC4 for CEA-608 or SERVICE1 to SERVICE63 for CEA-708
+        #[arg(long, default_value = "CC1")]
+        channel: CaptionChannel,
+
+        /// Write the raw CEA-608 data of field 1 (CC1 and CC2) as Scenarist SCC instead of SRT
+        #[arg(long)]
+        scc: bool,
+
+        /// File to write, standard output by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Tile thumbnails of evenly spaced frames, or of the first frame of every scene, in one image
+    Contactsheet {
+        #[command(flatten)]
+        input: InputArgs,
+
+        /// Image to write, PPM if its extension is .ppm, PNG otherwise
+        output: PathBuf,
+
+        /// Number of thumbnails
+        #[arg(short = 'n', long, default_value_t = 24)]
+        frames: usize,
+
+        /// Thumbnails per row
+        #[arg(long, default_value_t = 6)]
+        columns: usize,
+
+        /// Width of the thumbnails in pixels
+        #[arg(long, default_value_t = 240)]
+        width: usize,
+
+        /// Show the first frame of every scene, reading every frame to find the scene cuts
+        #[arg(long)]
+        scenes: bool,
+
+        /// Mean block error, from 0 to 1, from which a frame starts a new scene
+        #[arg(long, default_value_t = Thresholds::default().scene)]
+        scene_threshold: f32,
+
+        /// Deinterlacer of interlaced frames (weave, bob or adaptive[:THRESHOLD])
+        #[arg(long, default_value = "adaptive")]
+        deinterlacer: Deinterlacer,
+    },
+
+    /// Export a range of frames, deinterlaced, as an animated GIF or PNG keeping their durations
+    Clip {
+        #[command(flatten)]
+        input: InputArgs,
+
+        /// Animation to write, GIF if its extension is .gif, animated PNG otherwise
+        output: PathBuf,
+
+        /// Frames to export, as START..END with END excluded, or START.. up to the last frame
+        #[arg(long, value_parser = parse_range)]
+        frames: Range<usize>,
+
+        /// One image per displayed field of interlaced frames instead of one per frame
+        #[arg(long)]
+        field_rate: bool,
+
+        /// Deinterlacer of interlaced frames (weave, bob or adaptive[:THRESHOLD])
+        #[arg(long, default_value = "adaptive")]
+        deinterlacer: Deinterlacer,
+    },
+}
+
+fn parse_size(s: &str) -> Result<(usize, usize), String> {
+    let (width, height) = s
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got `{}`", s))?;
+
+    let width: usize = width
+        .parse()
+        .map_err(|_| format!("invalid width `{}`", width))?;
+    let height = height
+        .parse()
+        .map_err(|_| format!("invalid height `{}`", height))?;
+    // A raw file of empty frames would never end
+    if width == 0 || height == 0 {
+        return Err(format!("`{}` has no pixels", s));
+    }
+    // RGBA images of this size must fit in memory
+    if width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .is_none()
+    {
+        return Err(format!("`{}` is too large", s));
+    }
+
+    Ok((width, height))
+}
+
+fn parse_range(s: &str) -> Result<Range<usize>, String> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| format!("expected START..END, got `{}`", s))?;
+
+    let start = start
+        .parse()
+        .map_err(|_| format!("invalid first frame `{}`", start))?;
+    let end = match end {
+        "" => usize::MAX,
+        end => end
+            .parse()
+            .map_err(|_| format!("invalid end frame `{}`", end))?,
+    };
+    if end <= start {
+        return Err(format!("empty frame range `{}`", s));
+    }
+
+    Ok(start..end)
+}
+
+fn parse_pid(s: &str) -> Result<u16, String> {
+    let pid = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("invalid PID `{}`", s))?;
+
+    if pid > 0x1fff {
+        return Err(format!("PID `{}` does not fit in 13 bits", s));
+    }
+    Ok(pid)
 }
 
 fn main() {
     // Parse optional arguments
     let args = Args::parse();
-    let Args {
-        fps: img_per_second,
-        pathdir,
-        mode,
-        threshold,
-    } = args;
 
-    dbg!(img_per_second);
+    let result = match args.command {
+        Some(Command::Convert {
+            input,
+            output,
+            to,
+            square_pixels,
+            resize,
+            kernel,
+        }) => {
+            let scaling = match resize {
+                Some((width, height)) => Scaling::Resize(width, height),
+                None if square_pixels => Scaling::SquarePixels,
+                None => Scaling::None,
+            };
+            convert(&input, &output, to, scaling, kernel)
+        }
+        Some(Command::Compare { input, reference }) => match compare(&input, &reference) {
+            Ok(true) => Ok(()),
+            // Not an error, but scripts must be able to tell
+            Ok(false) => std::process::exit(2),
+            Err(err) => Err(err),
+        },
+        Some(Command::Metrics {
+            input,
+            reference,
+            reference_pix_fmt,
+            output,
+            deinterlacer,
+        }) => metrics(
+            &input,
+            &reference,
+            reference_pix_fmt,
+            output.as_deref(),
+            deinterlacer,
+        ),
+        Some(Command::Analyze {
+            input,
+            log,
+            json,
+            detect,
+        }) => {
+            let log = log.unwrap_or_else(|| Path::new(&input.pathdir).join("tvid.log"));
+            analyze(&input, &log, json, &detect)
+        }
+        Some(Command::Audio { input, output, pid }) => audio(&input, &output, pid),
+        Some(Command::Ac3 { input, output, pid }) => ac3(&input, &output, pid),
+        Some(Command::Captions {
+            pathdir,
+            log,
+            channel,
+            scc,
+            output,
+        }) => {
+            let log = log.unwrap_or_else(|| Path::new(&pathdir).join("tvid.log"));
+            captions(&log, channel, scc, output.as_deref())
+        }
+        Some(Command::Contactsheet {
+            input,
+            output,
+            frames,
+            columns,
+            width,
+            scenes,
+            scene_threshold,
+            deinterlacer,
+        }) => {
+            let scenes = scenes.then_some(Thresholds {
+                scene: scene_threshold,
+                ..Thresholds::default()
+            });
+            contactsheet(
+                &input,
+                &output,
+                frames,
+                columns,
+                width.max(1),
+                scenes,
+                deinterlacer,
+            )
+        }
+        Some(Command::Clip {
+            input,
+            output,
+            frames,
+            field_rate,
+            deinterlacer,
+        }) => clip(&input, &output, frames, field_rate, deinterlacer),
+        None => open_audio(args.audio.as_deref(), args.audio_pid).and_then(|audio| {
+            view(
+                args.input,
+                args.fps,
+                args.mode,
+                args.threshold,
+                args.afd,
+                &args.detect,
+                audio,
+            )
+        }),
+    };
 
-    let meta = mpeg2::meta_decode(&PathBuf::new().join(&pathdir).join("tvid.log"));
-    if mode.is_none() && meta.is_err() {
-        eprintln!(
-            "\n\nError while parsing metadata {:?}\n\n",
-            meta.as_ref().err()
-        );
-        panic!("Error while parsing metadata {:?}", meta.err());
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Opens the input frames, cropped as --crop asks, with the crop rectangle
+fn open_source(
+    input: &InputArgs,
+    metadata_optional: bool,
+) -> Result<(Box<dyn FrameSource>, Option<CropRect>), mpeg2::Error> {
+    let mut source = open_frames(input, metadata_optional)?;
+
+    let rect = match input.crop {
+        Some(Crop::Rect(rect)) => rect,
+        Some(Crop::Auto(frames)) => match detect_source_borders(source.as_mut(), frames)? {
+            Some(rect) => rect,
+            None => {
+                eprintln!("No picture found to crop to, frames are all black");
+                return Ok((source, None));
+            }
+        },
+        None => return Ok((source, None)),
+    };
+    eprintln!("Cropping frames to {}", rect);
+
+    Ok((Box::new(CropSource::new(source, rect)), Some(rect)))
+}
+
+/// Crops reference frames like the input ones
+fn crop_reference(reference: Box<dyn FrameSource>, rect: Option<CropRect>) -> Box<dyn FrameSource> {
+    match rect {
+        Some(rect) => Box::new(CropSource::new(reference, rect)),
+        None => reference,
+    }
+}
+
+/// Opens the PGM folder or raw YUV file of the arguments, a PGM folder needing a valid
+/// `tvid.log` unless `metadata_optional` is set
+fn open_frames(
+    input: &InputArgs,
+    metadata_optional: bool,
+) -> Result<Box<dyn FrameSource>, mpeg2::Error> {
+    if let (Some(raw), Some((width, height))) = (&input.raw, input.size) {
+        return Ok(Box::new(RawYuvSource::open(
+            raw,
+            width,
+            height,
+            input.pix_fmt,
+        )?));
     }
 
-    let meta = meta.ok();
+    let files = read_files(&input.pathdir)?;
+
+    let meta = match mpeg2::meta_decode(&PathBuf::new().join(&input.pathdir).join("tvid.log")) {
+        Ok(meta) => {
+            mpeg2::check_frame_count(&files, &meta)?;
+            Some(meta)
+        }
+        Err(err) if metadata_optional => {
+            eprintln!("Ignoring metadata: {}", err);
+            None
+        }
+        Err(err) => return Err(err),
+    };
+
+    Ok(Box::new(
+        PgmSource::new(files, meta, input.chroma).with_chroma_guess(input.guess_chroma),
+    ))
+}
+
+fn view(
+    input: InputArgs,
+    img_per_second: Option<u64>,
+    mode: Option<String>,
+    threshold: Option<f32>,
+    afd: AfdMode,
+    detect: &DetectArgs,
+    audio: Option<AudioPlayback>,
+) -> Result<(), mpeg2::Error> {
+    dbg!(img_per_second);
+
+    // Metadata is only optional when the frame mode is forced
+    let (mut source, _) = open_source(&input, mode.is_some() || input.raw.is_some())?;
+    let aspect = input.aspect;
+    let events = if detect.events {
+        Some(find_events(source.as_mut(), detect)?)
+    } else {
+        None
+    };
 
     // Run window
     eframe::run_native(
         mpeg2::MyApp::WINDOW_TITLE,
         Default::default(),
         Box::new(move |cc| {
-            Box::new(mpeg2::MyApp::new(
-                cc,
-                read_files(&pathdir),
-                img_per_second,
-                mode,
-                threshold,
-                meta,
-            ))
+            let mut app =
+                mpeg2::MyApp::new(cc, source, img_per_second, mode, threshold, afd, aspect);
+            if let Some(events) = events {
+                app = app.with_events(events);
+            }
+            if let Some(audio) = audio {
+                app = app.with_audio(audio);
+            }
+            Box::new(app)
         }),
     );
+
+    Ok(())
+}
+
+/// Decodes the audio of a transport stream to play it with the frames
+fn open_audio(
+    path: Option<&Path>,
+    pid: Option<u16>,
+) -> Result<Option<AudioPlayback>, mpeg2::Error> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let mut demuxer = TsDemuxer::open(path)?;
+    let (track, report) = decode_audio(&mut demuxer, pid, |sample_rate, channels| {
+        Ok(PcmBuffer::new(sample_rate, channels))
+    })?;
+    eprintln!(
+        "PID 0x{:04x}: {:.3} s of {} Hz audio to play",
+        report.pid,
+        track.frames_written() as f64 / f64::from(track.sample_rate),
+        track.sample_rate
+    );
+
+    AudioPlayback::open(track).map(Some)
+}
+
+/// Detects the events of a source, telling how many were found
+fn find_events(
+    source: &mut dyn FrameSource,
+    detect: &DetectArgs,
+) -> Result<Vec<Event>, mpeg2::Error> {
+    let events = detect_events(source, detect.thresholds())?;
+    eprintln!("{} events found", events.len());
+    Ok(events)
+}
+
+/// Prints the report of tvid.log, with the events of the frames if asked
+fn analyze(
+    input: &InputArgs,
+    log: &Path,
+    json: bool,
+    detect: &DetectArgs,
+) -> Result<(), mpeg2::Error> {
+    let mut report = mpeg2::analyze(&mpeg2::read_log(log)?);
+    if detect.events {
+        let (mut source, _) = open_source(input, true)?;
+        report.events = Some(find_events(source.as_mut(), detect)?);
+    }
+
+    if json {
+        print!("{}", report.to_json());
+    } else {
+        print!("{}", report);
+    }
+
+    Ok(())
+}
+
+/// How `convert` resizes frames
+enum Scaling {
+    None,
+    SquarePixels,
+    Resize(usize, usize),
+}
+
+fn convert(
+    input: &InputArgs,
+    output: &Path,
+    to: Option<PixelFormat>,
+    scaling: Scaling,
+    kernel: Kernel,
+) -> Result<(), mpeg2::Error> {
+    let (mut source, _) = open_source(input, true)?;
+
+    let mut writer = match to {
+        Some(format) => Some(RawYuvWriter::create(output, format)?),
+        None => {
+            std::fs::create_dir_all(output).map_err(|e| mpeg2::Error::Io {
+                path: output.to_path_buf(),
+                source: e,
+            })?;
+            None
+        }
+    };
+
+    let mut count = 0;
+    let mut unknown_aspect = 0;
+    while let Some(mut frame) = source.next_frame()? {
+        match scaling {
+            Scaling::None => (),
+            Scaling::SquarePixels => {
+                let (width, height) = (frame.frame.width(), frame.frame.height());
+                let sample_aspect = match input.aspect {
+                    Some(aspect) => Some(aspect.sample_aspect(width, height)),
+                    None => frame.picture.as_ref().and_then(|p| p.sample_aspect),
+                };
+                match sample_aspect {
+                    Some(sample_aspect) => {
+                        frame.frame = to_square_pixels(&frame.frame, sample_aspect, kernel)
+                    }
+                    None => unknown_aspect += 1,
+                }
+            }
+            Scaling::Resize(width, height) => {
+                frame.frame = resize(&frame.frame, width, height, kernel)
+            }
+        }
+
+        match &mut writer {
+            Some(writer) => writer.write_frame(&frame.frame)?,
+            None => mpeg2::write_frame(&output.join(format!("{}.pgm", count)), &frame.frame)?,
+        }
+        count += 1;
+    }
+
+    if let Some(writer) = writer {
+        writer.finish()?;
+    }
+
+    eprintln!("{} frames written to {}", count, output.display());
+    if unknown_aspect > 0 {
+        eprintln!(
+            "{} frames without a known aspect ratio were left as is, see --aspect",
+            unknown_aspect
+        );
+    }
+
+    Ok(())
+}
+
+fn format_psnr(psnr: f64) -> String {
+    if psnr.is_infinite() {
+        "inf".to_string()
+    } else {
+        format!("{:.2}", psnr)
+    }
+}
+
+/// Prints the differences of every frame with its reference, returns whether all are identical
+fn compare(input: &InputArgs, reference: &Path) -> Result<bool, mpeg2::Error> {
+    let (mut source, crop) = open_source(input, true)?;
+    let reference =
+        Box::new(PgmSource::open(reference, input.chroma)?.with_chroma_guess(input.guess_chroma));
+    let mut reference = crop_reference(reference, crop);
+
+    println!("frame\tpsnr_y\tpsnr_cb\tpsnr_cr\tmax_err\tfirst_mismatch");
+
+    let mut index = 0;
+    let mut identical = 0;
+    loop {
+        let (expected, actual) = match (reference.next_frame()?, source.next_frame()?) {
+            (Some(expected), Some(actual)) => (expected.frame, actual.frame),
+            (None, None) => break,
+            (expected, _) => {
+                let missing = if expected.is_some() {
+                    "input"
+                } else {
+                    "reference"
+                };
+                println!("{}\tmissing from the {}", index, missing);
+                return Ok(false);
+            }
+        };
+
+        if (expected.width(), expected.height(), expected.chroma_format)
+            != (actual.width(), actual.height(), actual.chroma_format)
+        {
+            println!(
+                "{}\t{}x{} {:?} instead of {}x{} {:?}",
+                index,
+                actual.width(),
+                actual.height(),
+                actual.chroma_format,
+                expected.width(),
+                expected.height(),
+                expected.chroma_format
+            );
+            index += 1;
+            continue;
+        }
+
+        let comparison = FrameComparison::new(&expected, &actual);
+        let mismatch = match comparison.first_mismatch {
+            Some((column, row)) => format!("mb({}, {})", column, row),
+            None => "-".to_string(),
+        };
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            index,
+            format_psnr(comparison.psnr[0]),
+            format_psnr(comparison.psnr[1]),
+            format_psnr(comparison.psnr[2]),
+            comparison.max_abs_error,
+            mismatch
+        );
+
+        if comparison.is_identical() {
+            identical += 1;
+        }
+        index += 1;
+    }
+
+    println!("{}/{} frames identical", identical, index);
+
+    Ok(identical == index)
+}
+
+/// Mean over the finite values, minimum and maximum of a metric
+fn summary(values: impl Iterator<Item = f64>) -> (f64, f64, f64) {
+    let (mut sum, mut count) = (0.0, 0);
+    let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+
+    for value in values {
+        if value.is_finite() {
+            sum += value;
+            count += 1;
+        }
+        min = min.min(value);
+        max = max.max(value);
+    }
+
+    // Only infinite values, identical frames for PSNR
+    let mean = if count == 0 { max } else { sum / count as f64 };
+
+    (mean, min, max)
+}
+
+/// Writes the quality of every frame as CSV, then prints a summary
+fn metrics(
+    input: &InputArgs,
+    reference: &Path,
+    reference_pix_fmt: Option<PixelFormat>,
+    output: Option<&Path>,
+    deinterlacer: Deinterlacer,
+) -> Result<(), mpeg2::Error> {
+    use std::io::Write;
+
+    let (mut source, crop) = open_source(input, true)?;
+    let reference: Box<dyn FrameSource> = match input.size {
+        Some((width, height)) if !reference.is_dir() => Box::new(RawYuvSource::open(
+            reference,
+            width,
+            height,
+            reference_pix_fmt.unwrap_or(input.pix_fmt),
+        )?),
+        _ => Box::new(
+            PgmSource::open(reference, input.chroma)?.with_chroma_guess(input.guess_chroma),
+        ),
+    };
+    let mut reference = crop_reference(reference, crop);
+
+    let (mut out, out_path): (Box<dyn Write>, PathBuf) = match output {
+        Some(path) => (
+            Box::new(std::io::BufWriter::new(
+                std::fs::File::create(path).map_err(|e| mpeg2::Error::Io {
+                    path: path.to_path_buf(),
+                    source: e,
+                })?,
+            )),
+            path.to_path_buf(),
+        ),
+        None => (Box::new(std::io::stdout()), PathBuf::from("<stdout>")),
+    };
+    let io_error = |e| mpeg2::Error::Io {
+        path: out_path.clone(),
+        source: e,
+    };
+
+    writeln!(
+        out,
+        "frame,psnr_y,psnr_cb,psnr_cr,ssim_y,ssim_cb,ssim_cr,ms_ssim_y,ms_ssim_cb,ms_ssim_cr"
+    )
+    .map_err(io_error)?;
+
+    let mut results: Vec<Quality> = Vec::new();
+    let mut prev: Option<(YuvFrame, YuvFrame)> = None;
+    loop {
+        let index = results.len();
+        let (expected, actual) = match (reference.next_frame()?, source.next_frame()?) {
+            (Some(expected), Some(actual)) => (expected, actual),
+            (None, None) => break,
+            (expected, _) => {
+                let missing = if expected.is_some() {
+                    "input"
+                } else {
+                    "reference"
+                };
+                eprintln!(
+                    "Frame {} is missing from the {}, only {} frames compared",
+                    index, missing, index
+                );
+                break;
+            }
+        };
+
+        // Both frames show the same picture, a raw YUV reference has no metadata of its own
+        let picture = actual.picture.as_ref().or(expected.picture.as_ref());
+        let first_field = picture
+            .filter(|picture| picture.picture_type != FrameMode::PROG)
+            .map(|picture| {
+                if picture.top_field_first {
+                    Field::Top
+                } else {
+                    Field::Bottom
+                }
+            });
+        let (expected_image, actual_image) = match first_field {
+            Some(field) => {
+                let (prev_expected, prev_actual) = match &prev {
+                    Some((expected, actual)) => (Some(expected), Some(actual)),
+                    None => (None, None),
+                };
+                (
+                    deinterlacer.apply(&expected.frame, prev_expected, field),
+                    deinterlacer.apply(&actual.frame, prev_actual, field),
+                )
+            }
+            None => (expected.frame.clone(), actual.frame.clone()),
+        };
+        prev = Some((expected.frame, actual.frame));
+        let expected = expected_image;
+        // Compare chroma at the resolution of the reference
+        let actual = actual_image.with_chroma_format(expected.chroma_format);
+
+        if (expected.width(), expected.height()) != (actual.width(), actual.height()) {
+            return Err(mpeg2::Error::FrameSize {
+                index,
+                expected: (expected.width(), expected.height()),
+                actual: (actual.width(), actual.height()),
+            });
+        }
+
+        let quality = Quality::new(&expected, &actual);
+        let values: Vec<String> = [quality.psnr, quality.ssim, quality.ms_ssim]
+            .iter()
+            .flatten()
+            .map(|value| format!("{:.6}", value))
+            .collect();
+        writeln!(out, "{},{}", index, values.join(",")).map_err(io_error)?;
+
+        results.push(quality);
+    }
+    out.flush().map_err(io_error)?;
+
+    // Keep standard output valid CSV
+    eprintln!("{} frames compared", results.len());
+    eprintln!("metric\tmean\tmin\tmax");
+    let columns = [
+        (
+            "psnr",
+            results
+                .iter()
+                .map(|quality| quality.psnr)
+                .collect::<Vec<_>>(),
+        ),
+        ("ssim", results.iter().map(|quality| quality.ssim).collect()),
+        (
+            "ms_ssim",
+            results.iter().map(|quality| quality.ms_ssim).collect(),
+        ),
+    ];
+    for (name, column) in columns {
+        for (plane, suffix) in ["y", "cb", "cr"].iter().enumerate() {
+            let (mean, min, max) = summary(column.iter().map(|values| values[plane]));
+            eprintln!("{}_{}\t{:.4}\t{:.4}\t{:.4}", name, suffix, mean, min, max);
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes the Layer II audio of a transport stream to a WAV file.
+///
+/// The file starts at the first video time stamp, with silence before the first audio frame
+/// or without the audio before the video, and stays aligned with the video.
+fn audio(input: &Path, output: &Path, pid: Option<u16>) -> Result<(), mpeg2::Error> {
+    let mut demuxer = TsDemuxer::open(input)?;
+    let (writer, report) = decode_audio(&mut demuxer, pid, |sample_rate, channels| {
+        WavWriter::create(output, sample_rate, channels)
+    })?;
+
+    eprintln!(
+        "PID 0x{:04x}: {:.3} s of {} Hz {} audio",
+        report.pid,
+        writer.frames_written() as f64 / f64::from(writer.sample_rate()),
+        writer.sample_rate(),
+        if writer.channels() == 1 {
+            "mono"
+        } else {
+            "stereo"
+        }
+    );
+    if !report.video_start {
+        eprintln!("No video time stamp, the audio starts at its first time stamp");
+    }
+    if report.ignored > 0 {
+        eprintln!("{} frames with a different format ignored", report.ignored);
+    }
+    if report.discontinuities > 0 {
+        eprintln!(
+            "{} time stamp discontinuities, the audio continues without a gap",
+            report.discontinuities
+        );
+    }
+    if report.skipped > 0 {
+        eprintln!("{} bytes skipped looking for frame headers", report.skipped);
+    }
+
+    writer.finish()
+}
+
+/// Copies the AC-3 sync frames of a transport stream that pass their CRC check.
+fn ac3(input: &Path, output: &Path, pid: Option<u16>) -> Result<(), mpeg2::Error> {
+    use std::io::Write;
+
+    let mut demuxer = TsDemuxer::open(input)?;
+    let io_error = |e| mpeg2::Error::Io {
+        path: output.to_path_buf(),
+        source: e,
+    };
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(output).map_err(io_error)?);
+
+    let mut ac3_pid = pid;
+    let mut parser = Ac3Parser::new();
+    // Frame count of every format, in order of appearance
+    let mut formats: Vec<(Ac3Header, usize)> = Vec::new();
+    while let Some(packet) = demuxer.next_pes()? {
+        if ac3_pid.is_none()
+            && demuxer
+                .streams()
+                .any(|stream| stream.pid == packet.pid && stream.kind() == StreamKind::Ac3)
+        {
+            ac3_pid = Some(packet.pid);
+        }
+        if Some(packet.pid) != ac3_pid {
+            continue;
+        }
+
+        parser.push(&packet.payload, packet.pts);
+        while let Some(frame) = parser.next_frame() {
+            writer.write_all(&frame.data).map_err(io_error)?;
+
+            // Frames only differ by their bitrate and layout in practice
+            let same = |header: &Ac3Header| {
+                (header.sample_rate, header.bitrate, header.acmod, header.lfe)
+                    == (
+                        frame.header.sample_rate,
+                        frame.header.bitrate,
+                        frame.header.acmod,
+                        frame.header.lfe,
+                    )
+            };
+            match formats.iter_mut().find(|(header, _)| same(header)) {
+                Some((_, count)) => *count += 1,
+                None => formats.push((frame.header, 1)),
+            }
+        }
+    }
+    writer.flush().map_err(io_error)?;
+
+    let ac3_pid = ac3_pid.ok_or_else(|| mpeg2::Error::TransportStream {
+        path: input.to_path_buf(),
+        reason: "no AC-3 stream",
+    })?;
+    if formats.is_empty() {
+        return Err(mpeg2::Error::TransportStream {
+            path: input.to_path_buf(),
+            reason: "no valid AC-3 frame",
+        });
+    }
+
+    println!("PID 0x{:04x}", ac3_pid);
+    println!("frames\tduration\tlayout\tchannels\tbitrate\tsample rate");
+    for (header, count) in &formats {
+        println!(
+            "{}\t{:.3} s\t{}\t{}\t{} kbit/s\t{} Hz",
+            count,
+            (count * AC3_SAMPLES_PER_FRAME) as f64 / f64::from(header.sample_rate),
+            header.channel_layout(),
+            header.channels(),
+            header.bitrate / 1000,
+            header.sample_rate
+        );
+    }
+    if parser.corrupted() > 0 {
+        eprintln!("{} frames dropped after a CRC mismatch", parser.corrupted());
+    }
+    if parser.skipped() > 0 {
+        eprintln!("{} bytes skipped looking for sync words", parser.skipped());
+    }
+
+    Ok(())
+}
+
+/// Writes the captions of one channel as SubRip, or the field 1 data as SCC.
+fn captions(
+    log: &Path,
+    channel: CaptionChannel,
+    scc: bool,
+    output: Option<&Path>,
+) -> Result<(), mpeg2::Error> {
+    let pictures = mpeg2::meta_decode(log)?;
+    let decoder = CaptionDecoder::from_pictures(&pictures);
+
+    let text = if scc {
+        let frame_rate = pictures
+            .first()
+            .map_or(FrameRate::new(30000, 1001), |picture| picture.frame_rate());
+        mpeg2::to_scc(decoder.field1_pairs(), frame_rate)
+    } else {
+        let captions: Vec<_> = decoder
+            .captions()
+            .iter()
+            .filter(|caption| caption.channel == channel)
+            .cloned()
+            .collect();
+        mpeg2::to_srt(&captions)
+    };
+
+    match output {
+        Some(path) => std::fs::write(path, text).map_err(|e| mpeg2::Error::Io {
+            path: path.to_path_buf(),
+            source: e,
+        })?,
+        None => print!("{}", text),
+    }
+
+    // Channels found, to help choosing one
+    let mut channels: Vec<(CaptionChannel, usize)> = Vec::new();
+    for caption in decoder.captions() {
+        match channels.iter_mut().find(|(c, _)| *c == caption.channel) {
+            Some((_, count)) => *count += 1,
+            None => channels.push((caption.channel, 1)),
+        }
+    }
+    channels.sort();
+    if channels.is_empty() {
+        eprintln!("No captions in the picture user data");
+    }
+    for (channel, count) in channels {
+        eprintln!("{}: {} captions", channel, count);
+    }
+
+    Ok(())
+}
+
+/// Writes thumbnails of evenly spaced frames, or of scene starts if `scenes` is given
+fn contactsheet(
+    input: &InputArgs,
+    output: &Path,
+    count: usize,
+    columns: usize,
+    width: usize,
+    scenes: Option<Thresholds>,
+    deinterlacer: Deinterlacer,
+) -> Result<(), mpeg2::Error> {
+    let (mut source, _) = open_source(input, true)?;
+
+    let frames = match scenes {
+        Some(thresholds) => {
+            let mut starts = vec![0];
+            starts.extend(
+                detect_events(source.as_mut(), thresholds)?
+                    .iter()
+                    .filter(|event| event.kind == EventKind::SceneCut)
+                    .map(|event| event.frame),
+            );
+            eprintln!("{} scenes found", starts.len());
+            // Spread over the whole source when there are too many scenes
+            evenly_spaced(starts.len(), count)
+                .into_iter()
+                .map(|i| starts[i])
+                .collect()
+        }
+        None => {
+            let len = match source.frame_count() {
+                Some(len) => len,
+                None => {
+                    let mut len = 0;
+                    while source.next_frame()?.is_some() {
+                        len += 1;
+                    }
+                    len
+                }
+            };
+            evenly_spaced(len, count)
+        }
+    };
+
+    let mut thumbnails = Vec::with_capacity(frames.len());
+    for index in frames {
+        if let Some(thumbnail) =
+            read_thumbnail(source.as_mut(), index, deinterlacer, width, input.aspect)?
+        {
+            thumbnails.push(thumbnail);
+        }
+    }
+    if thumbnails.is_empty() {
+        return Err(mpeg2::Error::NoFrames);
+    }
+
+    let sheet = contact_sheet(&thumbnails, columns);
+    sheet.save(output)?;
+    eprintln!(
+        "{} thumbnails written to {} ({}x{})",
+        thumbnails.len(),
+        output.display(),
+        sheet.width(),
+        sheet.height()
+    );
+
+    Ok(())
+}
+
+/// Writes the frames of `range` as an animated GIF if the extension of `output` is `.gif`, as
+/// an animated PNG otherwise
+fn clip(
+    input: &InputArgs,
+    output: &Path,
+    range: Range<usize>,
+    field_rate: bool,
+    deinterlacer: Deinterlacer,
+) -> Result<(), mpeg2::Error> {
+    let (mut source, _) = open_source(input, true)?;
+
+    let clip = read_clip(source.as_mut(), range, deinterlacer, field_rate)?;
+    if clip.is_empty() {
+        return Err(mpeg2::Error::NoFrames);
+    }
+
+    let gif = output
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+    if gif {
+        write_gif(output, &clip)?;
+    } else {
+        write_apng(output, &clip)?;
+    }
+
+    let frames = clip.last().unwrap().frame + 1 - clip[0].frame;
+    eprintln!(
+        "{} images of {} frames written to {}",
+        clip.len(),
+        frames,
+        output.display()
+    );
+
+    Ok(())
 }
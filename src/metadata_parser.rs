@@ -1,74 +1,412 @@
-// SEQ <frame_period>
-// PIC <offset> <temp_ref> [PROG] [RFF] [TFF]
+// SEQ <frame_period> [PROG] [4:2:0|4:2:2|4:4:4] [SIZE <w>x<h>] [DISP <w>x<h>] [SAR <w>:<h>]
+// GOP <offset> [CLOSED] [BROKEN]
+// PIC <offset> <temp_ref> [PROG] [RFF] [TFF] [I|P|B|D]
+// USR <hex bytes>
 // PIC ...
 // SEQ ...
+//
+// GOP lines are only written by recent `mpeg2dec`, older logs go from SEQ to PIC lines.
+// SIZE is the horizontal and vertical size of the sequence header, DISP the display size
+// of the sequence_display_extension (the coded size without one), and SAR the sample aspect
+// ratio derived from aspect_ratio_information, omitted when it is invalid.
+// USR lines hold the user data of the picture before them, they are only written when the
+// picture has some. Consecutive user data structures are separated by the 00 00 01 prefix of
+// their start codes.
 
 use std::io::BufRead;
+use std::path::Path;
 use std::time::Duration;
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::{fs::File, io::BufReader};
 
-use crate::flag::FrameMode;
+use crate::afd::{ActiveArea, ActiveFormat, BarData};
+use crate::aspect::AspectRatio;
+use crate::flag::{ChromaFormat, CodingType, FrameMode};
+use crate::timing::{
+    bitrate, displayed_fields, duration_from_27mhz, fields_duration, FrameRate, SYSTEM_CLOCK_HZ,
+};
+use crate::Error;
 
-#[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
+/// Longest frame period accepted in `tvid.log`, one second of the 27 MHz clock
+const MAX_FRAME_PERIOD: usize = SYSTEM_CLOCK_HZ as usize;
+
+#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Picture {
     pub duration: Duration,
     pub picture_type: FrameMode,
     id: usize,
+
+    /// Byte offset of the picture in the stream
+    pub offset: usize,
+    /// Coded size in bytes, the offset delta to the next picture in decoding order
+    pub size: Option<usize>,
+    pub temporal_reference: usize,
+    /// Frame period of the enclosing sequence, in 27 MHz ticks
+    pub frame_period: usize,
+    /// Coding type, only present in logs written by recent `mpeg2dec`
+    pub coding_type: Option<CodingType>,
+    /// Chroma format of the enclosing sequence, only present in recent logs
+    pub chroma_format: Option<ChromaFormat>,
+    /// Presentation time relative to the first picture
+    pub pts: Duration,
+    pub progressive_sequence: bool,
+    /// Horizontal and vertical size of the sequence, only present in recent logs
+    pub coded_size: Option<(usize, usize)>,
+    /// Size of the display area of the sequence, only present in recent logs
+    pub display_size: Option<(usize, usize)>,
+    /// Sample aspect ratio of the sequence, only present in recent logs
+    pub sample_aspect: Option<AspectRatio>,
+
+    pub progressive_frame: bool,
+    pub repeat_first_field: bool,
+    pub top_field_first: bool,
+
+    /// Picture user data, empty if the picture has none or the log is older
+    pub user_data: Vec<u8>,
+    /// Active format of the last AFD of the sequence, up to this picture
+    pub active_format: Option<ActiveFormat>,
+    /// Bar data sent with the picture
+    pub bar_data: Option<BarData>,
+}
+
+impl Picture {
+    /// Frame rate of the enclosing sequence
+    pub fn frame_rate(&self) -> FrameRate {
+        FrameRate::from_frame_period(self.frame_period as u64)
+    }
+
+    /// Number of fields the picture is displayed for, frame repetition counting as two fields
+    pub fn displayed_fields(&self) -> u64 {
+        displayed_fields(
+            self.progressive_sequence,
+            self.repeat_first_field,
+            self.top_field_first,
+        )
+    }
+
+    /// Display aspect ratio of the display area of the sequence
+    pub fn display_aspect(&self) -> Option<AspectRatio> {
+        let (width, height) = self.display_size.or(self.coded_size)?;
+        Some(self.sample_aspect?.display_aspect(width, height))
+    }
+
+    /// Area of the active image in a `width`×`height` frame shown at `display_aspect`, from the
+    /// bar data or else the active format
+    pub fn active_area(
+        &self,
+        width: usize,
+        height: usize,
+        display_aspect: f64,
+    ) -> Option<ActiveArea> {
+        match self.bar_data {
+            Some(bar_data) => bar_data.active_area(width, height),
+            None => self
+                .active_format?
+                .active_area(width, height, display_aspect),
+        }
+    }
+
+    /// Bits per second needed to send the picture in one frame period
+    pub fn bitrate(&self) -> Option<f64> {
+        Some(bitrate(self.size?, self.frame_period as u64))
+    }
+}
+
+/// Sequence header line of `tvid.log`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceEntry {
+    /// 1-based line number in the log
+    pub line: usize,
+    /// Frame period in 27 MHz ticks
+    pub frame_period: usize,
+    pub progressive_sequence: bool,
+    pub chroma_format: Option<ChromaFormat>,
+    pub coded_size: Option<(usize, usize)>,
+    pub display_size: Option<(usize, usize)>,
+    pub sample_aspect: Option<AspectRatio>,
+}
+
+/// Group of pictures header line of `tvid.log`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GopEntry {
+    pub line: usize,
+    /// Byte offset of the header in the stream
+    pub offset: usize,
+    pub closed: bool,
+    pub broken_link: bool,
+}
+
+/// Picture line of `tvid.log`, in decoding order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PictureEntry {
+    pub line: usize,
+    /// Byte offset of the picture in the stream
+    pub offset: usize,
+    pub temporal_reference: usize,
+    pub picture_type: FrameMode,
+    pub coding_type: Option<CodingType>,
+    pub progressive_frame: bool,
+    pub repeat_first_field: bool,
+    pub top_field_first: bool,
+}
+
+/// User data line of `tvid.log`, belonging to the picture before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserDataEntry {
+    pub line: usize,
+    pub data: Vec<u8>,
+}
+
+/// A line of `tvid.log`, unknown lines are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogEntry {
+    Sequence(SequenceEntry),
+    Gop(GopEntry),
+    Picture(PictureEntry),
+    UserData(UserDataEntry),
+}
+
+/// Splits the user data of a picture into its user data structures
+pub fn user_data_chunks(user_data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = Some(user_data);
+    std::iter::from_fn(move || {
+        let data = rest?;
+        match data.windows(3).position(|prefix| prefix == [0, 0, 1]) {
+            Some(end) => {
+                rest = Some(&data[end + 3..]);
+                Some(&data[..end])
+            }
+            None => {
+                rest = None;
+                Some(data)
+            }
+        }
+    })
+    .filter(|chunk| !chunk.is_empty())
+}
+
+/// Value `<a><separator><b>` following the word `key`, `None` without the key
+fn keyed_value(words: &[&str], key: &str, separator: char) -> Result<Option<(usize, usize)>, ()> {
+    let Some(position) = words.iter().position(|word| *word == key) else {
+        return Ok(None);
+    };
+
+    words
+        .get(position + 1)
+        .and_then(|value| value.split_once(separator))
+        .and_then(|(a, b)| Some((a.parse().ok()?, b.parse().ok()?)))
+        .map(Some)
+        .ok_or(())
 }
 
-pub fn meta_decode(path: &PathBuf) -> Result<Vec<Picture>, String> {
-    let file = File::open(path).map_err(|_| format!("Could not open `tvid.log` at {:?}", path))?;
+/// Reads every entry of `tvid.log` in file order, without interpreting them
+pub fn read_log(path: &Path) -> Result<Vec<LogEntry>, Error> {
+    let file = File::open(path).map_err(|e| Error::io(path, e))?;
     let mut reader = BufReader::new(file);
 
-    let mut sequence_frame_period = None;
     let mut line = String::new();
+    let mut line_number = 0;
+    let mut entries = Vec::new();
 
-    let mut pictures = Vec::new();
-    let mut last = 0;
-
-    while let Ok(read_count) = reader.read_line(&mut line) {
+    loop {
+        let read_count = reader
+            .read_line(&mut line)
+            .map_err(|e| Error::io(path, e))?;
         if read_count == 0 {
             break;
         }
+        line_number += 1;
 
-        let words = line.trim().split_whitespace().collect::<Vec<_>>();
+        let malformed = |reason| Error::LogLine {
+            path: path.to_path_buf(),
+            line: line_number,
+            reason,
+        };
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
 
         if line.starts_with("SEQ") {
             let frame_period = words
                 .get(1)
                 .unwrap_or(&"1080000")
                 .parse::<usize>()
-                .map_err(|_| "Could not parse frame_period")?;
+                .map_err(|_| malformed("could not parse frame_period"))?;
+            if frame_period == 0 {
+                return Err(malformed("frame_period must be non-zero"));
+            }
+            // Frames of MPEG-2 streams last a few tens of milliseconds
+            if frame_period > MAX_FRAME_PERIOD {
+                return Err(malformed("frame_period must be at most one second"));
+            }
 
-            sequence_frame_period = Some(frame_period);
-            last = pictures.len()
+            entries.push(LogEntry::Sequence(SequenceEntry {
+                line: line_number,
+                frame_period,
+                progressive_sequence: words[1..].contains(&"PROG"),
+                chroma_format: ChromaFormat::from_flags(words[1..].iter()),
+                coded_size: keyed_value(&words, "SIZE", 'x')
+                    .map_err(|_| malformed("could not parse sequence size"))?,
+                display_size: keyed_value(&words, "DISP", 'x')
+                    .map_err(|_| malformed("could not parse display size"))?,
+                sample_aspect: keyed_value(&words, "SAR", ':')
+                    .map_err(|_| malformed("could not parse sample aspect ratio"))?
+                    .filter(|(num, den)| *num != 0 && *den != 0)
+                    .map(|(num, den)| AspectRatio::new(num as u64, den as u64)),
+            }));
+        } else if line.starts_with("GOP") {
+            let offset = words
+                .get(1)
+                .and_then(|offset| usize::from_str_radix(offset, 16).ok())
+                .ok_or_else(|| malformed("could not parse GOP offset"))?;
+
+            entries.push(LogEntry::Gop(GopEntry {
+                line: line_number,
+                offset,
+                closed: words[2..].contains(&"CLOSED"),
+                broken_link: words[2..].contains(&"BROKEN"),
+            }));
         } else if line.starts_with("PIC") {
             if words.len() < 3 {
-                return Err("line PIC doesn't contain enough fields".into());
+                return Err(malformed("line PIC doesn't contain enough fields"));
             }
 
+            let offset = usize::from_str_radix(words[1], 16)
+                .map_err(|_| malformed("could not parse picture offset"))?;
+
             let temp_ref = words[2]
                 .parse::<usize>()
-                .map_err(|_| "Could not parse temp_ref")?;
+                .map_err(|_| malformed("could not parse temp_ref"))?;
+            // A 10-bit field of the picture header
+            if temp_ref > 1023 {
+                return Err(malformed("temp_ref does not fit in 10 bits"));
+            }
+
+            entries.push(LogEntry::Picture(PictureEntry {
+                line: line_number,
+                offset,
+                temporal_reference: temp_ref,
+                picture_type: FrameMode::from(words[3..].iter()),
+                coding_type: CodingType::from_flags(words[3..].iter()),
+                progressive_frame: words[3..].contains(&"PROG"),
+                repeat_first_field: words[3..].contains(&"RFF"),
+                top_field_first: words[3..].contains(&"TFF"),
+            }));
+        } else if line.starts_with("USR") {
+            let hex = words.get(1).unwrap_or(&"");
+            let data = (0..hex.len())
+                .step_by(2)
+                .map(|i| {
+                    hex.get(i..i + 2)
+                        .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                })
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(|| malformed("could not parse user data"))?;
 
-            let frame_mode = FrameMode::from(words[3..].iter());
+            entries.push(LogEntry::UserData(UserDataEntry {
+                line: line_number,
+                data,
+            }));
+        }
 
-            let frame_period =
-                sequence_frame_period.expect("You should have a SEQ before PIC") as f64;
+        line.clear();
+    }
 
-            let picture = Picture {
-                id: temp_ref + last,
-                duration: Duration::from_millis((27_000_000f64 / frame_period) as u64),
-                picture_type: frame_mode,
-            };
+    Ok(entries)
+}
 
-            pictures.push(picture);
+pub fn meta_decode(path: &Path) -> Result<Vec<Picture>, Error> {
+    let mut sequence = None;
+    let mut pictures = Vec::new();
+    let mut last = 0;
+    // AFDs stay in effect until the next one
+    let mut active_format = None;
+
+    for entry in read_log(path)? {
+        match entry {
+            LogEntry::Sequence(entry) => {
+                sequence = Some(entry);
+                last = pictures.len();
+                active_format = None;
+            }
+            // Temporal references restart at every group of pictures
+            LogEntry::Gop(_) => last = pictures.len(),
+            LogEntry::Picture(entry) => {
+                let sequence = sequence.as_ref().ok_or_else(|| Error::LogLine {
+                    path: path.to_path_buf(),
+                    line: entry.line,
+                    reason: "PIC line before any SEQ",
+                })?;
+                let frame_period = sequence.frame_period;
+
+                pictures.push(Picture {
+                    id: entry.temporal_reference + last,
+                    duration: duration_from_27mhz(frame_period as u64),
+                    picture_type: entry.picture_type,
+
+                    offset: entry.offset,
+                    size: None,
+                    temporal_reference: entry.temporal_reference,
+                    frame_period,
+                    coding_type: entry.coding_type,
+                    chroma_format: sequence.chroma_format,
+                    pts: Duration::ZERO,
+                    progressive_sequence: sequence.progressive_sequence,
+                    coded_size: sequence.coded_size,
+                    display_size: sequence.display_size,
+                    sample_aspect: sequence.sample_aspect,
+
+                    progressive_frame: entry.progressive_frame,
+                    repeat_first_field: entry.repeat_first_field,
+                    top_field_first: entry.top_field_first,
+
+                    user_data: Vec::new(),
+                    active_format,
+                    bar_data: None,
+                });
+            }
+            LogEntry::UserData(entry) => {
+                if let Some(picture) = pictures.last_mut() {
+                    for chunk in user_data_chunks(&entry.data) {
+                        if let Some(format) = ActiveFormat::parse(chunk) {
+                            active_format = Some(format);
+                            picture.active_format = active_format;
+                        }
+                        if let Some(bar_data) = BarData::parse(chunk) {
+                            picture.bar_data = Some(bar_data);
+                        }
+                    }
+                    if !picture.user_data.is_empty() {
+                        picture.user_data.extend([0, 0, 1]);
+                    }
+                    picture.user_data.extend(entry.data);
+                }
+            }
         }
+    }
 
-        line.clear();
+    // Pictures are still in decoding order
+    for i in 1..pictures.len() {
+        let offset = pictures[i].offset;
+        let previous = &mut pictures[i - 1];
+        previous.size = offset.checked_sub(previous.offset);
     }
 
     pictures.sort_by_key(|p| p.id);
 
+    // Presentation times in display order, restarting the field count at frame rate changes
+    let mut origin = Duration::ZERO;
+    let mut fields = 0;
+    let mut frame_period = None;
+    for picture in &mut pictures {
+        let period = picture.frame_period as u64;
+        if frame_period != Some(period) {
+            origin += fields_duration(frame_period.unwrap_or(0), fields);
+            fields = 0;
+            frame_period = Some(period);
+        }
+
+        picture.pts = origin + fields_duration(period, fields);
+        fields += picture.displayed_fields();
+    }
+
     Ok(pictures)
 }
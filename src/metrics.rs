@@ -0,0 +1,312 @@
+use crate::yuv::{Plane, YuvFrame};
+
+/// Size of a macroblock in luma samples.
+pub const MACROBLOCK_SIZE: usize = 16;
+
+/// Largest value of an 8-bit sample.
+const PEAK: f64 = 255.0;
+
+/// Side of the gaussian window used by SSIM, and its standard deviation.
+const SSIM_WINDOW: usize = 11;
+const SSIM_SIGMA: f64 = 1.5;
+
+/// SSIM stabilizing constants, `(K1 * PEAK)^2` and `(K2 * PEAK)^2`
+const SSIM_C1: f64 = (0.01 * PEAK) * (0.01 * PEAK);
+const SSIM_C2: f64 = (0.03 * PEAK) * (0.03 * PEAK);
+
+/// Weights of the MS-SSIM scales, finest first (Wang, Simoncelli and Bovik, 2003).
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+fn same_dimensions(a: &Plane, b: &Plane) -> bool {
+    a.width() == b.width() && a.height() == b.height()
+}
+
+/// Mean squared error between two planes of the same size
+pub fn mse(a: &Plane, b: &Plane) -> f64 {
+    assert!(same_dimensions(a, b), "planes of different sizes");
+
+    let sum: u64 = a
+        .rows()
+        .zip(b.rows())
+        .flat_map(|(a, b)| a.iter().zip(b))
+        .map(|(a, b)| u64::from(a.abs_diff(*b)).pow(2))
+        .sum();
+
+    sum as f64 / (a.width() * a.height()).max(1) as f64
+}
+
+/// Peak signal-to-noise ratio in dB, infinite for identical planes
+pub fn psnr(a: &Plane, b: &Plane) -> f64 {
+    let mse = mse(a, b);
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (PEAK * PEAK / mse).log10()
+    }
+}
+
+/// Plane samples as floats, for the filters of SSIM
+struct Samples {
+    width: usize,
+    height: usize,
+    data: Vec<f64>,
+}
+
+impl Samples {
+    fn new(plane: &Plane) -> Self {
+        Self {
+            width: plane.width(),
+            height: plane.height(),
+            data: plane.rows().flatten().map(|v| f64::from(*v)).collect(),
+        }
+    }
+
+    fn map(&self, other: &Samples, f: impl Fn(f64, f64) -> f64) -> Samples {
+        Samples {
+            width: self.width,
+            height: self.height,
+            data: self
+                .data
+                .iter()
+                .zip(&other.data)
+                .map(|(a, b)| f(*a, *b))
+                .collect(),
+        }
+    }
+
+    /// Separable gaussian filter, keeping only the positions where the window fits
+    fn filter(&self, kernel: &[f64]) -> Samples {
+        let width = self.width + 1 - kernel.len();
+        let height = self.height + 1 - kernel.len();
+
+        let mut rows = Vec::with_capacity(width * self.height);
+        for line in self.data.chunks_exact(self.width) {
+            rows.extend(
+                line.windows(kernel.len())
+                    .map(|window| window.iter().zip(kernel).map(|(v, k)| v * k).sum::<f64>()),
+            );
+        }
+
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            data.extend((0..width).map(|x| {
+                kernel
+                    .iter()
+                    .enumerate()
+                    .map(|(i, k)| rows[(y + i) * width + x] * k)
+                    .sum::<f64>()
+            }));
+        }
+
+        Samples {
+            width,
+            height,
+            data,
+        }
+    }
+
+    /// Half-size copy, each sample the average of a 2x2 block
+    fn downsample(&self) -> Samples {
+        let width = self.width / 2;
+        let height = self.height / 2;
+        let at = |x: usize, y: usize| self.data[y * self.width + x];
+
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            data.extend((0..width).map(|x| {
+                (at(2 * x, 2 * y)
+                    + at(2 * x + 1, 2 * y)
+                    + at(2 * x, 2 * y + 1)
+                    + at(2 * x + 1, 2 * y + 1))
+                    / 4.0
+            }));
+        }
+
+        Samples {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+/// Normalized gaussian kernel of `size` taps
+fn gaussian_kernel(size: usize) -> Vec<f64> {
+    let center = (size as f64 - 1.0) / 2.0;
+    let kernel: Vec<f64> = (0..size)
+        .map(|i| (-(i as f64 - center).powi(2) / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp())
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+
+    kernel.into_iter().map(|k| k / sum).collect()
+}
+
+/// Mean SSIM and mean contrast-structure term of two planes
+///
+/// Planes smaller than the SSIM window are compared with a window as large as the plane.
+fn ssim_terms(a: &Samples, b: &Samples) -> (f64, f64) {
+    let size = SSIM_WINDOW.min(a.width).min(a.height);
+    if size == 0 {
+        return (1.0, 1.0);
+    }
+    let kernel = gaussian_kernel(size);
+
+    let mu_a = a.filter(&kernel);
+    let mu_b = b.filter(&kernel);
+    let aa = a.map(a, |a, _| a * a).filter(&kernel);
+    let bb = b.map(b, |b, _| b * b).filter(&kernel);
+    let ab = a.map(b, |a, b| a * b).filter(&kernel);
+
+    let mut ssim = 0.0;
+    let mut cs = 0.0;
+    for i in 0..mu_a.data.len() {
+        let (mu_a, mu_b) = (mu_a.data[i], mu_b.data[i]);
+        let var_a = aa.data[i] - mu_a * mu_a;
+        let var_b = bb.data[i] - mu_b * mu_b;
+        let covariance = ab.data[i] - mu_a * mu_b;
+
+        let contrast_structure = (2.0 * covariance + SSIM_C2) / (var_a + var_b + SSIM_C2);
+        let luminance = (2.0 * mu_a * mu_b + SSIM_C1) / (mu_a * mu_a + mu_b * mu_b + SSIM_C1);
+
+        ssim += luminance * contrast_structure;
+        cs += contrast_structure;
+    }
+
+    let count = mu_a.data.len() as f64;
+    (ssim / count, cs / count)
+}
+
+/// Structural similarity index of two planes of the same size, 1 for identical planes
+///
+/// Mean over all the positions of an 11x11 gaussian window with a standard deviation of 1.5.
+pub fn ssim(a: &Plane, b: &Plane) -> f64 {
+    assert!(same_dimensions(a, b), "planes of different sizes");
+
+    ssim_terms(&Samples::new(a), &Samples::new(b)).0
+}
+
+/// Multi-scale SSIM of two planes of the same size, 1 for identical planes
+///
+/// Planes too small for the five scales use the scales that fit, with renormalized weights.
+pub fn ms_ssim(a: &Plane, b: &Plane) -> f64 {
+    assert!(same_dimensions(a, b), "planes of different sizes");
+
+    let mut a = Samples::new(a);
+    let mut b = Samples::new(b);
+
+    // A scale is only used if the SSIM window still fits
+    let mut scales = 1;
+    let mut side = a.width.min(a.height);
+    while scales < MS_SSIM_WEIGHTS.len() && side / 2 >= SSIM_WINDOW {
+        scales += 1;
+        side /= 2;
+    }
+    let weights = &MS_SSIM_WEIGHTS[..scales];
+    let total: f64 = weights.iter().sum();
+
+    let mut product = 1.0;
+    for (scale, weight) in weights.iter().enumerate() {
+        let (ssim, cs) = ssim_terms(&a, &b);
+
+        // Luminance only counts at the coarsest scale, negative terms are clamped as usual
+        let term = if scale + 1 == scales { ssim } else { cs };
+        product *= term.max(0.0).powf(weight / total);
+
+        a = a.downsample();
+        b = b.downsample();
+    }
+
+    product
+}
+
+/// Largest absolute difference between two samples at the same position
+pub fn max_abs_error(a: &Plane, b: &Plane) -> u8 {
+    assert!(same_dimensions(a, b), "planes of different sizes");
+
+    a.rows()
+        .zip(b.rows())
+        .flat_map(|(a, b)| a.iter().zip(b))
+        .map(|(a, b)| a.abs_diff(*b))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Position `(column, row)` of the first macroblock, in raster order, where the frames differ
+///
+/// A macroblock covers `MACROBLOCK_SIZE` luma samples and the chroma samples at the same place.
+pub fn first_mismatch(a: &YuvFrame, b: &YuvFrame) -> Option<(usize, usize)> {
+    assert!(
+        a.chroma_format == b.chroma_format && same_dimensions(&a.y, &b.y),
+        "frames of different formats"
+    );
+
+    let (h_shift, v_shift) = a.chroma_format.subsampling();
+    let shifts = [(0, 0), (h_shift, v_shift), (h_shift, v_shift)];
+
+    a.planes()
+        .into_iter()
+        .zip(b.planes())
+        .zip(shifts)
+        .filter_map(|((a, b), (h_shift, v_shift))| {
+            let block_width = MACROBLOCK_SIZE >> h_shift;
+            let block_height = MACROBLOCK_SIZE >> v_shift;
+
+            (0..a.height())
+                .flat_map(|y| (0..a.width()).map(move |x| (x, y)))
+                .filter(|(x, y)| a.get(*x, *y) != b.get(*x, *y))
+                .map(|(x, y)| (x / block_width, y / block_height))
+                .min_by_key(|(column, row)| (*row, *column))
+        })
+        .min_by_key(|(column, row)| (*row, *column))
+}
+
+/// Differences between a frame and its reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameComparison {
+    /// PSNR of the Y, Cb and Cr planes
+    pub psnr: [f64; 3],
+    /// Largest absolute error over all planes
+    pub max_abs_error: u8,
+    /// First differing macroblock, `None` if the frames are identical
+    pub first_mismatch: Option<(usize, usize)>,
+}
+
+impl FrameComparison {
+    pub fn new(reference: &YuvFrame, frame: &YuvFrame) -> Self {
+        let planes = reference.planes().into_iter().zip(frame.planes());
+
+        Self {
+            psnr: [0, 1, 2].map(|i| psnr(reference.planes()[i], frame.planes()[i])),
+            max_abs_error: planes.map(|(a, b)| max_abs_error(a, b)).max().unwrap_or(0),
+            first_mismatch: first_mismatch(reference, frame),
+        }
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.first_mismatch.is_none()
+    }
+}
+
+/// Objective quality of a frame against its reference, per Y, Cb and Cr plane.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quality {
+    pub psnr: [f64; 3],
+    pub ssim: [f64; 3],
+    pub ms_ssim: [f64; 3],
+}
+
+impl Quality {
+    /// # Panics
+    ///
+    /// If the frames do not have the same dimensions and chroma format.
+    pub fn new(reference: &YuvFrame, frame: &YuvFrame) -> Self {
+        let reference = reference.planes();
+        let frame = frame.planes();
+
+        Self {
+            psnr: [0, 1, 2].map(|i| psnr(reference[i], frame[i])),
+            ssim: [0, 1, 2].map(|i| ssim(reference[i], frame[i])),
+            ms_ssim: [0, 1, 2].map(|i| ms_ssim(reference[i], frame[i])),
+        }
+    }
+}
@@ -0,0 +1,560 @@
+// MPEG-1 and MPEG-2 audio Layer II, see ISO/IEC 11172-3 and 13818-3
+//
+// Each frame starts with a 32-bit header (12-bit sync word 0xfff) and holds
+// 1152 samples per channel: 12 granules of 3 samples for each of 32 subbands.
+// Bit allocations select a quantizer per subband, scalefactors scale each
+// third of the frame, and a polyphase filterbank turns subband samples back
+// into PCM.
+
+use std::{collections::VecDeque, f64::consts::PI};
+
+/// Samples per channel in a Layer II frame.
+pub const SAMPLES_PER_FRAME: usize = 1152;
+
+const SUBBANDS: usize = 32;
+
+/// Bitrates in kbit/s by bitrate index, index 0 is the unsupported free format
+const MPEG1_BITRATES: [u32; 15] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+];
+const MPEG2_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+const MPEG1_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+const MPEG2_SAMPLE_RATES: [u32; 3] = [22050, 24000, 16000];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelMode {
+    Stereo,
+    JointStereo,
+    DualChannel,
+    Mono,
+}
+
+/// Layer II frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioHeader {
+    /// MPEG-1, or the lower sampling frequencies of MPEG-2
+    pub mpeg1: bool,
+    /// A CRC follows the header
+    pub protected: bool,
+    /// Bits per second
+    pub bitrate: u32,
+    pub sample_rate: u32,
+    pub padding: bool,
+    pub mode: ChannelMode,
+    /// Joint stereo bound, subbands from `4 * (mode_extension + 1)` share their samples
+    pub mode_extension: u8,
+}
+
+impl AudioHeader {
+    /// Parses a Layer II header, `None` for other layers, free format and reserved values
+    pub fn parse(bytes: [u8; 4]) -> Option<Self> {
+        let header = u32::from_be_bytes(bytes);
+        if header >> 20 != 0xfff {
+            return None;
+        }
+        let mpeg1 = header & (1 << 19) != 0;
+        // Layer II is coded as 0b10
+        if (header >> 17) & 0x3 != 0b10 {
+            return None;
+        }
+
+        let bitrate_index = ((header >> 12) & 0xf) as usize;
+        let sample_rate_index = ((header >> 10) & 0x3) as usize;
+        if bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+            return None;
+        }
+        let (bitrates, sample_rates) = if mpeg1 {
+            (MPEG1_BITRATES, MPEG1_SAMPLE_RATES)
+        } else {
+            (MPEG2_BITRATES, MPEG2_SAMPLE_RATES)
+        };
+
+        Some(Self {
+            mpeg1,
+            protected: header & (1 << 16) == 0,
+            bitrate: bitrates[bitrate_index] * 1000,
+            sample_rate: sample_rates[sample_rate_index],
+            padding: header & (1 << 9) != 0,
+            mode: match (header >> 6) & 0x3 {
+                0 => ChannelMode::Stereo,
+                1 => ChannelMode::JointStereo,
+                2 => ChannelMode::DualChannel,
+                _ => ChannelMode::Mono,
+            },
+            mode_extension: ((header >> 4) & 0x3) as u8,
+        })
+    }
+
+    pub fn channels(&self) -> usize {
+        match self.mode {
+            ChannelMode::Mono => 1,
+            _ => 2,
+        }
+    }
+
+    /// Size of the frame in bytes, header included
+    pub fn frame_size(&self) -> usize {
+        (144 * self.bitrate / self.sample_rate) as usize + usize::from(self.padding)
+    }
+
+    /// Quantizers of every subband, by bit allocation
+    fn allocation_table(&self) -> &'static [&'static [u32]] {
+        if !self.mpeg1 {
+            return &TABLE_LSF;
+        }
+
+        let bitrate_per_channel = self.bitrate / self.channels() as u32;
+        if bitrate_per_channel <= 48_000 {
+            if self.sample_rate == 32_000 {
+                &TABLE_D
+            } else {
+                &TABLE_C
+            }
+        } else if bitrate_per_channel <= 80_000 || self.sample_rate == 48_000 {
+            &TABLE_A
+        } else {
+            &TABLE_B
+        }
+    }
+}
+
+// Quantizers by bit allocation, as their number of steps, 0 when the subband is
+// not transmitted. The number of allocation bits follows from the row length.
+// See tables 3-B.2a to 3-B.2d of ISO/IEC 11172-3 and B.1 of ISO/IEC 13818-3.
+
+const STEPS_16_WIDE: &[u32] = &[
+    0, 3, 7, 15, 31, 63, 127, 255, 511, 1023, 2047, 4095, 8191, 16383, 32767, 65535,
+];
+const STEPS_16_HIGH: &[u32] = &[
+    0, 3, 5, 7, 9, 15, 31, 63, 127, 255, 511, 1023, 2047, 4095, 8191, 65535,
+];
+const STEPS_16_MID: &[u32] = &[
+    0, 3, 5, 9, 15, 31, 63, 127, 255, 511, 1023, 2047, 4095, 8191, 16383, 32767,
+];
+const STEPS_16_LSF: &[u32] = &[
+    0, 3, 5, 7, 9, 15, 31, 63, 127, 255, 511, 1023, 2047, 4095, 8191, 16383,
+];
+const STEPS_8_HIGH: &[u32] = &[0, 3, 5, 7, 9, 15, 31, 65535];
+const STEPS_8_LOW: &[u32] = &[0, 3, 5, 9, 15, 31, 63, 127];
+const STEPS_4_LSF: &[u32] = &[0, 3, 5, 9];
+const STEPS_4_LOW: &[u32] = &[0, 3, 5, 65535];
+
+#[rustfmt::skip]
+const TABLE_A: [&[u32]; 27] = [
+    STEPS_16_WIDE, STEPS_16_WIDE, STEPS_16_WIDE,
+    STEPS_16_HIGH, STEPS_16_HIGH, STEPS_16_HIGH, STEPS_16_HIGH,
+    STEPS_16_HIGH, STEPS_16_HIGH, STEPS_16_HIGH, STEPS_16_HIGH,
+    STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH,
+    STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH,
+    STEPS_4_LOW, STEPS_4_LOW, STEPS_4_LOW, STEPS_4_LOW,
+];
+#[rustfmt::skip]
+const TABLE_B: [&[u32]; 30] = [
+    STEPS_16_WIDE, STEPS_16_WIDE, STEPS_16_WIDE,
+    STEPS_16_HIGH, STEPS_16_HIGH, STEPS_16_HIGH, STEPS_16_HIGH,
+    STEPS_16_HIGH, STEPS_16_HIGH, STEPS_16_HIGH, STEPS_16_HIGH,
+    STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH,
+    STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH, STEPS_8_HIGH,
+    STEPS_4_LOW, STEPS_4_LOW, STEPS_4_LOW, STEPS_4_LOW, STEPS_4_LOW, STEPS_4_LOW, STEPS_4_LOW,
+];
+#[rustfmt::skip]
+const TABLE_C: [&[u32]; 8] = [
+    STEPS_16_MID, STEPS_16_MID,
+    STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW,
+];
+#[rustfmt::skip]
+const TABLE_D: [&[u32]; 12] = [
+    STEPS_16_MID, STEPS_16_MID,
+    STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW,
+    STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW,
+];
+#[rustfmt::skip]
+const TABLE_LSF: [&[u32]; 30] = [
+    STEPS_16_LSF, STEPS_16_LSF, STEPS_16_LSF, STEPS_16_LSF,
+    STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW, STEPS_8_LOW,
+    STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF,
+    STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF,
+    STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF, STEPS_4_LSF,
+];
+
+/// Scalefactor `index`, `2^(1 - index / 3)`, index 63 is reserved
+fn scalefactor(index: u32) -> f32 {
+    if index >= 63 {
+        0.0
+    } else {
+        2.0f32.powf(1.0 - index as f32 / 3.0)
+    }
+}
+
+/// Reads bits most significant first, past the end of the frame it reads zeros.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn read(&mut self, bits: u32) -> u32 {
+        let mut value = 0;
+        for _ in 0..bits {
+            let byte = self.data.get(self.position / 8).copied().unwrap_or(0);
+            value = (value << 1) | u32::from((byte >> (7 - self.position % 8)) & 1);
+            self.position += 1;
+        }
+        value
+    }
+}
+
+/// Reads the three samples of a granule quantized with `steps` steps, in the range -1 to 1
+fn read_samples(reader: &mut BitReader, steps: u32) -> [f32; 3] {
+    let dequantize = |code: u32| (2.0 * code as f32 - (steps - 1) as f32) / steps as f32;
+
+    match steps {
+        // 3, 5 and 9 steps are grouped in a single codeword
+        3 | 5 | 9 => {
+            let bits = match steps {
+                3 => 5,
+                5 => 7,
+                _ => 10,
+            };
+            let mut code = reader.read(bits);
+            [0; 3].map(|_| {
+                let sample = dequantize(code % steps);
+                code /= steps;
+                sample
+            })
+        }
+        _ => {
+            let bits = (steps + 1).trailing_zeros();
+            [0; 3].map(|_| dequantize(reader.read(bits)))
+        }
+    }
+}
+
+/// Polyphase synthesis filterbank of one channel, see annex 3-A.2 of ISO/IEC 11172-3.
+struct Synthesis {
+    /// Last 16 outputs of the matrixing, `front` is the most recent
+    v: [[f32; 64]; 16],
+    front: usize,
+}
+
+impl Synthesis {
+    fn new() -> Self {
+        Self {
+            v: [[0.0; 64]; 16],
+            front: 0,
+        }
+    }
+
+    /// Turns one sample of each subband into 32 PCM samples
+    fn run(&mut self, cosines: &[[f32; SUBBANDS]; 64], samples: &[f32; SUBBANDS]) -> [f32; 32] {
+        self.front = (self.front + 15) % 16;
+        let v = &mut self.v[self.front];
+        for (v, row) in v.iter_mut().zip(cosines) {
+            *v = row.iter().zip(samples).map(|(n, s)| n * s).sum();
+        }
+
+        let mut out = [0.0; 32];
+        for i in 0..8 {
+            let even = &self.v[(self.front + 2 * i) % 16];
+            let odd = &self.v[(self.front + 2 * i + 1) % 16];
+            for (j, out) in out.iter_mut().enumerate() {
+                *out += even[j] * window(64 * i + j) + odd[32 + j] * window(64 * i + 32 + j);
+            }
+        }
+        out
+    }
+}
+
+fn window(i: usize) -> f32 {
+    SYNTHESIS_WINDOW[i] as f32 / 65536.0
+}
+
+/// Decoded frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFrame {
+    pub header: AudioHeader,
+    /// Presentation time stamp of the PES packet the frame starts in, in ticks of the 90 kHz clock
+    pub pts: Option<u64>,
+    /// `SAMPLES_PER_FRAME` interleaved samples per channel
+    pub samples: Vec<i16>,
+}
+
+/// Decodes a Layer II elementary stream pushed in pieces, such as PES payloads.
+///
+/// The decoder looks for the next header whenever a frame is malformed, and a header is only
+/// trusted if the next one, when already pushed, follows it with the same format.
+pub struct Mp2Decoder {
+    buffer: Vec<u8>,
+    /// Time stamps pushed with the data, with the position in `buffer` of their first byte
+    timestamps: VecDeque<(usize, u64)>,
+    /// Matrixing coefficients `cos((16 + i) * (2k + 1) * PI / 64)`
+    cosines: Box<[[f32; SUBBANDS]; 64]>,
+    synthesis: [Synthesis; 2],
+    /// Bytes skipped while looking for a frame header
+    skipped: usize,
+}
+
+impl Default for Mp2Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mp2Decoder {
+    pub fn new() -> Self {
+        let mut cosines = Box::new([[0.0; SUBBANDS]; 64]);
+        for (i, row) in cosines.iter_mut().enumerate() {
+            for (k, n) in row.iter_mut().enumerate() {
+                *n = ((16 + i) as f64 * (2 * k + 1) as f64 * PI / 64.0).cos() as f32;
+            }
+        }
+
+        Self {
+            buffer: Vec::new(),
+            timestamps: VecDeque::new(),
+            cosines,
+            synthesis: [Synthesis::new(), Synthesis::new()],
+            skipped: 0,
+        }
+    }
+
+    /// Appends stream bytes, `pts` applying to the first frame that starts in them
+    pub fn push(&mut self, data: &[u8], pts: Option<u64>) {
+        if let Some(pts) = pts {
+            self.timestamps.push_back((self.buffer.len(), pts));
+        }
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Bytes skipped so far while looking for frame headers
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Decodes the next complete frame pushed, `None` until more data is pushed
+    pub fn next_frame(&mut self) -> Option<AudioFrame> {
+        let mut start = 0;
+        let header = loop {
+            if start + 4 > self.buffer.len() {
+                self.consume(start);
+                return None;
+            }
+
+            let header = AudioHeader::parse(self.buffer[start..start + 4].try_into().unwrap());
+            if let Some(header) = header {
+                let end = start + header.frame_size();
+                if end > self.buffer.len() {
+                    // The end of the frame has not been pushed yet
+                    self.consume(start);
+                    return None;
+                }
+                let next = self
+                    .buffer
+                    .get(end..end + 4)
+                    .map(|bytes| AudioHeader::parse(bytes.try_into().unwrap()));
+                match next {
+                    None => break header,
+                    Some(Some(next))
+                        if next.mpeg1 == header.mpeg1
+                            && next.sample_rate == header.sample_rate
+                            && next.channels() == header.channels() =>
+                    {
+                        break header
+                    }
+                    _ => (),
+                }
+            }
+            start += 1;
+        };
+
+        // Time stamp of the last PES packet starting before the frame
+        let mut pts = None;
+        while let Some((position, timestamp)) = self.timestamps.front() {
+            if *position > start {
+                break;
+            }
+            pts = Some(*timestamp);
+            self.timestamps.pop_front();
+        }
+
+        let end = start + header.frame_size();
+        let samples = self.decode(&header, start, end);
+        self.consume(end);
+        self.skipped -= header.frame_size();
+
+        Some(AudioFrame {
+            header,
+            pts,
+            samples,
+        })
+    }
+
+    /// Drops the first `count` bytes of the buffer
+    fn consume(&mut self, count: usize) {
+        self.buffer.drain(..count);
+        self.skipped += count;
+        for (position, _) in self.timestamps.iter_mut() {
+            *position = position.saturating_sub(count);
+        }
+    }
+
+    fn decode(&mut self, header: &AudioHeader, start: usize, end: usize) -> Vec<i16> {
+        let data = &self.buffer[start..end];
+        let table = header.allocation_table();
+        let sblimit = table.len();
+        let channels = header.channels();
+        let bound = match header.mode {
+            ChannelMode::JointStereo => (4 * (usize::from(header.mode_extension) + 1)).min(sblimit),
+            _ => sblimit,
+        };
+
+        let mut reader = BitReader::new(data);
+        reader.read(32);
+        if header.protected {
+            reader.read(16);
+        }
+
+        // Number of quantizer steps of every subband
+        let mut steps = [[0u32; SUBBANDS]; 2];
+        for (sb, quantizers) in table.iter().enumerate() {
+            let bits = quantizers.len().trailing_zeros();
+            for ch in 0..channels {
+                if ch == 0 || sb < bound {
+                    steps[ch][sb] = quantizers[reader.read(bits) as usize];
+                } else {
+                    steps[ch][sb] = steps[0][sb];
+                }
+            }
+        }
+
+        // Scalefactor selection information, then scalefactors for each third of the frame
+        let mut selection = [[0u32; SUBBANDS]; 2];
+        for sb in 0..sblimit {
+            for ch in 0..channels {
+                if steps[ch][sb] != 0 {
+                    selection[ch][sb] = reader.read(2);
+                }
+            }
+        }
+        let mut scalefactors = [[[0.0f32; 3]; SUBBANDS]; 2];
+        for sb in 0..sblimit {
+            for ch in 0..channels {
+                if steps[ch][sb] == 0 {
+                    continue;
+                }
+                let [a, b, c] = match selection[ch][sb] {
+                    0 => [reader.read(6), reader.read(6), reader.read(6)],
+                    1 => {
+                        let (a, b) = (reader.read(6), reader.read(6));
+                        [a, a, b]
+                    }
+                    2 => {
+                        let a = reader.read(6);
+                        [a, a, a]
+                    }
+                    _ => {
+                        let (a, b) = (reader.read(6), reader.read(6));
+                        [a, b, b]
+                    }
+                };
+                scalefactors[ch][sb] = [scalefactor(a), scalefactor(b), scalefactor(c)];
+            }
+        }
+
+        let mut pcm = vec![0; SAMPLES_PER_FRAME * channels];
+        for granule in 0..12 {
+            let part = granule / 4;
+
+            // Subband samples, shared by both channels above the joint stereo bound
+            let mut samples = [[[0.0f32; 3]; SUBBANDS]; 2];
+            for sb in 0..sblimit {
+                for ch in 0..channels {
+                    if steps[ch][sb] == 0 {
+                        continue;
+                    }
+                    if ch == 0 || sb < bound {
+                        samples[ch][sb] = read_samples(&mut reader, steps[ch][sb]);
+                    } else {
+                        samples[ch][sb] = samples[0][sb];
+                    }
+                }
+            }
+
+            for ch in 0..channels {
+                for s in 0..3 {
+                    let mut subbands = [0.0; SUBBANDS];
+                    for ((value, sample), scalefactor) in subbands
+                        .iter_mut()
+                        .zip(&samples[ch])
+                        .zip(&scalefactors[ch])
+                        .take(sblimit)
+                    {
+                        *value = sample[s] * scalefactor[part];
+                    }
+
+                    let out = self.synthesis[ch].run(&self.cosines, &subbands);
+                    let first = (granule * 3 + s) * SUBBANDS;
+                    for (i, value) in out.iter().enumerate() {
+                        pcm[(first + i) * channels + ch] =
+                            (value * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+                    }
+                }
+            }
+        }
+
+        pcm
+    }
+}
+
+/// Synthesis window of table 3-B.3 of ISO/IEC 11172-3, in units of 2^-16
+#[rustfmt::skip]
+const SYNTHESIS_WINDOW: [i32; 512] = [
+    0, -1, -1, -1, -1, -1, -1, -2, -2, -2, -2, -3,
+    -3, -4, -4, -5, -5, -6, -7, -7, -8, -9, -10, -11,
+    -13, -14, -16, -17, -19, -21, -24, -26, -29, -31, -35, -38,
+    -41, -45, -49, -53, -58, -63, -68, -73, -79, -85, -91, -97,
+    -104, -111, -117, -125, -132, -139, -147, -154, -161, -169, -176, -183,
+    -190, -196, -202, -208, 213, 218, 222, 225, 227, 228, 228, 227,
+    224, 221, 215, 208, 200, 189, 177, 163, 146, 127, 106, 83,
+    57, 29, -2, -36, -72, -111, -153, -197, -244, -294, -347, -401,
+    -459, -519, -581, -645, -711, -779, -848, -919, -991, -1064, -1137, -1210,
+    -1283, -1356, -1428, -1498, -1567, -1634, -1698, -1759, -1817, -1870, -1919, -1962,
+    -2001, -2032, -2057, -2075, -2085, -2087, -2080, -2063, 2037, 2000, 1952, 1893,
+    1822, 1739, 1644, 1535, 1414, 1280, 1131, 970, 794, 605, 402, 185,
+    -45, -288, -545, -814, -1095, -1388, -1692, -2006, -2330, -2663, -3004, -3351,
+    -3705, -4063, -4425, -4788, -5153, -5517, -5879, -6237, -6589, -6935, -7271, -7597,
+    -7910, -8209, -8491, -8755, -8998, -9219, -9416, -9585, -9727, -9838, -9916, -9959,
+    -9966, -9935, -9863, -9750, -9592, -9389, -9139, -8840, -8492, -8092, -7640, -7134,
+    6574, 5959, 5288, 4561, 3776, 2935, 2037, 1082, 70, -998, -2122, -3300,
+    -4533, -5818, -7154, -8540, -9975, -11455, -12980, -14548, -16155, -17799, -19478, -21189,
+    -22929, -24694, -26482, -28289, -30112, -31947, -33791, -35640, -37489, -39336, -41176, -43006,
+    -44821, -46617, -48390, -50137, -51853, -53534, -55178, -56778, -58333, -59838, -61289, -62684,
+    -64019, -65290, -66494, -67629, -68692, -69679, -70590, -71420, -72169, -72835, -73415, -73908,
+    -74313, -74630, -74856, -74992, 75038, 74992, 74856, 74630, 74313, 73908, 73415, 72835,
+    72169, 71420, 70590, 69679, 68692, 67629, 66494, 65290, 64019, 62684, 61289, 59838,
+    58333, 56778, 55178, 53534, 51853, 50137, 48390, 46617, 44821, 43006, 41176, 39336,
+    37489, 35640, 33791, 31947, 30112, 28289, 26482, 24694, 22929, 21189, 19478, 17799,
+    16155, 14548, 12980, 11455, 9975, 8540, 7154, 5818, 4533, 3300, 2122, 998,
+    -70, -1082, -2037, -2935, -3776, -4561, -5288, -5959, 6574, 7134, 7640, 8092,
+    8492, 8840, 9139, 9389, 9592, 9750, 9863, 9935, 9966, 9959, 9916, 9838,
+    9727, 9585, 9416, 9219, 8998, 8755, 8491, 8209, 7910, 7597, 7271, 6935,
+    6589, 6237, 5879, 5517, 5153, 4788, 4425, 4063, 3705, 3351, 3004, 2663,
+    2330, 2006, 1692, 1388, 1095, 814, 545, 288, 45, -185, -402, -605,
+    -794, -970, -1131, -1280, -1414, -1535, -1644, -1739, -1822, -1893, -1952, -2000,
+    2037, 2063, 2080, 2087, 2085, 2075, 2057, 2032, 2001, 1962, 1919, 1870,
+    1817, 1759, 1698, 1634, 1567, 1498, 1428, 1356, 1283, 1210, 1137, 1064,
+    991, 919, 848, 779, 711, 645, 581, 519, 459, 401, 347, 294,
+    244, 197, 153, 111, 72, 36, 2, -29, -57, -83, -106, -127,
+    -146, -163, -177, -189, -200, -208, -215, -221, -224, -227, -228, -228,
+    -227, -225, -222, -218, 213, 208, 202, 196, 190, 183, 176, 169,
+    161, 154, 147, 139, 132, 125, 117, 111, 104, 97, 91, 85,
+    79, 73, 68, 63, 58, 53, 49, 45, 41, 38, 35, 31,
+    29, 26, 24, 21, 19, 17, 16, 14, 13, 11, 10, 9,
+    8, 7, 7, 6, 5, 5, 4, 4, 3, 3, 2, 2,
+    2, 2, 1, 1, 1, 1, 1, 1,
+];
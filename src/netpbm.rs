@@ -0,0 +1,225 @@
+// Netpbm grayscale and color images, see `man 5 pgm` and `man 5 ppm`
+//
+// P2 <width> <height> <max_val> <ascii samples...>
+// P5 <width> <height> <max_val> <binary samples>
+// P6 <width> <height> <max_val> <binary RGB samples>
+//
+// Fields are separated by any amount of whitespace and `#` comments run until
+// the end of the line. Binary samples are one byte if `max_val < 256`,
+// two big-endian bytes otherwise.
+
+use std::{fs, path::Path};
+
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetpbmFormat {
+    /// ASCII graymap
+    P2,
+    /// Binary graymap
+    P5,
+    /// Binary pixmap
+    P6,
+}
+
+impl NetpbmFormat {
+    /// Number of samples per pixel
+    pub fn channels(&self) -> usize {
+        match self {
+            NetpbmFormat::P2 | NetpbmFormat::P5 => 1,
+            NetpbmFormat::P6 => 3,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Netpbm {
+    pub format: NetpbmFormat,
+    pub width: usize,
+    pub height: usize,
+    pub max_val: u16,
+
+    /// Samples in row order, `channels()` samples per pixel
+    pub samples: Vec<u16>,
+}
+
+impl Netpbm {
+    /// Samples rescaled to the 0..=255 range
+    pub fn samples_u8(&self) -> Vec<u8> {
+        if self.max_val == 255 {
+            return self.samples.iter().map(|s| *s as u8).collect();
+        }
+
+        let max_val = u32::from(self.max_val);
+        self.samples
+            .iter()
+            .map(|s| ((u32::from(*s) * 255 + max_val / 2) / max_val) as u8)
+            .collect()
+    }
+}
+
+struct Lexer<'a> {
+    path: &'a Path,
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn error(&self, reason: &'static str) -> Error {
+        Error::PgmHeader {
+            path: self.path.to_path_buf(),
+            offset: self.offset as u64,
+            reason,
+        }
+    }
+
+    /// Skips whitespace and comments, returns whether anything was skipped
+    fn skip_separators(&mut self) -> bool {
+        let start = self.offset;
+
+        while let Some(c) = self.data.get(self.offset) {
+            if *c == b'#' {
+                while !matches!(self.data.get(self.offset), None | Some(b'\n' | b'\r')) {
+                    self.offset += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.offset += 1;
+            } else {
+                break;
+            }
+        }
+
+        self.offset != start
+    }
+
+    fn value(&mut self, reason: &'static str) -> Result<usize, Error> {
+        if !self.skip_separators() {
+            return Err(self.error("expected whitespace"));
+        }
+
+        let start = self.offset;
+        let mut value = 0usize;
+        while let Some(c) = self.data.get(self.offset).filter(|c| c.is_ascii_digit()) {
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add(usize::from(c - b'0')))
+                .ok_or_else(|| self.error("value overflows"))?;
+            self.offset += 1;
+        }
+
+        if self.offset == start {
+            return Err(self.error(reason));
+        }
+
+        Ok(value)
+    }
+}
+
+fn parse(path: &Path, data: &[u8]) -> Result<Netpbm, Error> {
+    let mut lexer = Lexer {
+        path,
+        data,
+        offset: 0,
+    };
+
+    let format = match data.get(..2) {
+        Some(b"P2") => NetpbmFormat::P2,
+        Some(b"P5") => NetpbmFormat::P5,
+        Some(b"P6") => NetpbmFormat::P6,
+        _ => return Err(lexer.error("expected magic number P2, P5 or P6")),
+    };
+    lexer.offset = 2;
+
+    let width = lexer.value("expected width")?;
+    let height = lexer.value("expected height")?;
+    let max_val_offset = lexer.offset;
+    let max_val = lexer.value("expected max value")?;
+
+    if width == 0 || height == 0 {
+        return Err(lexer.error("width and height must be positive"));
+    }
+    if max_val == 0 || max_val > usize::from(u16::MAX) {
+        lexer.offset = max_val_offset;
+        return Err(lexer.error("max value must be in 1..=65535"));
+    }
+    let max_val = max_val as u16;
+
+    let count = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(format.channels()))
+        .ok_or_else(|| lexer.error("image size overflows"))?;
+    let samples = match format {
+        NetpbmFormat::P2 => {
+            // Each sample takes at least a separator and a digit
+            let remaining = data.len() - lexer.offset;
+            if count > remaining / 2 {
+                return Err(lexer.error("fewer bytes left than the header announces samples"));
+            }
+
+            let mut samples = Vec::with_capacity(count);
+            for _ in 0..count {
+                // Any failure here is a bad sample, not a bad header field
+                let sample = lexer
+                    .value("expected ASCII sample")
+                    .map_err(|_| lexer.error("expected ASCII sample"))?;
+                if sample > usize::from(max_val) {
+                    return Err(lexer.error("sample exceeds max value"));
+                }
+                samples.push(sample as u16);
+            }
+
+            lexer.skip_separators();
+            if lexer.offset != data.len() {
+                return Err(Error::PgmSize {
+                    path: path.to_path_buf(),
+                    expected: lexer.offset as u64,
+                    actual: data.len() as u64,
+                });
+            }
+
+            samples
+        }
+        NetpbmFormat::P5 | NetpbmFormat::P6 => {
+            // A single whitespace character separates the header from the raster
+            if !matches!(data.get(lexer.offset), Some(c) if c.is_ascii_whitespace()) {
+                return Err(lexer.error("expected whitespace after max value"));
+            }
+            let raster = &data[lexer.offset + 1..];
+
+            let sample_size = if max_val < 256 { 1 } else { 2 };
+            let expected = count
+                .checked_mul(sample_size)
+                .ok_or_else(|| lexer.error("image size overflows"))?;
+            if raster.len() != expected {
+                return Err(Error::PgmSize {
+                    path: path.to_path_buf(),
+                    expected: (lexer.offset + 1) as u64 + expected as u64,
+                    actual: data.len() as u64,
+                });
+            }
+
+            if sample_size == 1 {
+                raster.iter().map(|s| u16::from(*s)).collect()
+            } else {
+                raster
+                    .chunks_exact(2)
+                    .map(|s| u16::from_be_bytes([s[0], s[1]]))
+                    .collect()
+            }
+        }
+    };
+
+    Ok(Netpbm {
+        format,
+        width,
+        height,
+        max_val,
+        samples,
+    })
+}
+
+pub fn read_netpbm(path: &Path) -> Result<Netpbm, Error> {
+    let data = fs::read(path).map_err(|e| Error::io(path, e))?;
+
+    parse(path, &data)
+}
@@ -1,175 +1,124 @@
-use std::{
-    fs::File,
-    io::{BufRead, BufReader, Read},
-    path::PathBuf,
-};
-
-use crate::{image::Rgb, RgbImage};
-
-#[derive(Debug)]
-struct Header {
-    #[allow(dead_code)]
-    /// The magic number of the file. Should be "P5".
-    header: &'static str,
-
-    width: usize,
-    height: usize,
-    #[allow(dead_code)]
-    max_val: usize,
-}
-
-#[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
-enum Token {
-    Whitespace,
-    Comment,
-    Value(usize),
-    Header,
-    End,
-    Unexpected,
-}
-
-fn lex_header(reader: &mut BufReader<File>) -> Token {
-    let mut buf = [0; 1];
-    if reader.read_exact(&mut buf).is_err() {
-        return Token::End;
-    }
-
-    if buf[0] == b'P' {
-        if reader.read_exact(&mut buf).is_err() {
-            return Token::End;
-        }
-        if buf[0] == b'5' {
-            return Token::Header;
-        }
-        reader.seek_relative(-1).unwrap();
-        buf[0] = b'P';
-    }
-
-    if buf[0] == b'#' {
-        reader.read_line(&mut String::new()).unwrap();
-        Token::Comment
-    } else if buf[0].is_ascii_whitespace() {
-        Token::Whitespace
-    } else if buf[0].is_ascii_digit() {
-        let mut number = usize::from(buf[0] - b'0');
+use std::{fs, path::Path};
 
-        while reader.read_exact(&mut buf).is_ok() && buf[0].is_ascii_digit() {
-            number = number * 10 + usize::from(buf[0] - b'0');
-        }
+use crate::{
+    flag::ChromaFormat,
+    netpbm::{read_netpbm, Netpbm, NetpbmFormat},
+    yuv::{Plane, YuvFrame},
+    Error, RgbImage,
+};
 
-        reader.seek_relative(-1).unwrap();
-        Token::Value(number)
+/// Guesses the chroma format of a frame from its PGM layout, 4:4:4 or else 4:2:0.
+///
+/// mpeg2dec pads 4:4:4 luma rows with zeros up to twice the luma width. Studio-range luma never
+/// goes below 16, but a full-range or damaged picture black on its right half has the same
+/// layout, and 4:2:0 cannot be told from 4:2:2 at all. Only use the guess when neither the
+/// command line nor the SEQ lines of tvid.log give the format.
+pub fn guess_chroma_format(image: &Netpbm) -> ChromaFormat {
+    // The luma rows of a 4:4:4 layout, the top half, all end with a half of zeros
+    let padded = image.height.is_multiple_of(2)
+        && image.width.is_multiple_of(2)
+        && image
+            .samples
+            .chunks_exact(image.width)
+            .take(image.height / 2)
+            .all(|row| row[image.width / 2..].iter().all(|s| *s == 0));
+
+    if padded {
+        ChromaFormat::Yuv444
     } else {
-        Token::Unexpected
+        ChromaFormat::Yuv420
     }
 }
 
-fn parse_headers(buf: &mut BufReader<File>) -> Result<Header, &'static str> {
-    let header = lex_header(buf);
-    assert!(matches!(header, Token::Header));
-
-    let whitespace = lex_header(buf);
-    assert!(matches!(whitespace, Token::Whitespace));
-
-    let mut width = lex_header(buf);
-    while width == Token::Comment {
-        width = lex_header(buf);
+/// Reads a graymap, the layout of every mpeg2dec frame
+fn read_graymap(path: &Path) -> Result<Netpbm, Error> {
+    let image = read_netpbm(path)?;
+    if image.format == NetpbmFormat::P6 {
+        return Err(Error::PgmHeader {
+            path: path.to_path_buf(),
+            offset: 0,
+            reason: "expected a graymap (P2 or P5) frame",
+        });
     }
-    assert!(matches!(width, Token::Value(_)));
-
-    let whitespace = lex_header(buf);
-    assert!(matches!(whitespace, Token::Whitespace));
 
-    let height = lex_header(buf);
-    assert!(matches!(height, Token::Value(_)));
-
-    let whitespace = lex_header(buf);
-    assert!(matches!(whitespace, Token::Whitespace));
-
-    let max_val = lex_header(buf);
-    assert!(matches!(max_val, Token::Value(_)));
-
-    let whitespace = lex_header(buf);
-    assert!(matches!(whitespace, Token::Whitespace));
-
-    let width = match width {
-        Token::Value(width) => width,
-        _ => return Err("Invalid width"),
-    };
-
-    let height = match height {
-        Token::Value(height) => height,
-        _ => return Err("Invalid height"),
-    };
-
-    let max_val = match max_val {
-        Token::Value(max_val) => max_val,
-        _ => return Err("Invalid max value"),
-    };
-
-    Ok(Header {
-        header: "P5",
-        width,
-        height,
-        max_val,
-    })
+    Ok(image)
 }
 
-pub fn decode(path: &PathBuf, image: &mut RgbImage) -> Result<(), &'static str> {
-    // Open file
-    let file = File::open(path).map_err(|_| "Could not open file")?;
-    let mut reader = BufReader::new(file);
-    let header = parse_headers(&mut reader)?;
+/// Reads a frame, splitting the planes according to `chroma_format`, 4:2:0 if
+/// unknown as in MPEG-2 main profile streams.
+///
+/// ```text
+///      YY        YY        YY
+/// 420: YY   422: YY   444: YY
+///      UV        UV        UUVV
+///                UV        UUVV
+/// ```
+pub fn read_frame(path: &Path, chroma_format: Option<ChromaFormat>) -> Result<YuvFrame, Error> {
+    let image = read_graymap(path)?;
+    Ok(split_planes(
+        &image,
+        chroma_format.unwrap_or(ChromaFormat::Yuv420),
+    ))
+}
 
-    let byte_width = 1; // usize::from(header.max_val >= 256) + 1;
+/// Reads a frame in the chroma format its layout suggests, see [`guess_chroma_format`]
+pub fn read_frame_guessing_chroma(path: &Path) -> Result<YuvFrame, Error> {
+    let image = read_graymap(path)?;
+    Ok(split_planes(&image, guess_chroma_format(&image)))
+}
 
-    // We multiply by 2 and divide by 3 because the "gray" part takes 2/3 of the image
-    let img_height = header.height * 2 / 3;
-    let img_width = header.width * byte_width;
+fn split_planes(image: &Netpbm, chroma_format: ChromaFormat) -> YuvFrame {
+    let (h_shift, v_shift) = chroma_format.subsampling();
 
-    // This is dimension for Cr and Cb
-    let channel_width = header.width / 2 * byte_width;
-    let channel_height = header.height / 3;
+    let samples = image.samples_u8();
 
-    let mut y = vec![0; img_width * img_height];
-    let mut u = vec![0; channel_width * channel_height];
-    let mut v = vec![0; channel_width * channel_height];
+    // Each PGM row holds a Cb row followed by a Cr row, luma rows are padded to the same width
+    let channel_width = image.width / 2;
+    let img_width = channel_width << h_shift;
 
-    reader
-        .read_exact(&mut y)
-        .map_err(|_| "Could not read gray")?;
+    // The luma plane takes 2/3 of the rows in 4:2:0, half of them otherwise
+    let img_height = (image.height << v_shift) / ((1 << v_shift) + 1);
+    let channel_height = image.height - img_height;
 
-    for i in 0..channel_height {
-        reader
-            .read_exact(&mut u[i * channel_width..(i + 1) * channel_width])
-            .map_err(|_| "Could not read u")?;
+    // Keep the PGM rows as they are, the planes are read through their stride
+    let (luma, chroma) = samples.split_at(image.width * img_height);
 
-        reader
-            .read_exact(&mut v[i * channel_width..(i + 1) * channel_width])
-            .map_err(|_| "Could not read v")?;
+    YuvFrame {
+        y: Plane::from_vec(img_width, img_height, image.width, luma.to_vec()),
+        cb: Plane::from_vec(channel_width, channel_height, image.width, chroma.to_vec()),
+        cr: Plane::from_vec(
+            channel_width,
+            channel_height,
+            image.width,
+            chroma[channel_width.min(chroma.len())..].to_vec(),
+        ),
+        chroma_format,
     }
+}
 
-    if image.len() != img_width * img_height {
-        *image = RgbImage::with_capacity(img_width, img_height)
-    }
+pub fn decode(path: &Path, image: &mut RgbImage) -> Result<(), Error> {
+    read_frame(path, None)?.to_rgb(image);
 
-    for i in 0..img_height {
-        for j in 0..img_width {
-            let y = f32::from(y[i * img_width + j]) - 16f32;
-            let u = f32::from(u[i / 2 * channel_width + j / 2]) - 128f32;
-            let v = f32::from(v[i / 2 * channel_width + j / 2]) - 128f32;
+    Ok(())
+}
 
-            let r = y + 1.370705f32 * v;
-            let g = y - 0.698001f32 * v - 0.337633f32 * u;
-            let b = y + 1.732446f32 * u;
+/// Writes a frame with the same layout as `mpeg2dec -o pgm`
+pub fn write_frame(path: &Path, frame: &YuvFrame) -> Result<(), Error> {
+    let width = 2 * frame.cb.width();
+    let height = frame.height() + frame.cb.height();
 
-            let r = r.max(0f32).min(255f32) as u8;
-            let g = g.max(0f32).min(255f32) as u8;
-            let b = b.max(0f32).min(255f32) as u8;
+    let mut data = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+    data.reserve(width * height);
 
-            image[i][j] = Rgb::new(r, g, b);
-        }
+    // Luma rows are padded with black up to the width of a Cb + Cr row
+    for row in frame.y.rows() {
+        data.extend_from_slice(row);
+        data.resize(data.len() + width - row.len(), 0);
+    }
+    for (cb, cr) in frame.cb.rows().zip(frame.cr.rows()) {
+        data.extend_from_slice(cb);
+        data.extend_from_slice(cr);
     }
 
-    Ok(())
+    fs::write(path, data).map_err(|e| Error::io(path, e))
 }
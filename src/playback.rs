@@ -0,0 +1,159 @@
+// Audio playback in the viewer
+//
+// The track is decoded beforehand, aligned with the first video time stamp, so the frame
+// presented at `pts` plays the sample at `pts` times the sample rate. Video stays the master
+// clock: the output reads on from where it is, and the viewer moves it back in line when the
+// frame on screen is too far from it, after seeks, pauses or drift between the two clocks.
+// Samples are mixed to the channels of the output device and resampled to its rate by linear
+// interpolation. The output itself needs the `playback` feature, which depends on cpal.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{audio::PcmBuffer, Error};
+
+/// Distance between the audio and the video past which the audio jumps to the video
+const SYNC_TOLERANCE: Duration = Duration::from_millis(80);
+
+/// Position of the output in a decoded track.
+#[derive(Debug, Clone)]
+pub struct PlaybackState {
+    track: PcmBuffer,
+    /// In samples per channel of the track, between two samples when resampling
+    position: f64,
+    playing: bool,
+}
+
+impl PlaybackState {
+    /// Paused at the start of `track`
+    pub fn new(track: PcmBuffer) -> Self {
+        Self {
+            track,
+            position: 0.0,
+            playing: false,
+        }
+    }
+
+    /// Time of the next sample to play
+    pub fn position(&self) -> Duration {
+        Duration::from_secs_f64(self.position / f64::from(self.track.sample_rate.max(1)))
+    }
+
+    pub fn playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Follows the video, showing the frame due at `pts`
+    pub fn sync(&mut self, pts: Duration, playing: bool) {
+        let target = pts.as_secs_f64() * f64::from(self.track.sample_rate);
+        let tolerance = SYNC_TOLERANCE.as_secs_f64() * f64::from(self.track.sample_rate);
+        if (playing && !self.playing) || (self.position - target).abs() > tolerance {
+            self.position = target;
+        }
+        self.playing = playing;
+    }
+
+    /// Fills interleaved output samples of `channels` channels at `sample_rate`, with silence
+    /// when paused or past the end of the track
+    pub fn fill(&mut self, output: &mut [f32], channels: usize, sample_rate: u32) {
+        let track_channels = usize::from(self.track.channels);
+        let frames = self.track.samples.len() / track_channels.max(1);
+        let step = f64::from(self.track.sample_rate) / f64::from(sample_rate.max(1));
+        let channels = channels.max(1);
+
+        for out in output.chunks_mut(channels) {
+            let index = self.position.floor();
+            if !self.playing || track_channels == 0 || index < 0.0 || index as usize >= frames {
+                out.fill(0.0);
+                if self.playing {
+                    self.position += step;
+                }
+                continue;
+            }
+
+            let index = index as usize;
+            let next = (index + 1).min(frames - 1);
+            let fraction = (self.position - index as f64) as f32;
+            let sample = |frame: usize, channel: usize| {
+                f32::from(self.track.samples[frame * track_channels + channel]) / 32768.0
+            };
+            let channel = |channel: usize| {
+                sample(index, channel) * (1.0 - fraction) + sample(next, channel) * fraction
+            };
+
+            match (track_channels, out.len()) {
+                // Both channels on a mono output
+                (2, 1) => out[0] = (channel(0) + channel(1)) / 2.0,
+                _ => {
+                    for (i, value) in out.iter_mut().enumerate() {
+                        *value = channel(i % track_channels);
+                    }
+                }
+            }
+            self.position += step;
+        }
+    }
+}
+
+/// Audio output of the viewer, playing a track in step with the video.
+pub struct AudioPlayback {
+    state: Arc<Mutex<PlaybackState>>,
+    #[cfg(feature = "playback")]
+    _stream: cpal::Stream,
+}
+
+impl AudioPlayback {
+    /// Plays `track` on the default output device
+    #[cfg(feature = "playback")]
+    pub fn open(track: PcmBuffer) -> Result<Self, Error> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let output_error = |reason: String| Error::AudioOutput { reason };
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| output_error("no audio output device".to_string()))?;
+        let config: cpal::StreamConfig = device
+            .default_output_config()
+            .map_err(|e| output_error(e.to_string()))?
+            .into();
+
+        let state = Arc::new(Mutex::new(PlaybackState::new(track)));
+        let output = Arc::clone(&state);
+        let channels = usize::from(config.channels);
+        let sample_rate = config.sample_rate.0;
+        let stream = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _| match output.lock() {
+                    Ok(mut state) => state.fill(data, channels, sample_rate),
+                    Err(_) => data.fill(0.0),
+                },
+                |err| eprintln!("Audio output: {}", err),
+                None,
+            )
+            .map_err(|e| output_error(e.to_string()))?;
+        stream.play().map_err(|e| output_error(e.to_string()))?;
+
+        Ok(Self {
+            state,
+            _stream: stream,
+        })
+    }
+
+    /// Fails, the audio output needs the `playback` feature
+    #[cfg(not(feature = "playback"))]
+    pub fn open(_track: PcmBuffer) -> Result<Self, Error> {
+        Err(Error::AudioOutput {
+            reason: "built without the `playback` feature".to_string(),
+        })
+    }
+
+    /// Follows the video, showing the frame due at `pts`
+    pub fn sync(&self, pts: Duration, playing: bool) {
+        if let Ok(mut state) = self.state.lock() {
+            state.sync(pts, playing);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+// Headerless raw YUV files, one frame after the other
+//
+// I420: Y plane, then Cb and Cr planes at quarter size
+// NV12: Y plane, then a half-height plane of interleaved Cb Cr samples
+// YUY2: packed 4:2:2, Y0 Cb Y1 Cr
+// UYVY: packed 4:2:2, Cb Y0 Cr Y1
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    flag::ChromaFormat,
+    source::{FrameSource, SourceFrame},
+    yuv::{Plane, YuvFrame},
+    Error,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    I420,
+    Nv12,
+    Yuy2,
+    Uyvy,
+}
+
+impl PixelFormat {
+    pub fn chroma_format(&self) -> ChromaFormat {
+        match self {
+            PixelFormat::I420 | PixelFormat::Nv12 => ChromaFormat::Yuv420,
+            PixelFormat::Yuy2 | PixelFormat::Uyvy => ChromaFormat::Yuv422,
+        }
+    }
+
+    /// Size in bytes of a frame, `None` if it overflows
+    pub fn frame_size(&self, width: usize, height: usize) -> Option<usize> {
+        let chroma_width = width.div_ceil(2);
+        match self {
+            PixelFormat::I420 | PixelFormat::Nv12 => {
+                let chroma = chroma_width
+                    .checked_mul(height.div_ceil(2))?
+                    .checked_mul(2)?;
+                width.checked_mul(height)?.checked_add(chroma)
+            }
+            PixelFormat::Yuy2 | PixelFormat::Uyvy => {
+                chroma_width.checked_mul(height)?.checked_mul(4)
+            }
+        }
+    }
+
+    /// Unpacks a frame of `frame_size` bytes
+    pub fn decode(&self, data: &[u8], width: usize, height: usize) -> YuvFrame {
+        let mut frame = YuvFrame::new(width, height, self.chroma_format());
+        let chroma_width = frame.cb.width();
+        let chroma_height = frame.cb.height();
+
+        match self {
+            PixelFormat::I420 => {
+                let (luma, chroma) = data.split_at(width * height);
+                let (cb, cr) = chroma.split_at(chroma_width * chroma_height);
+
+                frame.y = Plane::from_vec(width, height, width, luma.to_vec());
+                frame.cb = Plane::from_vec(chroma_width, chroma_height, chroma_width, cb.to_vec());
+                frame.cr = Plane::from_vec(chroma_width, chroma_height, chroma_width, cr.to_vec());
+            }
+            PixelFormat::Nv12 => {
+                let (luma, chroma) = data.split_at(width * height);
+                frame.y = Plane::from_vec(width, height, width, luma.to_vec());
+
+                for (y, row) in chroma.chunks_exact(2 * chroma_width).enumerate() {
+                    for (x, pair) in row.chunks_exact(2).enumerate() {
+                        frame.cb.set(x, y, pair[0]);
+                        frame.cr.set(x, y, pair[1]);
+                    }
+                }
+            }
+            PixelFormat::Yuy2 | PixelFormat::Uyvy => {
+                // Offsets of Y0, Cb, Y1 and Cr in a macropixel
+                let [y0, cb, y1, cr] = match self {
+                    PixelFormat::Yuy2 => [0, 1, 2, 3],
+                    _ => [1, 0, 3, 2],
+                };
+
+                for (y, row) in data.chunks_exact(4 * chroma_width).enumerate() {
+                    for (x, macropixel) in row.chunks_exact(4).enumerate() {
+                        frame.y.set(2 * x, y, macropixel[y0]);
+                        if 2 * x + 1 < width {
+                            frame.y.set(2 * x + 1, y, macropixel[y1]);
+                        }
+                        frame.cb.set(x, y, macropixel[cb]);
+                        frame.cr.set(x, y, macropixel[cr]);
+                    }
+                }
+            }
+        }
+
+        frame
+    }
+
+    /// Packs a frame, converting its chroma format if needed
+    pub fn encode(&self, frame: &YuvFrame, out: &mut Vec<u8>) {
+        let frame = frame.with_chroma_format(self.chroma_format());
+        let width = frame.width();
+
+        match self {
+            PixelFormat::I420 => {
+                for plane in frame.planes() {
+                    out.extend(plane.rows().flatten());
+                }
+            }
+            PixelFormat::Nv12 => {
+                out.extend(frame.y.rows().flatten());
+                for (cb, cr) in frame.cb.rows().zip(frame.cr.rows()) {
+                    out.extend(cb.iter().zip(cr).flat_map(|(cb, cr)| [*cb, *cr]));
+                }
+            }
+            PixelFormat::Yuy2 | PixelFormat::Uyvy => {
+                for y in 0..frame.height() {
+                    let luma = frame.y.row(y);
+                    let cb = frame.cb.row(y);
+                    let cr = frame.cr.row(y);
+
+                    for x in 0..cb.len() {
+                        let y0 = luma[2 * x];
+                        let y1 = if 2 * x + 1 < width {
+                            luma[2 * x + 1]
+                        } else {
+                            y0
+                        };
+                        out.extend(match self {
+                            PixelFormat::Yuy2 => [y0, cb[x], y1, cr[x]],
+                            _ => [cb[x], y0, cr[x], y1],
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for PixelFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "i420" | "yuv420p" => Ok(PixelFormat::I420),
+            "nv12" => Ok(PixelFormat::Nv12),
+            "yuy2" | "yuyv" => Ok(PixelFormat::Yuy2),
+            "uyvy" => Ok(PixelFormat::Uyvy),
+            _ => Err(format!("unknown pixel format `{}`", s)),
+        }
+    }
+}
+
+/// Headerless raw YUV file of known dimensions and pixel format.
+pub struct RawYuvSource {
+    path: PathBuf,
+    reader: BufReader<File>,
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    frame_size: usize,
+    frame_count: usize,
+}
+
+impl RawYuvSource {
+    /// Fails with `Error::NoFrames` if the frames are empty or the file is shorter than one,
+    /// which frames too large to address always are
+    pub fn open(
+        path: &Path,
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+    ) -> Result<Self, Error> {
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        let len = file.metadata().map_err(|e| Error::io(path, e))?.len() as usize;
+        let frame_size = format
+            .frame_size(width, height)
+            .filter(|size| *size > 0)
+            .ok_or(Error::NoFrames)?;
+        let frame_count = len / frame_size;
+        if frame_count == 0 {
+            return Err(Error::NoFrames);
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader: BufReader::new(file),
+            width,
+            height,
+            format,
+            frame_size,
+            frame_count,
+        })
+    }
+}
+
+impl FrameSource for RawYuvSource {
+    fn frame_count(&self) -> Option<usize> {
+        Some(self.frame_count)
+    }
+
+    fn seek(&mut self, index: usize) -> Result<(), Error> {
+        // Past the last frame, reading ends all the same, and the offset stays in the file
+        let offset = index.min(self.frame_count) * self.frame_size;
+        self.reader
+            .seek(SeekFrom::Start(offset as u64))
+            .map_err(|e| Error::io(&self.path, e))?;
+
+        Ok(())
+    }
+
+    fn next_frame(&mut self) -> Result<Option<SourceFrame>, Error> {
+        let mut data = vec![0; self.frame_size];
+
+        let mut read = 0;
+        while read < data.len() {
+            match self.reader.read(&mut data[read..]) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(Error::io(&self.path, e)),
+            }
+        }
+
+        // A truncated last frame is ignored, like the frame count does
+        if read < data.len() {
+            return Ok(None);
+        }
+
+        Ok(Some(SourceFrame {
+            frame: self.format.decode(&data, self.width, self.height),
+            picture: None,
+            path: Some(self.path.clone()),
+            pts: None,
+        }))
+    }
+}
+
+/// Appends frames to a headerless raw YUV file.
+pub struct RawYuvWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    format: PixelFormat,
+    buffer: Vec<u8>,
+}
+
+impl RawYuvWriter {
+    pub fn create(path: &Path, format: PixelFormat) -> Result<Self, Error> {
+        let file = File::create(path).map_err(|e| Error::io(path, e))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            format,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn write_frame(&mut self, frame: &YuvFrame) -> Result<(), Error> {
+        self.buffer.clear();
+        self.format.encode(frame, &mut self.buffer);
+
+        self.writer
+            .write_all(&self.buffer)
+            .map_err(|e| Error::io(&self.path, e))
+    }
+
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.writer.flush().map_err(|e| Error::io(&self.path, e))
+    }
+}
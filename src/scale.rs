@@ -0,0 +1,177 @@
+// Separable resampling of planar frames
+//
+// Rows are resampled first, into floating point samples, then columns. Each
+// output sample is a weighted sum of the input samples under the kernel,
+// centred on its position in the input. When shrinking, the kernel is widened
+// by the scale factor so that it also filters out what the output cannot hold.
+
+use std::{f32::consts::PI, fmt, str::FromStr};
+
+use crate::yuv::{Plane, YuvFrame};
+
+/// Interpolation kernel of the scaler.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Kernel {
+    /// Triangle over the two nearest samples
+    Bilinear,
+    /// Keys cubic convolution with a = -0.5 over four samples
+    Bicubic,
+    /// Windowed sinc over six samples
+    #[default]
+    Lanczos,
+}
+
+impl Kernel {
+    pub const ALL: [Kernel; 3] = [Kernel::Bilinear, Kernel::Bicubic, Kernel::Lanczos];
+
+    /// Distance in samples beyond which the kernel is zero
+    pub fn radius(&self) -> f32 {
+        match self {
+            Kernel::Bilinear => 1.0,
+            Kernel::Bicubic => 2.0,
+            Kernel::Lanczos => 3.0,
+        }
+    }
+
+    /// Weight of a sample `x` samples away
+    pub fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        if x >= self.radius() {
+            return 0.0;
+        }
+
+        match self {
+            Kernel::Bilinear => 1.0 - x,
+            Kernel::Bicubic => {
+                const A: f32 = -0.5;
+                if x < 1.0 {
+                    ((A + 2.0) * x - (A + 3.0)) * x * x + 1.0
+                } else {
+                    ((A * x - 5.0 * A) * x + 8.0 * A) * x - 4.0 * A
+                }
+            }
+            Kernel::Lanczos => sinc(x) * sinc(x / self.radius()),
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl fmt::Display for Kernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kernel::Bilinear => "bilinear",
+            Kernel::Bicubic => "bicubic",
+            Kernel::Lanczos => "lanczos",
+        })
+    }
+}
+
+impl FromStr for Kernel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bilinear" | "linear" => Ok(Kernel::Bilinear),
+            "bicubic" | "cubic" => Ok(Kernel::Bicubic),
+            "lanczos" | "lanczos3" => Ok(Kernel::Lanczos),
+            _ => Err(format!(
+                "unknown kernel `{}`, expected bilinear, bicubic or lanczos",
+                s
+            )),
+        }
+    }
+}
+
+/// Input samples and their normalised weights, for every output sample of a row or column
+fn contributions(src_len: usize, dst_len: usize, kernel: Kernel) -> Vec<Vec<(usize, f32)>> {
+    let scale = src_len as f32 / dst_len as f32;
+    let support = scale.max(1.0);
+    let radius = kernel.radius() * support;
+    let last = src_len as isize - 1;
+
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale - 0.5;
+            let first = (center - radius).floor() as isize;
+            let end = (center + radius).ceil() as isize;
+
+            let mut taps: Vec<(usize, f32)> = (first..=end)
+                .map(|j| {
+                    // Edge samples are repeated
+                    let index = j.clamp(0, last) as usize;
+                    (index, kernel.weight((j as f32 - center) / support))
+                })
+                .filter(|(_, weight)| *weight != 0.0)
+                .collect();
+
+            let sum: f32 = taps.iter().map(|(_, weight)| weight).sum();
+            for (_, weight) in &mut taps {
+                *weight /= sum;
+            }
+            taps
+        })
+        .collect()
+}
+
+/// Copy of a plane resampled to `width`×`height`
+pub fn scale_plane(plane: &Plane, width: usize, height: usize, kernel: Kernel) -> Plane {
+    if (width, height) == (plane.width(), plane.height()) {
+        return plane.clone();
+    }
+    if plane.width() == 0 || plane.height() == 0 || width == 0 || height == 0 {
+        return Plane::new(width, height, 0);
+    }
+
+    let columns = contributions(plane.width(), width, kernel);
+    let rows = contributions(plane.height(), height, kernel);
+
+    // Rows first, `width` samples for each input row
+    let mut horizontal = vec![0f32; width * plane.height()];
+    for (y, out) in horizontal.chunks_exact_mut(width).enumerate() {
+        let src = plane.row(y);
+        for (sample, taps) in out.iter_mut().zip(&columns) {
+            *sample = taps
+                .iter()
+                .map(|(x, weight)| f32::from(src[*x]) * weight)
+                .sum();
+        }
+    }
+
+    let mut out = Plane::new(width, height, 0);
+    let mut sums = vec![0f32; width];
+    for (y, taps) in rows.iter().enumerate() {
+        sums.fill(0.0);
+        for (row, weight) in taps {
+            let src = &horizontal[row * width..(row + 1) * width];
+            for (sum, sample) in sums.iter_mut().zip(src) {
+                *sum += sample * weight;
+            }
+        }
+        for (sample, sum) in out.row_mut(y).iter_mut().zip(&sums) {
+            *sample = sum.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    out
+}
+
+/// Copy of a frame resampled to `width`×`height`, chroma planes keeping their subsampling
+pub fn resize(frame: &YuvFrame, width: usize, height: usize, kernel: Kernel) -> YuvFrame {
+    let (h_shift, v_shift) = frame.chroma_format.subsampling();
+    let chroma_width = (width + (1 << h_shift) - 1) >> h_shift;
+    let chroma_height = (height + (1 << v_shift) - 1) >> v_shift;
+
+    YuvFrame {
+        y: scale_plane(&frame.y, width, height, kernel),
+        cb: scale_plane(&frame.cb, chroma_width, chroma_height, kernel),
+        cr: scale_plane(&frame.cr, chroma_width, chroma_height, kernel),
+        chroma_format: frame.chroma_format,
+    }
+}
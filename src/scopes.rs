@@ -0,0 +1,108 @@
+use ndarray::Array2;
+
+use crate::{image::Rgb, yuv::YuvFrame, RgbImage};
+
+/// Number of distinct 8-bit sample values, used as the scopes resolution.
+pub const LEVELS: usize = 256;
+
+/// Luma waveform: for each image column, how many pixels have a given luma value.
+///
+/// Indexed by `[(255 - luma, column)]` so that row 0 is the top (white) of the scope.
+pub fn waveform(frame: &YuvFrame) -> Array2<u32> {
+    let mut counts = Array2::zeros((LEVELS, frame.width()));
+
+    for line in frame.y.rows() {
+        for (column, luma) in line.iter().enumerate() {
+            counts[(LEVELS - 1 - *luma as usize, column)] += 1;
+        }
+    }
+
+    counts
+}
+
+/// Cb/Cr scatter plot: how many pixels have a given chroma pair.
+///
+/// Indexed by `[(255 - cr, cb)]` so that red sits in the upper part of the scope.
+pub fn vectorscope(frame: &YuvFrame) -> Array2<u32> {
+    let mut counts = Array2::zeros((LEVELS, LEVELS));
+
+    for (cb, cr) in frame.cb.rows().zip(frame.cr.rows()) {
+        for (cb, cr) in cb.iter().zip(cr) {
+            counts[(LEVELS - 1 - *cr as usize, *cb as usize)] += 1;
+        }
+    }
+
+    counts
+}
+
+/// Per-channel histograms of the luma plane and of the converted RGB image.
+pub struct Histogram {
+    pub luma: [u32; LEVELS],
+    pub red: [u32; LEVELS],
+    pub green: [u32; LEVELS],
+    pub blue: [u32; LEVELS],
+}
+
+impl Histogram {
+    pub fn max(&self) -> u32 {
+        [&self.luma, &self.red, &self.green, &self.blue]
+            .iter()
+            .flat_map(|channel| channel.iter())
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+pub fn histogram(frame: &YuvFrame, image: &RgbImage) -> Histogram {
+    let mut histogram = Histogram {
+        luma: [0; LEVELS],
+        red: [0; LEVELS],
+        green: [0; LEVELS],
+        blue: [0; LEVELS],
+    };
+
+    for luma in frame.y.rows().flatten() {
+        histogram.luma[*luma as usize] += 1;
+    }
+
+    for Rgb { r, g, b } in image.get_data() {
+        histogram.red[*r as usize] += 1;
+        histogram.green[*g as usize] += 1;
+        histogram.blue[*b as usize] += 1;
+    }
+
+    histogram
+}
+
+/// Vectorscope target of a color bar.
+pub struct BarTarget {
+    pub name: &'static str,
+    pub cb: u8,
+    pub cr: u8,
+}
+
+/// Cb/Cr positions of the 75% color bars (BT.601, studio range), in bar order.
+pub fn bar_targets_75() -> [BarTarget; 6] {
+    // (name, R, G, B) with 75% amplitude
+    const BARS: [(&str, f32, f32, f32); 6] = [
+        ("Yl", 0.75, 0.75, 0.0),
+        ("Cy", 0.0, 0.75, 0.75),
+        ("G", 0.0, 0.75, 0.0),
+        ("Mg", 0.75, 0.0, 0.75),
+        ("R", 0.75, 0.0, 0.0),
+        ("B", 0.0, 0.0, 0.75),
+    ];
+
+    BARS.map(|(name, r, g, b)| {
+        let y = 0.299 * r + 0.587 * g + 0.114 * b;
+        let cb = (b - y) / 1.772;
+        let cr = (r - y) / 1.402;
+
+        BarTarget {
+            name,
+            cb: (128.0 + 224.0 * cb).round() as u8,
+            cr: (128.0 + 224.0 * cr).round() as u8,
+        }
+    })
+}
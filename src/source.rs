@@ -0,0 +1,132 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    flag::ChromaFormat,
+    metadata_parser::Picture,
+    pgm_parser::{read_frame, read_frame_guessing_chroma},
+    read_files,
+    yuv::YuvFrame,
+    Error,
+};
+
+/// A decoded frame and everything known about it.
+pub struct SourceFrame {
+    pub frame: YuvFrame,
+    /// Stream metadata of the frame, if the source has any
+    pub picture: Option<Picture>,
+    /// File the frame was read from, if any
+    pub path: Option<PathBuf>,
+    /// Presentation time relative to the first frame, from the stream metadata or PES headers
+    pub pts: Option<Duration>,
+}
+
+/// Anything that yields planar YUV frames in display order.
+pub trait FrameSource {
+    /// Number of frames, if known without reading the whole source
+    fn frame_count(&self) -> Option<usize>;
+
+    /// Moves the source so that the next call to `next_frame` returns frame `index`
+    fn seek(&mut self, index: usize) -> Result<(), Error>;
+
+    /// Reads the next frame, `None` once the end of the source is reached
+    fn next_frame(&mut self) -> Result<Option<SourceFrame>, Error>;
+
+    /// Stream metadata of every frame in display order, if the source has any
+    fn pictures(&self) -> Option<&[Picture]> {
+        None
+    }
+}
+
+/// Directory of `mpeg2dec -o pgm` frames with an optional `tvid.log`.
+pub struct PgmSource {
+    files: Vec<PathBuf>,
+    meta: Option<Vec<Picture>>,
+    chroma_format: Option<ChromaFormat>,
+    guess_chroma: bool,
+    position: usize,
+}
+
+impl PgmSource {
+    pub fn new(
+        files: Vec<PathBuf>,
+        meta: Option<Vec<Picture>>,
+        chroma_format: Option<ChromaFormat>,
+    ) -> Self {
+        Self {
+            files,
+            meta,
+            chroma_format,
+            guess_chroma: false,
+            position: 0,
+        }
+    }
+
+    /// Guesses the chroma format of the frames that neither `chroma_format` nor tvid.log
+    /// describe from their PGM layout, see `guess_chroma_format`, instead of assuming 4:2:0
+    pub fn with_chroma_guess(mut self, guess: bool) -> Self {
+        self.guess_chroma = guess;
+        self
+    }
+
+    /// Opens a directory, reading `tvid.log` if there is one
+    pub fn open(dir: &Path, chroma_format: Option<ChromaFormat>) -> Result<Self, Error> {
+        let files = read_files(&dir.to_string_lossy())?;
+
+        let log = dir.join("tvid.log");
+        let meta = if log.exists() {
+            let meta = crate::meta_decode(&log)?;
+            crate::check_frame_count(&files, &meta)?;
+            Some(meta)
+        } else {
+            None
+        };
+
+        Ok(Self::new(files, meta, chroma_format))
+    }
+}
+
+impl FrameSource for PgmSource {
+    fn frame_count(&self) -> Option<usize> {
+        Some(self.files.len())
+    }
+
+    fn seek(&mut self, index: usize) -> Result<(), Error> {
+        self.position = index;
+        Ok(())
+    }
+
+    fn next_frame(&mut self) -> Result<Option<SourceFrame>, Error> {
+        let Some(path) = self.files.get(self.position) else {
+            return Ok(None);
+        };
+
+        let picture = self
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.get(self.position))
+            .cloned();
+        let chroma_format = self
+            .chroma_format
+            .or_else(|| picture.as_ref().and_then(|picture| picture.chroma_format));
+
+        let frame = match chroma_format {
+            None if self.guess_chroma => read_frame_guessing_chroma(path)?,
+            chroma_format => read_frame(path, chroma_format)?,
+        };
+        self.position += 1;
+
+        Ok(Some(SourceFrame {
+            frame,
+            pts: picture.as_ref().map(|picture| picture.pts),
+            picture,
+            path: Some(path.clone()),
+        }))
+    }
+
+    fn pictures(&self) -> Option<&[Picture]> {
+        self.meta.as_deref()
+    }
+}
@@ -0,0 +1,136 @@
+use std::{fmt, time::Duration};
+
+/// Frequency of the MPEG-2 system clock, in which frame periods are expressed.
+pub const SYSTEM_CLOCK_HZ: u64 = 27_000_000;
+
+/// Frequency of presentation and decoding time stamps, the system clock divided by 300.
+pub const PTS_CLOCK_HZ: u64 = 90_000;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Frame duration used without stream metadata nor requested frame rate.
+pub(crate) const DEFAULT_FRAME_DURATION: Duration = Duration::from_millis(40);
+
+/// Duration of `ticks` periods of the 27 MHz system clock, rounded to the nanosecond
+pub fn duration_from_27mhz(ticks: u64) -> Duration {
+    fields_duration(ticks, 2)
+}
+
+/// Duration of `ticks` periods of the 90 kHz time stamp clock, rounded to the nanosecond
+pub fn duration_from_90khz(ticks: u64) -> Duration {
+    Duration::from_nanos(div_round(
+        ticks * (NANOS_PER_SEC / 10_000),
+        PTS_CLOCK_HZ / 10_000,
+    ))
+}
+
+/// Duration of `fields` fields of frames lasting `frame_period` ticks of the 27 MHz clock
+///
+/// Counting fields keeps repeated first fields, which last half a frame period, exact.
+pub fn fields_duration(frame_period: u64, fields: u64) -> Duration {
+    // Whole seconds apart, so that no product overflows
+    let field_clock = 2 * SYSTEM_CLOCK_HZ;
+    let ticks = u128::from(fields) * u128::from(frame_period);
+    let secs = u64::try_from(ticks / u128::from(field_clock)).unwrap_or(u64::MAX);
+    let rest = (ticks % u128::from(field_clock)) as u64;
+    let nanos = div_round(rest * 1000, field_clock / 1_000_000);
+
+    Duration::from_secs(secs).saturating_add(Duration::from_nanos(nanos))
+}
+
+/// Number of fields a picture is displayed for, frame repetition counting as two fields
+///
+/// In progressive sequences repeat_first_field repeats the whole frame, twice more with
+/// top_field_first. In interlaced ones it repeats the first field.
+pub fn displayed_fields(
+    progressive_sequence: bool,
+    repeat_first_field: bool,
+    top_field_first: bool,
+) -> u64 {
+    match (progressive_sequence, repeat_first_field, top_field_first) {
+        (true, false, _) => 2,
+        (true, true, false) => 4,
+        (true, true, true) => 6,
+        (false, repeat_first_field, _) => 2 + u64::from(repeat_first_field),
+    }
+}
+
+/// Bits per second needed to send `size` bytes in one frame period of `frame_period` ticks of
+/// the 27 MHz clock, whatever the number of fields the picture is displayed for
+pub fn bitrate(size: usize, frame_period: u64) -> f64 {
+    size as f64 * 8.0 * SYSTEM_CLOCK_HZ as f64 / frame_period as f64
+}
+
+fn div_round(num: u64, den: u64) -> u64 {
+    (num + den / 2) / den
+}
+
+pub(crate) fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Exact frame rate, as a reduced fraction of frames per second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameRate {
+    num: u64,
+    den: u64,
+}
+
+impl FrameRate {
+    /// # Panics
+    ///
+    /// If `den` is zero.
+    pub fn new(num: u64, den: u64) -> Self {
+        assert!(den != 0, "frame rate with a zero denominator");
+
+        let divisor = gcd(num, den).max(1);
+        Self {
+            num: num / divisor,
+            den: den / divisor,
+        }
+    }
+
+    /// Frame rate of a sequence whose frames last `frame_period` ticks of the 27 MHz clock
+    pub fn from_frame_period(frame_period: u64) -> Self {
+        Self::new(SYSTEM_CLOCK_HZ, frame_period)
+    }
+
+    pub fn num(&self) -> u64 {
+        self.num
+    }
+
+    pub fn den(&self) -> u64 {
+        self.den
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Duration of one frame, rounded to the nanosecond
+    ///
+    /// # Panics
+    ///
+    /// If the frame rate is zero.
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_nanos(div_round(self.den * NANOS_PER_SEC, self.num))
+    }
+
+    /// Duration of `frames` frames, without accumulating rounding errors
+    pub fn frames_duration(&self, frames: u64) -> Duration {
+        Duration::from_nanos(div_round(frames * self.den * NANOS_PER_SEC, self.num))
+    }
+}
+
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
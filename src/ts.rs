@@ -0,0 +1,501 @@
+// MPEG-2 transport streams, see ISO/IEC 13818-1
+//
+// 188-byte packets starting with the 0x47 sync byte, each carrying a part of
+// the stream identified by its 13-bit PID. The program association table on
+// PID 0 lists the PIDs of the program map tables, which list the elementary
+// streams of each program. Elementary streams are carried in PES packets,
+// whose headers hold the 33-bit presentation and decoding time stamps.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::Error;
+
+/// Size of a transport stream packet in bytes.
+pub const PACKET_SIZE: usize = 188;
+
+const SYNC_BYTE: u8 = 0x47;
+const PAT_PID: u16 = 0x0000;
+const NULL_PID: u16 = 0x1fff;
+
+const REGISTRATION_DESCRIPTOR: u8 = 0x05;
+const AC3_DESCRIPTOR: u8 = 0x6a;
+
+/// Time stamps are 33-bit counters of the 90 kHz clock.
+const PTS_MODULO: u64 = 1 << 33;
+
+/// Signed difference `a - b` of two time stamps, accounting for the wrap around of the counter
+pub fn pts_delta(a: u64, b: u64) -> i64 {
+    let delta = (a.wrapping_sub(b)) % PTS_MODULO;
+    if delta >= PTS_MODULO / 2 {
+        delta as i64 - PTS_MODULO as i64
+    } else {
+        delta as i64
+    }
+}
+
+/// Descriptor of an elementary stream in the program map table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Descriptor {
+    pub tag: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    /// MPEG-1 or MPEG-2 video
+    Video,
+    /// MPEG-1 or MPEG-2 audio, Layer I, II or III
+    MpegAudio,
+    /// AC-3 audio, ATSC style or DVB private data
+    Ac3,
+    Other,
+}
+
+/// Elementary stream of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementaryStream {
+    pub pid: u16,
+    pub stream_type: u8,
+    pub descriptors: Vec<Descriptor>,
+}
+
+impl ElementaryStream {
+    pub fn kind(&self) -> StreamKind {
+        match self.stream_type {
+            0x01 | 0x02 => StreamKind::Video,
+            0x03 | 0x04 => StreamKind::MpegAudio,
+            0x81 => StreamKind::Ac3,
+            // DVB carries AC-3 as private data with an AC-3 descriptor
+            0x06 if self.descriptor(AC3_DESCRIPTOR).is_some() => StreamKind::Ac3,
+            0x06 if self
+                .descriptor(REGISTRATION_DESCRIPTOR)
+                .is_some_and(|data| data.starts_with(b"AC-3")) =>
+            {
+                StreamKind::Ac3
+            }
+            _ => StreamKind::Other,
+        }
+    }
+
+    /// Data of the first descriptor with the given tag
+    pub fn descriptor(&self, tag: u8) -> Option<&[u8]> {
+        self.descriptors
+            .iter()
+            .find(|descriptor| descriptor.tag == tag)
+            .map(|descriptor| &descriptor.data[..])
+    }
+}
+
+/// Program described by a program map table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub number: u16,
+    pub pmt_pid: u16,
+    pub streams: Vec<ElementaryStream>,
+}
+
+/// Reassembled PES packet of an elementary stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PesPacket {
+    pub pid: u16,
+    pub stream_id: u8,
+    /// Presentation time stamp, in ticks of the 90 kHz clock
+    pub pts: Option<u64>,
+    /// Decoding time stamp, in ticks of the 90 kHz clock
+    pub dts: Option<u64>,
+    /// Elementary stream bytes
+    pub payload: Vec<u8>,
+    /// Offset in the transport stream of the packet that started the PES packet
+    pub offset: u64,
+}
+
+/// PES packet being reassembled.
+struct PesBuilder {
+    offset: u64,
+    data: Vec<u8>,
+}
+
+/// Reads a transport stream and yields the PES packets of the elementary streams it announces.
+///
+/// Packets of an elementary stream are only collected once its program map table has been read,
+/// and PES packets interrupted by a continuity counter discontinuity are dropped.
+pub struct TsDemuxer<R> {
+    reader: R,
+    path: PathBuf,
+    /// Offset of the next packet
+    offset: u64,
+    programs: Vec<Program>,
+    /// Program number of every program map table PID
+    pmt_pids: HashMap<u16, u16>,
+    /// Table sections being reassembled
+    sections: HashMap<u16, Vec<u8>>,
+    pes: HashMap<u16, PesBuilder>,
+    continuity: HashMap<u16, u8>,
+    ready: VecDeque<PesPacket>,
+    eof: bool,
+}
+
+impl TsDemuxer<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let file = File::open(path).map_err(|e| Error::io(path, e))?;
+        Ok(Self::new(BufReader::new(file), path))
+    }
+}
+
+impl<R: Read> TsDemuxer<R> {
+    /// `path` only names the stream in errors
+    pub fn new(reader: R, path: &Path) -> Self {
+        Self {
+            reader,
+            path: path.to_path_buf(),
+            offset: 0,
+            programs: Vec::new(),
+            pmt_pids: HashMap::new(),
+            sections: HashMap::new(),
+            pes: HashMap::new(),
+            continuity: HashMap::new(),
+            ready: VecDeque::new(),
+            eof: false,
+        }
+    }
+
+    /// Path naming the stream in errors
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Programs read so far
+    pub fn programs(&self) -> &[Program] {
+        &self.programs
+    }
+
+    /// Elementary streams of all the programs read so far
+    pub fn streams(&self) -> impl Iterator<Item = &ElementaryStream> {
+        self.programs.iter().flat_map(|program| &program.streams)
+    }
+
+    /// Reads the next complete PES packet, `None` at the end of the stream
+    pub fn next_pes(&mut self) -> Result<Option<PesPacket>, Error> {
+        while self.ready.is_empty() && !self.eof {
+            match self.read_packet()? {
+                Some(packet) => self.handle_packet(&packet),
+                None => {
+                    self.eof = true;
+                    let mut pids: Vec<u16> = self.pes.keys().copied().collect();
+                    pids.sort_unstable();
+                    for pid in pids {
+                        self.finish_pes(pid);
+                    }
+                }
+            }
+        }
+
+        Ok(self.ready.pop_front())
+    }
+
+    /// Reads the next packet, skipping bytes until a sync byte if needed
+    fn read_packet(&mut self) -> Result<Option<[u8; PACKET_SIZE]>, Error> {
+        let mut packet = [0; PACKET_SIZE];
+        if !self.fill(&mut packet)? {
+            return Ok(None);
+        }
+
+        while packet[0] != SYNC_BYTE {
+            let skip = packet
+                .iter()
+                .position(|b| *b == SYNC_BYTE)
+                .unwrap_or(PACKET_SIZE);
+            packet.copy_within(skip.., 0);
+            self.offset += skip as u64;
+            if !self.fill(&mut packet[PACKET_SIZE - skip..])? {
+                return Ok(None);
+            }
+        }
+
+        self.offset += PACKET_SIZE as u64;
+        Ok(Some(packet))
+    }
+
+    /// Fills `buf` completely, `false` if the stream ends first
+    fn fill(&mut self, buf: &mut [u8]) -> Result<bool, Error> {
+        let mut read = 0;
+        while read < buf.len() {
+            match self.reader.read(&mut buf[read..]) {
+                Ok(0) => return Ok(false),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(Error::io(&self.path, e)),
+            }
+        }
+        Ok(true)
+    }
+
+    fn handle_packet(&mut self, packet: &[u8; PACKET_SIZE]) {
+        let offset = self.offset - PACKET_SIZE as u64;
+        let transport_error = packet[1] & 0x80 != 0;
+        let unit_start = packet[1] & 0x40 != 0;
+        let pid = u16::from_be_bytes([packet[1] & 0x1f, packet[2]]);
+        let adaptation_field = packet[3] & 0x20 != 0;
+        let has_payload = packet[3] & 0x10 != 0;
+        let counter = packet[3] & 0x0f;
+
+        if transport_error || pid == NULL_PID || !has_payload {
+            return;
+        }
+
+        // Duplicate packets carry the same counter, lost packets break the sequence
+        let previous = self.continuity.insert(pid, counter);
+        if previous == Some(counter) {
+            return;
+        }
+        let discontinuity = previous.is_some_and(|previous| (previous + 1) & 0x0f != counter);
+
+        let mut start = 4;
+        if adaptation_field {
+            start += 1 + usize::from(packet[4]);
+        }
+        if start >= PACKET_SIZE {
+            return;
+        }
+        let payload = &packet[start..];
+
+        if pid == PAT_PID || self.pmt_pids.contains_key(&pid) {
+            self.handle_section(pid, unit_start, discontinuity, payload);
+        } else if self.streams().any(|stream| stream.pid == pid) {
+            if discontinuity {
+                self.pes.remove(&pid);
+            }
+            if unit_start {
+                self.finish_pes(pid);
+                self.pes.insert(
+                    pid,
+                    PesBuilder {
+                        offset,
+                        data: payload.to_vec(),
+                    },
+                );
+            } else if let Some(builder) = self.pes.get_mut(&pid) {
+                builder.data.extend_from_slice(payload);
+            }
+
+            // Packets of known length are complete without waiting for the next one
+            if self.pes.get(&pid).is_some_and(|builder| {
+                let data = &builder.data;
+                data.len() >= 6 && {
+                    let length = usize::from(u16::from_be_bytes([data[4], data[5]]));
+                    length != 0 && data.len() >= 6 + length
+                }
+            }) {
+                self.finish_pes(pid);
+            }
+        }
+    }
+
+    fn handle_section(&mut self, pid: u16, unit_start: bool, discontinuity: bool, payload: &[u8]) {
+        if discontinuity {
+            self.sections.remove(&pid);
+        }
+
+        if unit_start {
+            let pointer = usize::from(payload[0]);
+            if 1 + pointer > payload.len() {
+                return;
+            }
+            // The end of the previous section comes before the pointed one
+            if let Some(section) = self.sections.get_mut(&pid) {
+                section.extend_from_slice(&payload[1..1 + pointer]);
+                self.parse_sections(pid);
+            }
+            self.sections.insert(pid, payload[1 + pointer..].to_vec());
+        } else if let Some(section) = self.sections.get_mut(&pid) {
+            section.extend_from_slice(payload);
+        }
+
+        self.parse_sections(pid);
+    }
+
+    /// Parses the complete sections at the start of the buffer of `pid`
+    fn parse_sections(&mut self, pid: u16) {
+        loop {
+            let Some(buffer) = self.sections.get_mut(&pid) else {
+                return;
+            };
+            // Stuffing bytes end the packet
+            if buffer.first().is_none_or(|table_id| *table_id == 0xff) {
+                buffer.clear();
+                return;
+            }
+            if buffer.len() < 3 {
+                return;
+            }
+            let length = 3 + usize::from(u16::from_be_bytes([buffer[1] & 0x0f, buffer[2]]));
+            if buffer.len() < length {
+                return;
+            }
+
+            let section: Vec<u8> = buffer.drain(..length).collect();
+            if crc32(&section) == 0 {
+                match section[0] {
+                    0x00 if pid == PAT_PID => self.parse_pat(&section),
+                    0x02 if pid != PAT_PID => self.parse_pmt(pid, &section),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn parse_pat(&mut self, section: &[u8]) {
+        if section.len() < 12 {
+            return;
+        }
+
+        for entry in section[8..section.len() - 4].chunks_exact(4) {
+            let number = u16::from_be_bytes([entry[0], entry[1]]);
+            let pid = u16::from_be_bytes([entry[2] & 0x1f, entry[3]]);
+            // Program 0 is the network information table
+            if number != 0 {
+                self.pmt_pids.insert(pid, number);
+            }
+        }
+    }
+
+    fn parse_pmt(&mut self, pid: u16, section: &[u8]) {
+        if section.len() < 16 {
+            return;
+        }
+        let number = u16::from_be_bytes([section[3], section[4]]);
+        let info_length = usize::from(u16::from_be_bytes([section[10] & 0x0f, section[11]]));
+
+        let mut streams = Vec::new();
+        let end = section.len() - 4;
+        let mut i = 12 + info_length;
+        while i + 5 <= end {
+            let stream_type = section[i];
+            let es_pid = u16::from_be_bytes([section[i + 1] & 0x1f, section[i + 2]]);
+            let es_info_length =
+                usize::from(u16::from_be_bytes([section[i + 3] & 0x0f, section[i + 4]]));
+            i += 5;
+
+            let info_end = (i + es_info_length).min(end);
+            let mut descriptors = Vec::new();
+            while i + 2 <= info_end {
+                let length = usize::from(section[i + 1]);
+                let data_end = (i + 2 + length).min(info_end);
+                descriptors.push(Descriptor {
+                    tag: section[i],
+                    data: section[i + 2..data_end].to_vec(),
+                });
+                i = data_end;
+            }
+            i = info_end;
+
+            streams.push(ElementaryStream {
+                pid: es_pid,
+                stream_type,
+                descriptors,
+            });
+        }
+
+        let program = Program {
+            number,
+            pmt_pid: pid,
+            streams,
+        };
+        match self.programs.iter_mut().find(|p| p.number == number) {
+            Some(existing) => *existing = program,
+            None => self.programs.push(program),
+        }
+    }
+
+    /// Parses the PES packet collected on `pid`, if any
+    fn finish_pes(&mut self, pid: u16) {
+        let Some(builder) = self.pes.remove(&pid) else {
+            return;
+        };
+        if let Some(packet) = parse_pes(pid, builder) {
+            self.ready.push_back(packet);
+        }
+    }
+}
+
+/// Splits a PES packet into its header fields and payload, `None` if the header is malformed
+fn parse_pes(pid: u16, builder: PesBuilder) -> Option<PesPacket> {
+    let data = builder.data;
+    if data.len() < 6 || data[..3] != [0x00, 0x00, 0x01] {
+        return None;
+    }
+    let stream_id = data[3];
+    let length = usize::from(u16::from_be_bytes([data[4], data[5]]));
+    // A zero length is only allowed for video and means the packet runs until the next one
+    let end = if length == 0 {
+        data.len()
+    } else {
+        (6 + length).min(data.len())
+    };
+
+    let mut packet = PesPacket {
+        pid,
+        stream_id,
+        pts: None,
+        dts: None,
+        payload: Vec::new(),
+        offset: builder.offset,
+    };
+
+    // Program stream map, padding, private stream 2, ECM, EMM, directory and DSMCC have no header
+    if matches!(
+        stream_id,
+        0xbc | 0xbe | 0xbf | 0xf0 | 0xf1 | 0xf2 | 0xf8 | 0xff
+    ) {
+        packet.payload = data[6..end].to_vec();
+        return Some(packet);
+    }
+
+    if end < 9 {
+        return None;
+    }
+    let flags = data[7];
+    let header_end = 9 + usize::from(data[8]);
+    if header_end > end {
+        return None;
+    }
+    if flags & 0x80 != 0 && header_end >= 14 {
+        packet.pts = Some(read_timestamp(&data[9..14]));
+    }
+    if flags & 0xc0 == 0xc0 && header_end >= 19 {
+        packet.dts = Some(read_timestamp(&data[14..19]));
+    }
+    packet.payload = data[header_end..end].to_vec();
+
+    Some(packet)
+}
+
+/// 33-bit time stamp split in 3, 15 and 15 bits separated by marker bits
+fn read_timestamp(bytes: &[u8]) -> u64 {
+    (u64::from(bytes[0] >> 1) & 0x07) << 30
+        | u64::from(bytes[1]) << 22
+        | u64::from(bytes[2] >> 1) << 15
+        | u64::from(bytes[3]) << 7
+        | u64::from(bytes[4] >> 1)
+}
+
+/// CRC-32 of table sections, zero over a whole section including its CRC
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= u32::from(*byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
@@ -0,0 +1,132 @@
+// WAVE files of 16-bit PCM samples
+//
+// RIFF header, a `fmt ` chunk describing the samples and a `data` chunk of
+// little-endian interleaved samples. Chunk sizes are patched when the writer
+// is finished, once the number of samples is known. RIFF sizes are 32-bit, so
+// the file cannot exceed 4 GiB.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::Error;
+
+const HEADER_SIZE: u32 = 44;
+
+/// Largest `data` chunk, for the RIFF size to fit in 32 bits
+const MAX_DATA_SIZE: u32 = u32::MAX - (HEADER_SIZE - 8);
+
+/// Samples per channel of silence written at once
+const SILENCE_FRAMES: u64 = 4096;
+
+/// Writes interleaved 16-bit samples to a WAVE file.
+pub struct WavWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    sample_rate: u32,
+    channels: u16,
+    /// Bytes of samples written so far
+    data_size: u32,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, sample_rate: u32, channels: u16) -> Result<Self, Error> {
+        let file = File::create(path).map_err(|e| Error::io(path, e))?;
+        let mut writer = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            sample_rate,
+            channels,
+            data_size: 0,
+        };
+        writer.write_header()?;
+
+        Ok(writer)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Number of samples written per channel
+    pub fn frames_written(&self) -> u64 {
+        u64::from(self.data_size) / (2 * u64::from(self.channels))
+    }
+
+    fn write_header(&mut self) -> Result<(), Error> {
+        let block_align = 2 * self.channels;
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend(b"RIFF");
+        header.extend((HEADER_SIZE - 8 + self.data_size).to_le_bytes());
+        header.extend(b"WAVEfmt ");
+        header.extend(16u32.to_le_bytes());
+        // PCM
+        header.extend(1u16.to_le_bytes());
+        header.extend(self.channels.to_le_bytes());
+        header.extend(self.sample_rate.to_le_bytes());
+        header.extend((self.sample_rate * u32::from(block_align)).to_le_bytes());
+        header.extend(block_align.to_le_bytes());
+        header.extend(16u16.to_le_bytes());
+        header.extend(b"data");
+        header.extend(self.data_size.to_le_bytes());
+
+        self.writer
+            .write_all(&header)
+            .map_err(|e| Error::io(&self.path, e))
+    }
+
+    /// Size of the `data` chunk once `bytes` more are written, if it stays within the RIFF limit
+    fn data_size_after(&self, bytes: u64) -> Result<u32, Error> {
+        u32::try_from(bytes)
+            .ok()
+            .and_then(|bytes| self.data_size.checked_add(bytes))
+            .filter(|size| *size <= MAX_DATA_SIZE)
+            .ok_or_else(|| Error::FileTooLarge {
+                path: self.path.clone(),
+                limit: u64::from(u32::MAX) + 8,
+            })
+    }
+
+    /// Appends interleaved samples, a whole number of samples per channel
+    pub fn write_samples(&mut self, samples: &[i16]) -> Result<(), Error> {
+        let data_size = self.data_size_after(2 * samples.len() as u64)?;
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+
+        self.writer
+            .write_all(&bytes)
+            .map_err(|e| Error::io(&self.path, e))?;
+        self.data_size = data_size;
+        Ok(())
+    }
+
+    /// Appends `frames` samples of silence per channel
+    pub fn write_silence(&mut self, frames: u64) -> Result<(), Error> {
+        let channels = u64::from(self.channels);
+        self.data_size_after(frames.saturating_mul(2 * channels))?;
+
+        let silence = vec![0; (frames.min(SILENCE_FRAMES) * channels) as usize];
+        let mut left = frames;
+        while left > 0 {
+            let count = left.min(SILENCE_FRAMES);
+            self.write_samples(&silence[..(count * channels) as usize])?;
+            left -= count;
+        }
+        Ok(())
+    }
+
+    /// Writes the final chunk sizes
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.writer
+            .seek(SeekFrom::Start(0))
+            .map_err(|e| Error::io(&self.path, e))?;
+        self.write_header()?;
+
+        self.writer.flush().map_err(|e| Error::io(&self.path, e))
+    }
+}
@@ -0,0 +1,297 @@
+use crate::{flag::ChromaFormat, image::Rgb, RgbImage};
+
+/// Luma up to which a sample counts as black, video black being 16
+pub(crate) const BLACK_LEVEL: u8 = 32;
+
+/// Field of an interlaced frame: even lines for the top field, odd lines for the bottom one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Top,
+    Bottom,
+}
+
+impl Field {
+    /// Index of the first line of the field
+    pub fn parity(&self) -> usize {
+        match self {
+            Field::Top => 0,
+            Field::Bottom => 1,
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Field::Top => Field::Bottom,
+            Field::Bottom => Field::Top,
+        }
+    }
+}
+
+/// A single plane of 8-bit samples, rows are `stride` bytes apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plane {
+    width: usize,
+    height: usize,
+    stride: usize,
+    data: Vec<u8>,
+}
+
+impl Plane {
+    pub fn new(width: usize, height: usize, value: u8) -> Self {
+        Self {
+            width,
+            height,
+            stride: width,
+            data: vec![value; width * height],
+        }
+    }
+
+    /// Wraps `data`, whose rows are `stride` bytes apart
+    ///
+    /// # Panics
+    ///
+    /// If `data` is too small for `height` rows of `stride` bytes.
+    pub fn from_vec(width: usize, height: usize, stride: usize, data: Vec<u8>) -> Self {
+        assert!(stride >= width, "stride smaller than width");
+        assert!(
+            height == 0 || data.len() >= (height - 1) * stride + width,
+            "plane data too small"
+        );
+
+        Self {
+            width,
+            height,
+            stride,
+            data,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn row(&self, y: usize) -> &[u8] {
+        &self.data[y * self.stride..y * self.stride + self.width]
+    }
+
+    pub fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        &mut self.data[y * self.stride..y * self.stride + self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        (0..self.height).map(move |y| self.row(y))
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.row(y)[x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.row_mut(y)[x] = value;
+    }
+
+    /// Rows of a field, top to bottom
+    pub fn field_rows(&self, field: Field) -> impl Iterator<Item = &[u8]> + '_ {
+        (field.parity()..self.height)
+            .step_by(2)
+            .map(move |y| self.row(y))
+    }
+
+    /// Copy of a field, half as high as the plane
+    pub fn field(&self, field: Field) -> Plane {
+        let height = (self.height + 1 - field.parity()) / 2;
+        let data = self.field_rows(field).flatten().copied().collect();
+
+        Plane::from_vec(self.width, height, self.width, data)
+    }
+
+    /// Copy of a rectangle of the plane, clipped to its edges
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Plane {
+        let x = x.min(self.width);
+        let y = y.min(self.height);
+        let width = width.min(self.width - x);
+        let height = height.min(self.height - y);
+        let data = (y..y + height)
+            .flat_map(|row| &self.row(row)[x..x + width])
+            .copied()
+            .collect();
+
+        Plane::from_vec(width, height, width, data)
+    }
+}
+
+/// A frame made of separate Y, Cb and Cr planes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YuvFrame {
+    pub y: Plane,
+    pub cb: Plane,
+    pub cr: Plane,
+    pub chroma_format: ChromaFormat,
+}
+
+impl YuvFrame {
+    /// Black frame
+    pub fn new(width: usize, height: usize, chroma_format: ChromaFormat) -> Self {
+        let (h_shift, v_shift) = chroma_format.subsampling();
+        let chroma_width = (width + (1 << h_shift) - 1) >> h_shift;
+        let chroma_height = (height + (1 << v_shift) - 1) >> v_shift;
+
+        Self {
+            y: Plane::new(width, height, 16),
+            cb: Plane::new(chroma_width, chroma_height, 128),
+            cr: Plane::new(chroma_width, chroma_height, 128),
+            chroma_format,
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self::new(0, 0, ChromaFormat::Yuv420)
+    }
+
+    pub fn width(&self) -> usize {
+        self.y.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.y.height()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width() == 0 || self.height() == 0
+    }
+
+    pub fn planes(&self) -> [&Plane; 3] {
+        [&self.y, &self.cb, &self.cr]
+    }
+
+    pub fn planes_mut(&mut self) -> [&mut Plane; 3] {
+        [&mut self.y, &mut self.cb, &mut self.cr]
+    }
+
+    /// Copy of a field, half as high as the frame
+    pub fn field(&self, field: Field) -> YuvFrame {
+        YuvFrame {
+            y: self.y.field(field),
+            cb: self.cb.field(field),
+            cr: self.cr.field(field),
+            chroma_format: self.chroma_format,
+        }
+    }
+
+    /// Copy of a rectangle of the frame, clipped to its edges
+    ///
+    /// The rectangle is widened to whole chroma samples.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> YuvFrame {
+        let (h_shift, v_shift) = self.chroma_format.subsampling();
+        let (h_mask, v_mask) = ((1 << h_shift) - 1, (1 << v_shift) - 1);
+        let left = x & !h_mask;
+        let top = y & !v_mask;
+        let right = ((x + width + h_mask) & !h_mask).min(self.width());
+        let bottom = ((y + height + v_mask) & !v_mask).min(self.height());
+        let width = right.saturating_sub(left);
+        let height = bottom.saturating_sub(top);
+
+        let chroma = |plane: &Plane| {
+            plane.crop(
+                left >> h_shift,
+                top >> v_shift,
+                (width + h_mask) >> h_shift,
+                (height + v_mask) >> v_shift,
+            )
+        };
+
+        YuvFrame {
+            y: self.y.crop(left, top, width, height),
+            cb: chroma(&self.cb),
+            cr: chroma(&self.cr),
+            chroma_format: self.chroma_format,
+        }
+    }
+
+    /// Copy of the frame with resampled chroma planes
+    ///
+    /// Each chroma sample is the average of the source samples covering the same luma area.
+    pub fn with_chroma_format(&self, chroma_format: ChromaFormat) -> YuvFrame {
+        if chroma_format == self.chroma_format {
+            return self.clone();
+        }
+
+        let mut out = YuvFrame::new(self.width(), self.height(), chroma_format);
+        let (src_h, src_v) = self.chroma_format.subsampling();
+        let (dst_h, dst_v) = chroma_format.subsampling();
+
+        // Range of source samples covering the luma area of a destination sample
+        let covered = |i: usize, dst_shift: usize, src_shift: usize, len: usize| {
+            let first = (i << dst_shift) >> src_shift;
+            let last = ((((i + 1) << dst_shift) - 1) >> src_shift).min(len - 1);
+            first..=last
+        };
+
+        for (src, dst) in [(&self.cb, &mut out.cb), (&self.cr, &mut out.cr)] {
+            for y in 0..dst.height() {
+                let rows = covered(y, dst_v, src_v, src.height());
+                for x in 0..dst.width() {
+                    let columns = covered(x, dst_h, src_h, src.width());
+
+                    let mut sum = 0u32;
+                    let mut count = 0u32;
+                    for row in rows.clone() {
+                        for sample in &src.row(row)[columns.clone()] {
+                            sum += u32::from(*sample);
+                            count += 1;
+                        }
+                    }
+
+                    dst.set(x, y, ((sum + count / 2) / count) as u8);
+                }
+            }
+        }
+
+        out.y = self.y.clone();
+        out
+    }
+
+    pub fn to_rgb(&self, image: &mut RgbImage) {
+        if image.width() != self.width() || image.height() != self.height() {
+            *image = RgbImage::with_capacity(self.width(), self.height())
+        }
+
+        let (h_shift, v_shift) = self.chroma_format.subsampling();
+
+        for i in 0..self.height() {
+            let luma = self.y.row(i);
+            let cb = self.cb.row(i >> v_shift);
+            let cr = self.cr.row(i >> v_shift);
+
+            for (j, pixel) in image[i].iter_mut().enumerate() {
+                let y = f32::from(luma[j]) - 16f32;
+                let u = f32::from(cb[j >> h_shift]) - 128f32;
+                let v = f32::from(cr[j >> h_shift]) - 128f32;
+
+                let r = y + 1.370705f32 * v;
+                let g = y - 0.698001f32 * v - 0.337633f32 * u;
+                let b = y + 1.732446f32 * u;
+
+                let r = r.clamp(0f32, 255f32) as u8;
+                let g = g.clamp(0f32, 255f32) as u8;
+                let b = b.clamp(0f32, 255f32) as u8;
+
+                *pixel = Rgb::new(r, g, b);
+            }
+        }
+    }
+
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let mut image = RgbImage::with_capacity(self.width(), self.height());
+        self.to_rgb(&mut image);
+        image.get_rgba()
+    }
+}
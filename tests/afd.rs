@@ -0,0 +1,168 @@
+use std::fs;
+
+use mpeg2::{
+    a53_cc_data, meta_decode, user_data_chunks, ActiveArea, ActiveFormat, AfdMode, BarData,
+    ChromaFormat, Plane, YuvFrame,
+};
+
+const AFD_16_9: &[u8] = b"DTG1\x41\xfa";
+
+#[test]
+fn active_format_parse() {
+    assert_eq!(ActiveFormat::parse(AFD_16_9), Some(ActiveFormat(10)));
+    // Without active_format_flag
+    assert_eq!(ActiveFormat::parse(b"DTG1\x01"), None);
+    assert_eq!(ActiveFormat::parse(b"GA94\x03\x41"), None);
+}
+
+#[test]
+fn active_format_areas() {
+    // 16:9 centre in a 4:3 frame is letterboxed, in a 16:9 frame it fills the frame
+    assert_eq!(
+        ActiveFormat(10).active_area(720, 576, 4.0 / 3.0),
+        Some(ActiveArea {
+            x: 0,
+            y: 72,
+            width: 720,
+            height: 432
+        })
+    );
+    assert_eq!(
+        ActiveFormat(10).active_area(720, 576, 16.0 / 9.0),
+        Some(ActiveArea::full(720, 576))
+    );
+
+    // The 16:9 box sits at the top
+    assert_eq!(
+        ActiveFormat(2)
+            .active_area(720, 576, 4.0 / 3.0)
+            .map(|area| area.y),
+        Some(0)
+    );
+
+    // 4:3 centre in a 16:9 frame is pillarboxed
+    assert_eq!(
+        ActiveFormat(9).active_area(1920, 1080, 16.0 / 9.0),
+        Some(ActiveArea {
+            x: 240,
+            y: 0,
+            width: 1440,
+            height: 1080
+        })
+    );
+
+    // Boxes wider than 16:9 need bar data
+    assert_eq!(ActiveFormat(4).active_area(720, 576, 4.0 / 3.0), None);
+}
+
+#[test]
+fn bar_data() {
+    // Top and bottom bars of a 2.35:1 film in a 1080 lines frame
+    let bars = BarData::parse(b"GA94\x06\xcf\xc0\x83\xc3\xbc").unwrap();
+    assert_eq!(
+        bars,
+        BarData {
+            top: Some(131),
+            bottom: Some(956),
+            left: None,
+            right: None
+        }
+    );
+    assert_eq!(
+        bars.active_area(1920, 1080),
+        Some(ActiveArea {
+            x: 0,
+            y: 132,
+            width: 1920,
+            height: 824
+        })
+    );
+
+    // Truncated
+    assert_eq!(BarData::parse(b"GA94\x06\xcf\xc0\x83"), None);
+}
+
+#[test]
+fn user_data_structures() {
+    let mut user_data = AFD_16_9.to_vec();
+    user_data.extend([0, 0, 1]);
+    user_data.extend(b"GA94\x03\x41\xff\xfc\x94\x20\xff");
+
+    let chunks: Vec<_> = user_data_chunks(&user_data).collect();
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[0], AFD_16_9);
+    assert_eq!(a53_cc_data(chunks[1]).len(), 1);
+}
+
+#[test]
+fn active_format_stays_until_the_next_sequence() {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-afd.log", std::process::id()));
+    fs::write(
+        &path,
+        "SEQ 1080000\n\
+         PIC 00000000 0 TFF I\n\
+         USR 4454473141fa00000147413934068fc083\n\
+         PIC 00001000 1 TFF P\n\
+         PIC 00002000 2 TFF P\n\
+         USR 4454473141f9\n\
+         SEQ 1080000\n\
+         PIC 00003000 0 TFF I\n",
+    )
+    .unwrap();
+    let pictures = meta_decode(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let formats: Vec<_> = pictures.iter().map(|p| p.active_format).collect();
+    assert_eq!(
+        formats,
+        [
+            Some(ActiveFormat(10)),
+            Some(ActiveFormat(10)),
+            Some(ActiveFormat(9)),
+            None
+        ]
+    );
+
+    // Bar data only applies to its picture, and goes before the active format
+    assert_eq!(pictures[0].bar_data.and_then(|bars| bars.top), Some(131));
+    assert_eq!(pictures[1].bar_data, None);
+    assert_eq!(
+        pictures[0]
+            .active_area(720, 576, 4.0 / 3.0)
+            .map(|area| area.y),
+        Some(132)
+    );
+    assert_eq!(
+        pictures[1]
+            .active_area(720, 576, 4.0 / 3.0)
+            .map(|area| area.y),
+        Some(72)
+    );
+}
+
+#[test]
+fn crop_and_letterbox() {
+    let mut frame = YuvFrame::new(8, 8, ChromaFormat::Yuv420);
+    frame.y = Plane::from_vec(8, 8, 8, (0..64).collect());
+    let area = ActiveArea {
+        x: 1,
+        y: 2,
+        width: 4,
+        height: 4,
+    };
+
+    // Widened to whole chroma samples
+    let cropped = AfdMode::Crop.apply(&frame, area);
+    assert_eq!((cropped.width(), cropped.height()), (6, 4));
+    assert_eq!((cropped.cb.width(), cropped.cb.height()), (3, 2));
+    assert_eq!(cropped.y.get(0, 0), 16);
+
+    let letterboxed = AfdMode::Letterbox.apply(&frame, area);
+    assert_eq!((letterboxed.width(), letterboxed.height()), (8, 8));
+    assert_eq!(letterboxed.y.get(0, 0), 16);
+    assert_eq!(letterboxed.y.get(1, 2), 17);
+    assert_eq!(letterboxed.y.get(4, 5), 44);
+    assert_eq!(letterboxed.y.get(5, 5), 16);
+
+    assert_eq!("Letterbox".parse(), Ok(AfdMode::Letterbox));
+}
@@ -0,0 +1,99 @@
+use std::{fs, path::Path};
+
+use mpeg2::{analyze, meta_decode, read_log, AnomalyKind, CadenceKind, Error};
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+#[test]
+fn groups_follow_gop_lines() {
+    let report = analyze(&read_log(&fixture("pulldown.log")).unwrap());
+
+    assert_eq!(report.sequences, 1);
+    assert_eq!(report.gops.len(), 2);
+    assert_eq!(report.gops[0].closed, Some(true));
+    assert_eq!(report.gops[1].closed, Some(false));
+    assert_eq!(report.gop_structure(&report.gops[0]), "IPIB");
+    assert_eq!((report.types.i, report.types.p, report.types.b), (3, 2, 2));
+
+    // Offset deltas in decoding order, the last picture has no known size
+    let sizes: Vec<_> = report.pictures.iter().map(|p| p.size).collect();
+    assert_eq!(sizes[0], Some(0x4000 - 0x10));
+    assert_eq!(sizes[6], None);
+}
+
+#[test]
+fn pulldown_cadence_and_temporal_reference_gap() {
+    let report = analyze(&read_log(&fixture("pulldown.log")).unwrap());
+
+    assert_eq!(report.cadence.kind, CadenceKind::Pulldown32);
+    assert_eq!(report.cadence.pattern, "T3 B2 B3 T2 T3 B2 B3");
+
+    let kinds: Vec<_> = report.anomalies.iter().map(|a| a.kind).collect();
+    assert_eq!(kinds, [AnomalyKind::MissingTemporalReference(2)]);
+}
+
+#[test]
+fn groups_follow_sequences_in_old_logs() {
+    let report = analyze(&read_log(&fixture("synthetic_tff/tvid.log")).unwrap());
+
+    assert_eq!(report.gops.len(), 1);
+    assert_eq!(report.cadence.kind, CadenceKind::TopFieldFirst);
+    assert!(report.anomalies.is_empty());
+
+    let display: Vec<_> = report.pictures.iter().map(|p| p.display_index).collect();
+    assert_eq!(display, [0, 2, 1]);
+}
+
+#[test]
+fn temporal_reference_out_of_range() {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-temp-ref.log", std::process::id()));
+    fs::write(&path, "SEQ 1080000\nPIC 00000000 4000000000 TFF I\n").unwrap();
+    let result = read_log(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        result,
+        Err(Error::LogLine {
+            line: 2,
+            reason: "temp_ref does not fit in 10 bits",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn bitrate_matches_the_viewer() {
+    let path = fixture("pulldown.log");
+    let report = analyze(&read_log(&path).unwrap());
+    let pictures = meta_decode(&path).unwrap();
+
+    // The viewer plot sends every picture in one frame period, repeated fields or not
+    for picture in &report.pictures {
+        let viewer = pictures
+            .iter()
+            .find(|p| p.offset == picture.entry.offset)
+            .unwrap();
+        assert_eq!(picture.bitrate(), viewer.bitrate());
+        assert_eq!(picture.displayed_fields(), viewer.displayed_fields());
+    }
+}
+
+#[test]
+fn frame_repetition_in_progressive_sequences() {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-repeat.log", std::process::id()));
+    fs::write(
+        &path,
+        "SEQ 900900 PROG\nPIC 00000000 0 PROG TFF RFF I\nPIC 00001000 1 PROG RFF P\nPIC 00002000 2 PROG P\n",
+    )
+    .unwrap();
+    let report = analyze(&read_log(&path).unwrap());
+    fs::remove_file(&path).unwrap();
+
+    // A repeated frame is shown twice, three times with top_field_first
+    assert_eq!(report.cadence.pattern, "T6 B4 B2");
+}
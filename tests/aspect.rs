@@ -0,0 +1,81 @@
+use std::fs;
+
+use mpeg2::{meta_decode, to_square_pixels, AspectRatio, ChromaFormat, Kernel, Plane, YuvFrame};
+
+#[test]
+fn aspect_ratio_parse() {
+    assert_eq!("16:9".parse(), Ok(AspectRatio::new(16, 9)));
+    assert_eq!("32/18".parse(), Ok(AspectRatio::new(16, 9)));
+    assert!("16:0".parse::<AspectRatio>().is_err());
+    assert!("wide".parse::<AspectRatio>().is_err());
+    assert_eq!(AspectRatio::new(128, 90).to_string(), "64:45");
+}
+
+#[test]
+fn sample_and_display_aspect_ratios() {
+    // 720x576 frames of 4:3 and 16:9 PAL
+    let narrow = AspectRatio::new(16, 15);
+    let wide = AspectRatio::new(64, 45);
+    assert_eq!(narrow.display_aspect(720, 576), AspectRatio::new(4, 3));
+    assert_eq!(wide.display_aspect(720, 576), AspectRatio::new(16, 9));
+    assert_eq!(AspectRatio::new(16, 9).sample_aspect(720, 576), wide);
+
+    assert_eq!(narrow.square_width(720), 768);
+    assert_eq!(wide.square_width(720), 1024);
+}
+
+#[test]
+fn log_sequence_aspect() {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-aspect.log", std::process::id()));
+    fs::write(
+        &path,
+        "SEQ 1080000 4:2:0 SIZE 720x576 DISP 704x576 SAR 16:11\n\
+         PIC 00000000 0 TFF I\n\
+         SEQ 1080000 4:2:0\n\
+         PIC 00001000 0 TFF I\n",
+    )
+    .unwrap();
+    let pictures = meta_decode(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(pictures[0].coded_size, Some((720, 576)));
+    assert_eq!(pictures[0].display_size, Some((704, 576)));
+    assert_eq!(pictures[0].sample_aspect, Some(AspectRatio::new(16, 11)));
+    // The display aspect ratio is the one of the display area
+    assert_eq!(pictures[0].display_aspect(), Some(AspectRatio::new(16, 9)));
+
+    // Older logs
+    assert_eq!(pictures[1].sample_aspect, None);
+    assert_eq!(pictures[1].display_aspect(), None);
+}
+
+#[test]
+fn malformed_sequence_size() {
+    let path =
+        std::env::temp_dir().join(format!("mpeg2-{}-aspect-malformed.log", std::process::id()));
+    fs::write(&path, "SEQ 1080000 SIZE 720\nPIC 00000000 0 TFF I\n").unwrap();
+    let result = meta_decode(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn square_pixels() {
+    let mut frame = YuvFrame::new(8, 2, ChromaFormat::Yuv420);
+    frame.y = Plane::from_vec(8, 2, 8, [0, 0, 0, 0, 120, 120, 120, 120].repeat(2));
+
+    // 8:1 shown on 8x2 samples, each twice as wide as high
+    let stretched = to_square_pixels(
+        &frame,
+        AspectRatio::new(8, 1).sample_aspect(8, 2),
+        Kernel::Bilinear,
+    );
+    assert_eq!((stretched.width(), stretched.height()), (16, 2));
+    assert_eq!((stretched.cb.width(), stretched.cb.height()), (8, 1));
+
+    let row = stretched.y.row(0);
+    assert_eq!(row[0], 0);
+    assert_eq!(row[15], 120);
+    assert!(row.windows(2).all(|pair| pair[0] <= pair[1]));
+}
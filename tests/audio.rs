@@ -0,0 +1,414 @@
+use std::{fs, io::Cursor, path::Path};
+
+use std::time::Duration;
+
+use mpeg2::{
+    decode_audio, pts_delta, Ac3Header, Ac3Parser, AudioHeader, AudioSink, ChannelMode, Error,
+    Mp2Decoder, PcmBuffer, PlaybackState, StreamKind, TsDemuxer, WavWriter,
+};
+
+/// Writes bits most significant first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        for i in (0..bits).rev() {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.bits % 8);
+            self.bits += 1;
+        }
+    }
+}
+
+/// MPEG-1 Layer II mono frame at 48 kHz and 64 kbit/s, whose first subband holds `code`
+/// in all its samples with a scalefactor of 1
+fn mono_frame(code: u32) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    // Sync, MPEG-1, Layer II, no CRC, 64 kbit/s, 48 kHz, no padding, mono
+    writer.write(0xfff, 12);
+    writer.write(0b1101, 4);
+    writer.write(0b0100, 4);
+    writer.write(0b0100, 4);
+    writer.write(0b1100_0000, 8);
+
+    // Table 3-B.2a: 27 subbands, 4 allocation bits for the first 11, then 3, then 2
+    for sb in 0..27 {
+        let bits = match sb {
+            0..=10 => 4,
+            11..=22 => 3,
+            _ => 2,
+        };
+        // 65535 steps, 16-bit samples
+        writer.write(if sb == 0 { 15 } else { 0 }, bits);
+    }
+    // One scalefactor for the whole frame
+    writer.write(2, 2);
+    writer.write(3, 6);
+    for _ in 0..36 {
+        writer.write(code, 16);
+    }
+
+    writer.bytes.resize(192, 0);
+    writer.bytes
+}
+
+#[test]
+fn layer2_header() {
+    let header = AudioHeader::parse([0xff, 0xfd, 0x44, 0xc0]).unwrap();
+
+    assert!(header.mpeg1);
+    assert!(!header.protected);
+    assert_eq!(header.bitrate, 64_000);
+    assert_eq!(header.sample_rate, 48_000);
+    assert_eq!(header.mode, ChannelMode::Mono);
+    assert_eq!(header.frame_size(), 192);
+
+    // Layer III
+    assert_eq!(AudioHeader::parse([0xff, 0xfb, 0x44, 0xc0]), None);
+}
+
+#[test]
+fn decode_layer2_frames() {
+    let silence = mono_frame(0x7fff);
+    let tone = mono_frame(0xc000);
+
+    let mut decoder = Mp2Decoder::new();
+    // Garbage, then frames split across pushes like PES payloads
+    decoder.push(&[0x12, 0xff, 0x00], None);
+    decoder.push(&silence[..100], Some(9000));
+    decoder.push(&silence[100..], None);
+    decoder.push(&tone, Some(9000 + 2160));
+
+    let first = decoder.next_frame().unwrap();
+    assert_eq!(first.pts, Some(9000));
+    assert_eq!(first.samples.len(), 1152);
+    assert!(first.samples.iter().all(|s| *s == 0));
+
+    let second = decoder.next_frame().unwrap();
+    assert_eq!(second.pts, Some(11160));
+    assert!(second.samples.iter().any(|s| s.abs() > 1000));
+    assert!(decoder.next_frame().is_none());
+    assert_eq!(decoder.skipped(), 3);
+}
+
+#[test]
+fn pts_wrap_around() {
+    assert_eq!(pts_delta(100, (1 << 33) - 100), 200);
+    assert_eq!(pts_delta((1 << 33) - 100, 100), -200);
+    assert_eq!(pts_delta(3000, 1000), 2000);
+}
+
+#[test]
+fn wav_size_limit() {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-limit.wav", std::process::id()));
+    let mut writer = WavWriter::create(&path, 48000, 2).unwrap();
+    writer.write_silence(10_000).unwrap();
+    assert_eq!(writer.frames_written(), 10_000);
+
+    // 2^30 stereo samples would make a 4 GiB data chunk, refused before writing anything
+    assert!(matches!(
+        writer.write_silence(1 << 30),
+        Err(Error::FileTooLarge { .. })
+    ));
+    assert_eq!(writer.frames_written(), 10_000);
+    writer.finish().unwrap();
+
+    let size = fs::metadata(&path).unwrap().len();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(size, 44 + 40_000);
+}
+
+/// CRC-32 of table sections
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= u32::from(*byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-16 of AC-3 sync frames
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for byte in data {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// AC-3 sync frame at 48 kHz and 192 kbit/s in 3/2 with LFE, its audio blocks all zeros
+fn ac3_frame() -> Vec<u8> {
+    // Sync word, crc1, fscod 0 and frmsizecod 20, bsid 8, acmod 7, mixing levels, lfeon
+    let mut frame = vec![0x0b, 0x77, 0, 0, 0x14, 0x40, 0xe1, 0x00];
+    frame.resize(768 - 2, 0);
+    // crc2 makes the CRC of the frame after the sync word zero
+    let crc = crc16(&frame[2..]);
+    frame.extend(crc.to_be_bytes());
+    frame
+}
+
+#[test]
+fn ac3_header() {
+    let header = Ac3Header::parse(&ac3_frame()).unwrap();
+
+    assert_eq!(header.sample_rate, 48_000);
+    assert_eq!(header.bitrate, 192_000);
+    assert_eq!(header.frame_size, 768);
+    assert_eq!(header.channel_layout(), "3/2+LFE");
+    assert_eq!(header.channels(), 6);
+
+    // 44.1 kHz frames of odd size codes are one word longer
+    let header = Ac3Header::parse(&[0x0b, 0x77, 0, 0, 0x41, 0x40, 0x40, 0]).unwrap();
+    assert_eq!(header.frame_size, 140);
+    assert_eq!(header.channel_layout(), "2/0");
+}
+
+#[test]
+fn ac3_frames_are_validated() {
+    let frame = ac3_frame();
+    let mut corrupted = frame.clone();
+    corrupted[100] ^= 0x01;
+
+    let mut parser = Ac3Parser::new();
+    parser.push(&[0x0b, 0x00, 0x42], None);
+    parser.push(&frame, Some(1000));
+    parser.push(&corrupted, Some(3880));
+    parser.push(&frame[..500], Some(6760));
+    parser.push(&frame[500..], None);
+
+    assert_eq!(parser.next_frame().unwrap().pts, Some(1000));
+    let last = parser.next_frame().unwrap();
+    assert_eq!(last.pts, Some(6760));
+    assert_eq!(last.data, frame);
+    assert!(parser.next_frame().is_none());
+    assert_eq!(parser.corrupted(), 1);
+    assert_eq!(parser.skipped(), 3);
+}
+
+/// Transport stream packets carrying `data` on `pid`, padded with an adaptation field
+fn packets(pid: u16, data: &[u8], counter: &mut u8) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (i, chunk) in data.chunks(184).enumerate() {
+        let unit_start = if i == 0 { 0x40 } else { 0 };
+        out.extend([0x47, unit_start | (pid >> 8) as u8, pid as u8]);
+
+        let stuffing = 184 - chunk.len();
+        if stuffing == 0 {
+            out.push(0x10 | *counter);
+        } else {
+            out.push(0x30 | *counter);
+            out.push(stuffing as u8 - 1);
+            if stuffing > 1 {
+                out.push(0);
+                out.extend(std::iter::repeat_n(0xff, stuffing - 2));
+            }
+        }
+        out.extend(chunk);
+        *counter = (*counter + 1) & 0x0f;
+    }
+    out
+}
+
+/// Table section with its CRC, preceded by a zero pointer field
+fn section(table_id: u8, id: u16, body: &[u8]) -> Vec<u8> {
+    let length = 5 + body.len() + 4;
+    let mut section = vec![table_id, 0xb0 | (length >> 8) as u8, length as u8];
+    section.extend(id.to_be_bytes());
+    section.extend([0xc1, 0, 0]);
+    section.extend(body);
+    section.extend(crc32(&section).to_be_bytes());
+
+    let mut out = vec![0];
+    out.extend(section);
+    out
+}
+
+fn pes(stream_id: u8, pts: u64, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![0, 0, 1, stream_id];
+    out.extend(((8 + payload.len()) as u16).to_be_bytes());
+    out.extend([0x80, 0x80, 5]);
+    out.extend([
+        0x21 | ((pts >> 29) & 0x0e) as u8,
+        (pts >> 22) as u8,
+        0x01 | (pts >> 14) as u8 & 0xfe,
+        (pts >> 7) as u8,
+        0x01 | (pts << 1) as u8,
+    ]);
+    out.extend(payload);
+    out
+}
+
+#[test]
+fn demux_pes_packets() {
+    let mut stream = Vec::new();
+    let mut counters = [0u8; 4];
+    // Program 1 on PMT PID 0x20, with video on 0x100 and audio on 0x101
+    stream.extend(packets(
+        0,
+        &section(0x00, 1, &[0, 1, 0xe0, 0x20]),
+        &mut counters[0],
+    ));
+    #[rustfmt::skip]
+    let pmt = [
+        // PCR PID, no program descriptors
+        0xe1, 0x00, 0xf0, 0x00,
+        // MPEG-2 video
+        0x02, 0xe1, 0x00, 0xf0, 0x00,
+        // MPEG-2 audio with a language descriptor
+        0x04, 0xe1, 0x01, 0xf0, 0x06, 0x0a, 0x04, b'f', b'r', b'a', 0x00,
+        // DVB AC-3, private data with an AC-3 descriptor
+        0x06, 0xe1, 0x02, 0xf0, 0x03, 0x6a, 0x01, 0x00,
+    ];
+    stream.extend(packets(0x20, &section(0x02, 1, &pmt), &mut counters[1]));
+
+    let audio: Vec<u8> = (0..300).map(|i| i as u8).collect();
+    stream.extend(packets(
+        0x100,
+        &pes(0xe0, 3600, &[0, 0, 1, 0xb3]),
+        &mut counters[2],
+    ));
+    stream.extend(packets(
+        0x101,
+        &pes(0xc0, (1 << 32) + 12345, &audio),
+        &mut counters[3],
+    ));
+
+    let mut demuxer = TsDemuxer::new(Cursor::new(stream), Path::new("test.ts"));
+    let video = demuxer.next_pes().unwrap().unwrap();
+    assert_eq!(video.pid, 0x100);
+    assert_eq!(video.pts, Some(3600));
+    assert_eq!(video.payload, [0, 0, 1, 0xb3]);
+
+    let audio_packet = demuxer.next_pes().unwrap().unwrap();
+    assert_eq!(audio_packet.pid, 0x101);
+    assert_eq!(audio_packet.stream_id, 0xc0);
+    assert_eq!(audio_packet.pts, Some((1 << 32) + 12345));
+    assert_eq!(audio_packet.payload, audio);
+    assert!(demuxer.next_pes().unwrap().is_none());
+
+    let kinds: Vec<StreamKind> = demuxer.streams().map(|stream| stream.kind()).collect();
+    assert_eq!(
+        kinds,
+        [StreamKind::Video, StreamKind::MpegAudio, StreamKind::Ac3]
+    );
+    let descriptors = &demuxer.programs()[0].streams[1].descriptors;
+    assert_eq!(descriptors[0].tag, 0x0a);
+    assert_eq!(descriptors[0].data, b"fra\0");
+}
+
+#[test]
+fn first_subband_reconstructs_a_constant() {
+    let mut decoder = Mp2Decoder::new();
+    for _ in 0..2 {
+        decoder.push(&mono_frame(0xc000), None);
+    }
+
+    // 0xc000 of 65535 steps is 0.5, once the filterbank is past its delay
+    decoder.next_frame().unwrap();
+    let frame = decoder.next_frame().unwrap();
+    assert!(frame.samples.iter().all(|s| (s - 16384).abs() <= 4));
+}
+
+#[test]
+fn audio_aligned_on_video() {
+    let mut stream = Vec::new();
+    let mut counters = [0u8; 4];
+    stream.extend(packets(
+        0,
+        &section(0x00, 1, &[0, 1, 0xe0, 0x20]),
+        &mut counters[0],
+    ));
+    #[rustfmt::skip]
+    let pmt = [
+        0xe1, 0x00, 0xf0, 0x00,
+        0x02, 0xe1, 0x00, 0xf0, 0x00,
+        0x04, 0xe1, 0x01, 0xf0, 0x00,
+    ];
+    stream.extend(packets(0x20, &section(0x02, 1, &pmt), &mut counters[1]));
+
+    // A frame lasts 2160 ticks at 48 kHz: one before the video, two from its start, then a
+    // jump of ten seconds
+    let frame = mono_frame(0xc000);
+    stream.extend(packets(
+        0x101,
+        &pes(0xc0, 90_000 - 2160, &frame),
+        &mut counters[3],
+    ));
+    stream.extend(packets(
+        0x100,
+        &pes(0xe0, 90_000, &[0, 0, 1, 0xb3]),
+        &mut counters[2],
+    ));
+    for pts in [90_000, 92_160, 94_320 + 900_000] {
+        stream.extend(packets(0x101, &pes(0xc0, pts, &frame), &mut counters[3]));
+    }
+
+    let mut demuxer = TsDemuxer::new(Cursor::new(stream), Path::new("test.ts"));
+    let (track, report) = decode_audio(&mut demuxer, None, |sample_rate, channels| {
+        Ok(PcmBuffer::new(sample_rate, channels))
+    })
+    .unwrap();
+
+    assert_eq!((track.sample_rate, track.channels), (48000, 1));
+    // The frame before the video is cut, the jump leaves no gap
+    assert_eq!(track.frames_written(), 3 * 1152);
+    assert_eq!(report.pid, 0x101);
+    assert!(report.video_start);
+    assert_eq!(report.discontinuities, 1);
+}
+
+#[test]
+fn playback_follows_the_video() {
+    let track = PcmBuffer {
+        sample_rate: 48000,
+        channels: 1,
+        samples: vec![0, 16384, -16384, 8192],
+    };
+    let mut state = PlaybackState::new(track);
+    let mut output = [1.0; 4];
+
+    // Silent while paused
+    state.fill(&mut output, 2, 48000);
+    assert_eq!(output, [0.0; 4]);
+    assert_eq!(state.position(), Duration::ZERO);
+
+    // Mono samples on both channels, then halfway between them at twice the rate
+    state.sync(Duration::ZERO, true);
+    state.fill(&mut output, 2, 48000);
+    assert_eq!(output, [0.0, 0.0, 0.5, 0.5]);
+    state.fill(&mut output, 1, 96000);
+    assert_eq!(output, [-0.5, -0.125, 0.25, 0.25]);
+
+    // Past the end of the track
+    state.fill(&mut output, 1, 48000);
+    assert_eq!(output, [0.0; 4]);
+
+    // Only a distant video moves the audio
+    state.sync(Duration::from_millis(50), true);
+    assert!(state.position() < Duration::from_millis(1));
+    state.sync(Duration::from_secs(1), true);
+    assert_eq!(state.position(), Duration::from_secs(1));
+    state.sync(Duration::from_secs(1), false);
+    assert!(!state.playing());
+}
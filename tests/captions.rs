@@ -0,0 +1,104 @@
+use std::{path::Path, time::Duration};
+
+use mpeg2::{a53_cc_data, meta_decode, to_scc, to_srt, CaptionChannel, CaptionDecoder, FrameRate};
+
+/// 30 pictures at 30000/1001 fps: a CC1 pop-on caption "HELLO" displayed at frame 7 and erased
+/// at frame 20, and a CEA-708 service 1 window "HI" shown at frame 3 and cleared at frame 15
+fn decode_fixture() -> CaptionDecoder {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("captions.log");
+    let pictures = meta_decode(&path).unwrap();
+    assert_eq!(pictures.len(), 30);
+    assert!(pictures.iter().all(|picture| !picture.user_data.is_empty()));
+
+    CaptionDecoder::from_pictures(&pictures)
+}
+
+#[test]
+fn a53_cc_data_parse() {
+    let user_data = [
+        b'G', b'A', b'9', b'4', 0x03, 0x42, 0xff, 0xfc, 0x94, 0x20, 0xf9, 0x80, 0x80, 0xff,
+    ];
+    let cc_data = a53_cc_data(&user_data);
+
+    assert_eq!(cc_data.len(), 2);
+    assert_eq!(cc_data[0].cc_type, 0);
+    assert!(cc_data[0].valid);
+    assert_eq!(cc_data[0].data, [0x94, 0x20]);
+    assert_eq!(cc_data[1].cc_type, 1);
+
+    // Other registered user data, such as AFD, holds no captions
+    assert!(a53_cc_data(b"DTG1\x41\xf8").is_empty());
+}
+
+#[test]
+fn caption_channel_names() {
+    assert_eq!("cc3".parse(), Ok(CaptionChannel::Cc(3)));
+    assert_eq!("SERVICE2".parse(), Ok(CaptionChannel::Service(2)));
+    assert!("CC5".parse::<CaptionChannel>().is_err());
+    assert!("SERVICE0".parse::<CaptionChannel>().is_err());
+    assert_eq!(CaptionChannel::Service(12).to_string(), "SERVICE12");
+}
+
+#[test]
+fn cea608_pop_on_caption() {
+    let decoder = decode_fixture();
+    let captions: Vec<_> = decoder
+        .captions()
+        .iter()
+        .filter(|caption| caption.channel == CaptionChannel::Cc(1))
+        .cloned()
+        .collect();
+
+    assert_eq!(captions.len(), 1);
+    assert_eq!(captions[0].text, "HELLO");
+    assert_eq!(captions[0].start, Duration::from_nanos(233_566_667));
+    assert_eq!(
+        to_srt(&captions),
+        "1\n00:00:00,233 --> 00:00:00,667\nHELLO\n\n"
+    );
+}
+
+#[test]
+fn cea708_service_window() {
+    let decoder = decode_fixture();
+    let captions: Vec<_> = decoder
+        .captions()
+        .iter()
+        .filter(|caption| caption.channel == CaptionChannel::Service(1))
+        .cloned()
+        .collect();
+
+    assert_eq!(captions.len(), 1);
+    assert_eq!(captions[0].text, "HI");
+    assert_eq!(
+        to_srt(&captions),
+        "1\n00:00:00,100 --> 00:00:00,500\nHI\n\n"
+    );
+}
+
+#[test]
+fn scc_lines_and_drop_frame_time_codes() {
+    let decoder = decode_fixture();
+    let scc = to_scc(decoder.field1_pairs(), FrameRate::new(30000, 1001));
+
+    assert_eq!(
+        scc,
+        "Scenarist_SCC V1.0\n\
+         \n00:00:00;00\t9420 9420 94e0 94e0 c845 4c4c 4f80 942f 942f\n\
+         \n00:00:00;20\t942c 942c\n"
+    );
+
+    // Frame 1800 is a minute of drop-frame time code later, frames 00 and 01 are skipped
+    let minute = Duration::from_secs_f64(1800.0 * 1001.0 / 30000.0);
+    let scc = to_scc(&[(minute, [0x94, 0x2c])], FrameRate::new(30000, 1001));
+    assert!(scc.contains("00:01:00;02\t942c"));
+
+    let scc = to_scc(
+        &[(Duration::from_secs(61), [0x94, 0x2c])],
+        FrameRate::new(25, 1),
+    );
+    assert!(scc.contains("00:01:01:00\t942c"));
+}
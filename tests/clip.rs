@@ -0,0 +1,268 @@
+use std::{fs, path::PathBuf};
+
+use mpeg2::{
+    median_cut, meta_decode, read_clip, read_files, write_apng, write_gif, ClipFrame, Deinterlacer,
+    GifWriter, PgmSource, Rgb, RgbImage, CLIP_CLOCK_HZ,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// Images of the GIF frames, read back as palette colors, with their delays
+fn decode_gif(data: &[u8]) -> (usize, usize, Vec<(Vec<Rgb>, u16)>) {
+    assert_eq!(&data[..6], b"GIF89a");
+    let width = usize::from(u16::from_le_bytes([data[6], data[7]]));
+    let height = usize::from(u16::from_le_bytes([data[8], data[9]]));
+    assert_eq!(data[10], 0xf7);
+    let palette: Vec<Rgb> = data[13..13 + 768]
+        .chunks(3)
+        .map(|color| Rgb::new(color[0], color[1], color[2]))
+        .collect();
+
+    let mut frames = Vec::new();
+    let mut delay = 0;
+    let mut i = 13 + 768;
+    loop {
+        match data[i] {
+            0x21 => {
+                if data[i + 1] == 0xf9 {
+                    delay = u16::from_le_bytes([data[i + 4], data[i + 5]]);
+                }
+                i += 2;
+                while data[i] != 0 {
+                    i += 1 + usize::from(data[i]);
+                }
+                i += 1;
+            }
+            0x2c => {
+                assert_eq!(data[i + 9], 0, "no local color table");
+                assert_eq!(data[i + 10], 8);
+                i += 11;
+                let mut lzw = Vec::new();
+                while data[i] != 0 {
+                    let len = usize::from(data[i]);
+                    lzw.extend(&data[i + 1..i + 1 + len]);
+                    i += 1 + len;
+                }
+                i += 1;
+                let pixels = lzw_decode(&lzw)
+                    .into_iter()
+                    .map(|index| palette[usize::from(index)])
+                    .collect();
+                frames.push((pixels, delay));
+            }
+            0x3b => break,
+            block => panic!("unexpected block {:#x}", block),
+        }
+    }
+    assert_eq!(i, data.len() - 1);
+
+    (width, height, frames)
+}
+
+fn lzw_decode(data: &[u8]) -> Vec<u8> {
+    let (clear, end) = (256, 257);
+    let mut table: Vec<Vec<u8>> = Vec::new();
+    let mut size = 9;
+    let mut prev: Option<usize> = None;
+    let mut out = Vec::new();
+
+    let (mut buffer, mut bits, mut bytes) = (0u32, 0, data.iter());
+    loop {
+        while bits < size {
+            buffer |= u32::from(*bytes.next().expect("end code")) << bits;
+            bits += 8;
+        }
+        let code = (buffer & ((1 << size) - 1)) as usize;
+        buffer >>= size;
+        bits -= size;
+
+        if code == clear {
+            table = (0..=255).map(|i| vec![i]).collect();
+            table.extend([vec![], vec![]]);
+            size = 9;
+            prev = None;
+            continue;
+        }
+        if code == end {
+            return out;
+        }
+        let entry = match (table.get(code), prev) {
+            (Some(entry), _) => entry.clone(),
+            (None, Some(prev)) => {
+                let mut entry = table[prev].clone();
+                entry.push(table[prev][0]);
+                entry
+            }
+            (None, None) => panic!("unknown first code {}", code),
+        };
+        if let Some(prev) = prev {
+            if table.len() < 4096 {
+                let mut added = table[prev].clone();
+                added.push(entry[0]);
+                table.push(added);
+                if table.len() == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+        }
+        out.extend(&entry);
+        prev = Some(code);
+    }
+}
+
+#[test]
+fn median_cut_palette() {
+    let samples: Vec<Rgb> = (0..=255).map(|i| Rgb::new(i, 0, 255 - i)).collect();
+    let mut palette = median_cut(&samples, 4);
+    palette.sort_by_key(|color| color.r);
+    // The means of four runs of 64 samples
+    let reds: Vec<_> = palette.iter().map(|color| color.r).collect();
+    assert_eq!(reds, [32, 96, 160, 224]);
+    assert!(palette
+        .iter()
+        .all(|color| (255..=256).contains(&(u16::from(color.r) + u16::from(color.b)))));
+
+    // Never more colors than the samples have
+    let grey = vec![Rgb::new(128, 128, 128); 100];
+    assert_eq!(median_cut(&grey, 256), [Rgb::new(128, 128, 128)]);
+}
+
+#[test]
+fn gif_round_trip() {
+    // Enough colors and noise to fill the LZW table several times
+    let mut image = RgbImage::with_capacity(200, 150);
+    let mut seed = 1u32;
+    for y in 0..150 {
+        for x in 0..200 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let level = (seed >> 24) as u8 & 0xf8;
+            image[y][x] = Rgb::new(level, (x as u8) & 0xf8, (y as u8) & 0xf8);
+        }
+    }
+    let palette: Vec<Rgb> = (0..=255)
+        .map(|i: u16| Rgb::new(i as u8 & 0xe0, (i as u8) << 3 & 0xe0, (i as u8) << 6))
+        .collect();
+
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-round-trip.gif", std::process::id()));
+    let mut writer = GifWriter::create(&path, 200, 150, palette.clone()).unwrap();
+    writer.write_frame(&image, 4).unwrap();
+    writer
+        .write_frame(&RgbImage::with_capacity(200, 150), 7)
+        .unwrap();
+    writer.finish().unwrap();
+    let data = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let (width, height, frames) = decode_gif(&data);
+    assert_eq!((width, height), (200, 150));
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].1, 4);
+    assert_eq!(frames[1].1, 7);
+
+    // Every pixel maps to a palette entry nearby
+    assert_eq!(frames[0].0.len(), 200 * 150);
+    for (decoded, pixel) in frames[0].0.iter().zip(image.get_data()) {
+        assert!(
+            decoded.r.abs_diff(pixel.r) <= 36,
+            "{:?} {:?}",
+            decoded,
+            pixel
+        );
+        assert!(decoded.g.abs_diff(pixel.g) <= 36);
+        assert!(decoded.b.abs_diff(pixel.b) <= 64);
+    }
+    assert!(frames[1].0.iter().all(|pixel| *pixel == palette[0]));
+}
+
+#[test]
+fn field_rate_clip() {
+    // 25 Hz top field first frames
+    let dir = fixture("synthetic_tff");
+    let mut source = PgmSource::open(&dir, None).unwrap();
+
+    let frames = read_clip(&mut source, 1..usize::MAX, Deinterlacer::Bob, false).unwrap();
+    let summary: Vec<_> = frames.iter().map(|f| (f.frame, f.duration)).collect();
+    assert_eq!(summary, [(1, CLIP_CLOCK_HZ / 25), (2, CLIP_CLOCK_HZ / 25)]);
+
+    let fields = read_clip(&mut source, 0..2, Deinterlacer::Bob, true).unwrap();
+    assert_eq!(fields.len(), 4);
+    assert!(fields
+        .iter()
+        .all(|field| field.duration == CLIP_CLOCK_HZ / 50));
+    assert_eq!(
+        fields.iter().map(|field| field.frame).collect::<Vec<_>>(),
+        [0, 0, 1, 1]
+    );
+}
+
+#[test]
+fn repeated_fields() {
+    // Frames of a 3:2 pulldown, the first one repeating its top field
+    let dir = fixture("synthetic_tff");
+    let files = read_files(&dir.to_string_lossy()).unwrap();
+    let meta = meta_decode(&fixture("pulldown.log")).unwrap();
+    let mut source = PgmSource::new(files, Some(meta), None);
+
+    let fields = read_clip(&mut source, 0..1, Deinterlacer::Weave, true).unwrap();
+    assert_eq!(fields.len(), 3);
+    // 30000/1001 Hz frames, fields last a frame period of the 27 MHz clock
+    assert!(fields.iter().all(|field| field.duration == 900_900));
+
+    let frames = read_clip(&mut source, 0..1, Deinterlacer::Weave, false).unwrap();
+    assert_eq!(frames[0].duration, 3 * 900_900);
+}
+
+#[test]
+fn apng_delays() {
+    let dir = fixture("synthetic_tff");
+    let files = read_files(&dir.to_string_lossy()).unwrap();
+    let meta = meta_decode(&fixture("pulldown.log")).unwrap();
+    let mut source = PgmSource::new(files, Some(meta), None);
+    let clip = read_clip(&mut source, 0..3, Deinterlacer::Bob, false).unwrap();
+
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-clip.png", std::process::id()));
+    write_apng(&path, &clip).unwrap();
+    let decoder = png::Decoder::new(fs::File::open(&path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let animation = reader.info().animation_control.unwrap();
+    assert_eq!((animation.num_frames, animation.num_plays), (3, 0));
+
+    let mut delays = Vec::new();
+    let mut data = vec![0; reader.output_buffer_size()];
+    for _ in 0..3 {
+        reader.next_frame(&mut data).unwrap();
+        let control = reader.info().frame_control.unwrap();
+        delays.push((control.delay_num, control.delay_den));
+    }
+    fs::remove_file(&path).unwrap();
+
+    // Three, two then three fields of 1001/60000 s in display order
+    assert_eq!(delays, [(1001, 20000), (1001, 30000), (1001, 20000)]);
+}
+
+#[test]
+fn gif_delays_keep_the_clip_length() {
+    let frame = |duration| ClipFrame {
+        frame: 0,
+        image: RgbImage::with_capacity(4, 2),
+        duration,
+    };
+    // Fields of 29.97 Hz frames, 1.668 hundredths of a second each
+    let clip: Vec<_> = (0..6).map(|_| frame(900_900)).collect();
+
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-clip.gif", std::process::id()));
+    write_gif(&path, &clip).unwrap();
+    let data = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let (_, _, frames) = decode_gif(&data);
+    let delays: Vec<_> = frames.iter().map(|(_, delay)| *delay).collect();
+    // Fields shown for one hundredth are merged into the previous one
+    assert_eq!(delays, [2, 3, 2, 3]);
+    assert!(write_gif(&path, &[]).is_err());
+}
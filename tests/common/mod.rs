@@ -0,0 +1,60 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use mpeg2::{Error, FrameSource, SourceFrame, YuvFrame};
+
+/// Frames in memory without metadata, counting the frames read
+pub struct Frames {
+    pub frames: Vec<YuvFrame>,
+    pub position: usize,
+    /// Index of every frame read, in order
+    pub read: Vec<usize>,
+    /// Frame period of the presentation times, `None` for frames without time stamps
+    pub period: Option<Duration>,
+}
+
+impl Frames {
+    pub fn new(frames: Vec<YuvFrame>) -> Self {
+        Self {
+            frames,
+            position: 0,
+            read: Vec::new(),
+            period: None,
+        }
+    }
+
+    /// Stamps the frames with presentation times `period` apart
+    pub fn with_period(mut self, period: Duration) -> Self {
+        self.period = Some(period);
+        self
+    }
+}
+
+impl FrameSource for Frames {
+    fn frame_count(&self) -> Option<usize> {
+        Some(self.frames.len())
+    }
+
+    fn seek(&mut self, index: usize) -> Result<(), Error> {
+        self.position = index;
+        Ok(())
+    }
+
+    fn next_frame(&mut self) -> Result<Option<SourceFrame>, Error> {
+        let Some(frame) = self.frames.get(self.position) else {
+            return Ok(None);
+        };
+        let pts = self.period.map(|period| period * self.position as u32);
+        self.read.push(self.position);
+        self.position += 1;
+
+        Ok(Some(SourceFrame {
+            frame: frame.clone(),
+            picture: None,
+            path: None,
+            pts,
+        }))
+    }
+}
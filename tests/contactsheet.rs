@@ -0,0 +1,125 @@
+mod common;
+
+use std::{fs, time::Duration};
+
+use common::Frames;
+use mpeg2::{
+    contact_sheet, evenly_spaced, read_thumbnail, ChromaFormat, Deinterlacer, Rgb, RgbImage,
+    Thumbnail, YuvFrame,
+};
+
+/// `count` frames 40 ms apart, each of a different grey
+fn greys(count: usize) -> Frames {
+    let frames = (0..count)
+        .map(|i| {
+            let mut frame = YuvFrame::new(64, 48, ChromaFormat::Yuv420);
+            for y in 0..48 {
+                frame.y.row_mut(y).fill(16 + 20 * i as u8);
+            }
+            frame
+        })
+        .collect();
+    Frames::new(frames).with_period(Duration::from_millis(40))
+}
+
+#[test]
+fn evenly_spaced_frames() {
+    assert_eq!(evenly_spaced(100, 4), [0, 25, 50, 75]);
+    assert_eq!(evenly_spaced(10, 3), [0, 3, 6]);
+    // Never more than the frames
+    assert_eq!(evenly_spaced(3, 10), [0, 1, 2]);
+    assert!(evenly_spaced(0, 10).is_empty());
+}
+
+#[test]
+fn deinterlacer_parse() {
+    assert_eq!("weave".parse(), Ok(Deinterlacer::Weave));
+    assert_eq!("Bob".parse(), Ok(Deinterlacer::Bob));
+    assert_eq!(
+        "adaptive".parse(),
+        Ok(Deinterlacer::Adaptive {
+            threshold: Deinterlacer::DEFAULT_THRESHOLD
+        })
+    );
+    assert_eq!(
+        "adaptive:0.1".parse(),
+        Ok(Deinterlacer::Adaptive { threshold: 0.1 })
+    );
+    assert_eq!(
+        Deinterlacer::Adaptive { threshold: 0.1 }.to_string(),
+        "adaptive:0.1"
+    );
+    assert!("adaptive:2".parse::<Deinterlacer>().is_err());
+    assert!("bob:0.1".parse::<Deinterlacer>().is_err());
+}
+
+#[test]
+fn thumbnails_of_a_source() {
+    let mut source = greys(10);
+
+    let thumbnail = read_thumbnail(&mut source, 5, Deinterlacer::Bob, 32, None)
+        .unwrap()
+        .unwrap();
+    // Square pixels without metadata
+    assert_eq!(
+        (thumbnail.image.width(), thumbnail.image.height()),
+        (32, 24)
+    );
+    assert_eq!(thumbnail.frame, 5);
+    assert_eq!(thumbnail.caption(), "#5 00:00:00.200");
+
+    assert!(read_thumbnail(&mut source, 10, Deinterlacer::Bob, 32, None)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn sheet_layout() {
+    let thumbnail = |frame: usize| {
+        let mut image = RgbImage::with_capacity(40, 30);
+        for y in 0..30 {
+            image[y].fill(Rgb::new(200, 0, 0));
+        }
+        Thumbnail {
+            frame,
+            pts: None,
+            image,
+        }
+    };
+    let thumbnails: Vec<_> = (0..5).map(thumbnail).collect();
+
+    // Two rows of three, with 8 pixel margins and 22 pixel captions
+    let sheet = contact_sheet(&thumbnails, 3);
+    assert_eq!((sheet.width(), sheet.height()), (8 + 3 * 48, 8 + 2 * 60));
+
+    let background = Rgb::new(32, 32, 32);
+    assert_eq!(sheet[0][0], background);
+    assert_eq!(sheet[8][8], Rgb::new(200, 0, 0));
+    // The caption of the first thumbnail, then the empty sixth cell
+    assert!((8 + 30..8 + 52).any(|y| sheet[y][8..48].contains(&Rgb::new(255, 255, 255))));
+    assert_eq!(sheet[68 + 8][8 + 2 * 48], background);
+}
+
+#[test]
+fn save_png_and_ppm() {
+    let mut image = RgbImage::with_capacity(3, 2);
+    image[1][2] = Rgb::new(10, 20, 30);
+
+    let png = std::env::temp_dir().join(format!("mpeg2-{}-sheet.png", std::process::id()));
+    image.save(&png).unwrap();
+    let decoder = png::Decoder::new(fs::File::open(&png).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    fs::remove_file(&png).unwrap();
+
+    assert_eq!((info.width, info.height), (3, 2));
+    assert_eq!(&data[15..18], [10, 20, 30]);
+
+    let ppm = std::env::temp_dir().join(format!("mpeg2-{}-sheet.ppm", std::process::id()));
+    image.save(&ppm).unwrap();
+    let text = fs::read_to_string(&ppm).unwrap();
+    fs::remove_file(&ppm).unwrap();
+
+    assert!(text.starts_with("P3\n3 2\n255\n"));
+}
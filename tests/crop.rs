@@ -0,0 +1,186 @@
+mod common;
+
+use common::Frames;
+use mpeg2::{
+    detect_borders, detect_source_borders, ChromaFormat, Crop, CropRect, CropSource, FrameSource,
+    YuvFrame, DEFAULT_DETECT_FRAMES,
+};
+
+/// 64x48 black frame with a grey picture in `rect`
+fn boxed_frame(rect: CropRect) -> YuvFrame {
+    let mut frame = YuvFrame::new(64, 48, ChromaFormat::Yuv420);
+    for y in rect.y..rect.y + rect.height {
+        for x in rect.x..rect.x + rect.width {
+            frame.y.set(x, y, 128);
+        }
+    }
+    frame
+}
+
+#[test]
+fn crop_parse() {
+    let rect = CropRect {
+        x: 8,
+        y: 16,
+        width: 704,
+        height: 544,
+    };
+    assert_eq!("704x544+8+16".parse(), Ok(rect));
+    assert_eq!(rect.to_string(), "704x544+8+16");
+    assert_eq!("704x544+8+16".parse(), Ok(Crop::Rect(rect)));
+    assert_eq!("auto".parse(), Ok(Crop::Auto(DEFAULT_DETECT_FRAMES)));
+    assert_eq!("auto:100".parse(), Ok(Crop::Auto(100)));
+    assert!("704x544".parse::<Crop>().is_err());
+    assert!("auto:".parse::<Crop>().is_err());
+}
+
+#[test]
+fn crop_keeps_fields_whole() {
+    let rect = CropRect {
+        x: 3,
+        y: 3,
+        width: 701,
+        height: 570,
+    };
+
+    // Chroma lines of 4:2:0 alternate between fields too
+    assert_eq!(
+        rect.aligned(ChromaFormat::Yuv420, 720, 576),
+        CropRect {
+            x: 4,
+            y: 4,
+            width: 700,
+            height: 568
+        }
+    );
+    assert_eq!(
+        rect.aligned(ChromaFormat::Yuv422, 720, 576),
+        CropRect {
+            x: 4,
+            y: 4,
+            width: 700,
+            height: 568
+        }
+    );
+    assert_eq!(
+        rect.aligned(ChromaFormat::Yuv444, 720, 576),
+        CropRect {
+            x: 3,
+            y: 4,
+            width: 701,
+            height: 568
+        }
+    );
+
+    // Clipped to the frame
+    let rect = CropRect {
+        x: 700,
+        y: 0,
+        width: 100,
+        height: 1000,
+    };
+    assert_eq!(
+        rect.aligned(ChromaFormat::Yuv420, 720, 576),
+        CropRect {
+            x: 700,
+            y: 0,
+            width: 20,
+            height: 576
+        }
+    );
+
+    // Even when the right and bottom edges overflow
+    let rect = CropRect {
+        x: 8,
+        y: 8,
+        width: usize::MAX,
+        height: usize::MAX,
+    };
+    assert_eq!(
+        rect.aligned(ChromaFormat::Yuv420, 720, 576),
+        CropRect {
+            x: 8,
+            y: 8,
+            width: 712,
+            height: 568
+        }
+    );
+}
+
+#[test]
+fn cropped_frame_starts_on_a_top_field_line() {
+    let mut frame = YuvFrame::new(16, 16, ChromaFormat::Yuv420);
+    for y in 0..16 {
+        frame.y.row_mut(y).fill(y as u8);
+    }
+
+    let rect = CropRect {
+        x: 0,
+        y: 2,
+        width: 16,
+        height: 8,
+    };
+    // Line 2 would start on the bottom field of the chroma planes
+    let cropped = rect.apply(&frame);
+    assert_eq!((cropped.width(), cropped.height()), (16, 4));
+    assert_eq!(cropped.y.get(0, 0), 4);
+    assert_eq!((cropped.cb.width(), cropped.cb.height()), (8, 2));
+}
+
+#[test]
+fn black_borders() {
+    let picture = CropRect {
+        x: 8,
+        y: 6,
+        width: 48,
+        height: 36,
+    };
+    let mut frame = boxed_frame(picture);
+    // A few bright samples in the borders, such as noise, are still black
+    frame.y.set(1, 1, 200);
+
+    assert_eq!(detect_borders([&frame]), Some(picture));
+
+    // A frame with a wider picture widens the detected area
+    let wide = boxed_frame(CropRect {
+        x: 4,
+        y: 6,
+        width: 56,
+        height: 30,
+    });
+    assert_eq!(
+        detect_borders([&frame, &wide]),
+        Some(CropRect {
+            x: 4,
+            y: 6,
+            width: 56,
+            height: 36
+        })
+    );
+
+    // Black frames have no borders
+    let black = YuvFrame::new(64, 48, ChromaFormat::Yuv420);
+    assert_eq!(detect_borders([&black]), None);
+}
+
+#[test]
+fn borders_of_a_source() {
+    let picture = CropRect {
+        x: 8,
+        y: 4,
+        width: 48,
+        height: 40,
+    };
+    let mut source = Frames::new(vec![boxed_frame(picture); 10]);
+
+    // Frames are sampled over the whole source, which is rewound
+    let rect = detect_source_borders(&mut source, 5).unwrap();
+    assert_eq!(rect, Some(picture));
+    assert_eq!(source.read, [0, 2, 4, 6, 8]);
+    assert_eq!(source.position, 0);
+
+    let mut cropped = CropSource::new(Box::new(source), picture);
+    let frame = cropped.next_frame().unwrap().unwrap().frame;
+    assert_eq!((frame.width(), frame.height()), (48, 40));
+    assert!(frame.y.rows().flatten().all(|sample| *sample == 128));
+}
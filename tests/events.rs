@@ -0,0 +1,142 @@
+mod common;
+
+use std::{path::Path, time::Duration};
+
+use common::Frames;
+use mpeg2::{
+    analyze, detect_events, read_log, ChromaFormat, Event, EventDetector, EventKind, Thresholds,
+    YuvFrame,
+};
+
+const FRAME: Duration = Duration::from_millis(40);
+
+/// 64x48 frame, black, a horizontal ramp, or the ramp reversed
+fn frame(kind: u8) -> YuvFrame {
+    let mut frame = YuvFrame::new(64, 48, ChromaFormat::Yuv420);
+    for y in 0..48 {
+        for x in 0..64 {
+            let ramp = 40 + 3 * x as u8;
+            let luma = match kind {
+                0 => 16,
+                1 => ramp,
+                _ => 255 - ramp,
+            };
+            frame.y.set(x, y, luma);
+        }
+    }
+    frame
+}
+
+fn detect(frames: &[u8], thresholds: Thresholds) -> Vec<Event> {
+    let mut detector = EventDetector::new(thresholds);
+    for (i, kind) in frames.iter().enumerate() {
+        detector.push(&frame(*kind), FRAME * i as u32, FRAME);
+    }
+    detector.finish()
+}
+
+#[test]
+fn black_freeze_and_cuts() {
+    let thresholds = Thresholds {
+        min_freeze: Duration::from_millis(200),
+        ..Thresholds::default()
+    };
+    // Black, then the ramp for 6 frames, then the reversed ramp
+    let events = detect(&[0, 0, 0, 1, 1, 1, 1, 1, 1, 2, 2], thresholds);
+
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| (event.kind, event.frame, event.frames))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (EventKind::Black, 0, 3),
+            (EventKind::Freeze, 3, 6),
+            (EventKind::SceneCut, 3, 1),
+            (EventKind::SceneCut, 9, 1),
+        ]
+    );
+
+    assert_eq!(events[0].duration, FRAME * 3);
+    assert_eq!(events[1].start, FRAME * 3);
+    assert_eq!(events[1].duration, FRAME * 6);
+    assert!(events[3].score >= thresholds.scene);
+    // The last two frames are too short a freeze
+    assert!(!events
+        .iter()
+        .any(|event| event.frame == 9 && event.kind == EventKind::Freeze));
+}
+
+#[test]
+fn black_frames_are_not_frozen() {
+    let events = detect(&[0; 100], Thresholds::default());
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, EventKind::Black);
+    assert_eq!(events[0].frames, 100);
+    assert_eq!(events[0].duration, Duration::from_secs(4));
+}
+
+#[test]
+fn short_runs_are_ignored() {
+    let thresholds = Thresholds {
+        min_black: Duration::from_millis(100),
+        ..Thresholds::default()
+    };
+    let events = detect(&[1, 0, 0, 1, 1], thresholds);
+
+    // Two black frames last 80 ms, the cuts in and out of them remain
+    let kinds: Vec<_> = events
+        .iter()
+        .map(|event| (event.kind, event.frame))
+        .collect();
+    assert_eq!(kinds, [(EventKind::SceneCut, 1), (EventKind::SceneCut, 3)]);
+}
+
+#[test]
+fn events_of_a_source() {
+    let mut source = Frames::new([1, 1, 2, 2, 0].into_iter().map(frame).collect());
+
+    // Frames without time stamps last 40 ms, and the source is rewound
+    let events = detect_events(&mut source, Thresholds::default()).unwrap();
+    assert_eq!(source.position, 0);
+
+    let cut = events[0];
+    assert_eq!((cut.kind, cut.frame), (EventKind::SceneCut, 2));
+    assert_eq!(cut.start, FRAME * 2);
+    let black = events
+        .iter()
+        .find(|event| event.kind == EventKind::Black)
+        .unwrap();
+    assert_eq!((black.frame, black.start), (4, FRAME * 4));
+}
+
+#[test]
+fn report_events() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("pulldown.log");
+    let mut report = analyze(&read_log(&path).unwrap());
+
+    // Only reported when looked for
+    assert!(!report.to_json().contains("\"events\""));
+    assert!(!report.to_string().contains("Events"));
+
+    report.events = Some(vec![Event {
+        kind: EventKind::Freeze,
+        frame: 25,
+        frames: 75,
+        start: Duration::from_secs(1),
+        duration: Duration::from_secs(3),
+        score: 0.002,
+    }]);
+    assert!(report.to_json().contains(
+        "\"events\": [\n    {\"kind\": \"freeze\", \"frame\": 25, \"frames\": 75, \
+         \"start\": 1.000, \"duration\": 3.000, \"score\": 0.0020}\n  ]\n}"
+    ));
+    assert!(report
+        .to_string()
+        .contains("frame     25  00:00:01.000  freeze     75 frames, 3.000 s"));
+}
@@ -0,0 +1,273 @@
+// Golden-frame regression tests
+//
+// Every fixture in tests/fixtures is a folder of frames in the `mpeg2dec -o pgm` layout with its
+// tvid.log. The frames are read, converted to RGB and deinterlaced, and a checksum of every
+// output is compared to tests/golden/<fixture>.txt.
+//
+// The fixtures are synthetic, not decoded from a stream: `synthetic` below draws them and
+// `fixtures_match_their_generator` keeps the files in step with it. A bar moves between the two
+// fields of every frame, so bobbing and the adaptive deinterlacer give different images. Weaving
+// returns the frame itself, which is checked rather than recorded in the goldens.
+//
+// After an intended change of output, regenerate the fixtures and goldens with
+// tools/update-goldens.sh (or MPEG2_UPDATE_GOLDENS=1 cargo test --test golden) and review the
+// diff.
+
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use mpeg2::{
+    guess_chroma_format, read_frame_guessing_chroma, read_netpbm, write_frame, ChromaFormat,
+    Deinterlacer, Error, Field, FrameMode, FrameSource, PgmSource, RgbImage, YuvFrame,
+};
+
+const UPDATE_VAR: &str = "MPEG2_UPDATE_GOLDENS";
+
+const FIXTURES: [&str; 2] = ["synthetic_tff", "synthetic_prog422"];
+
+const DEINTERLACERS: [(&str, Deinterlacer); 2] = [
+    ("bob", Deinterlacer::Bob),
+    ("adaptive", Deinterlacer::Adaptive { threshold: 0.05 }),
+];
+
+/// 64-bit FNV-1a, stable across platforms and Rust versions
+fn checksum<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in chunks.into_iter().flatten() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn frame_checksum(frame: &YuvFrame) -> u64 {
+    checksum(frame.planes().into_iter().flat_map(|plane| plane.rows()))
+}
+
+fn rgb_checksum(frame: &YuvFrame) -> u64 {
+    let mut image = RgbImage::with_capacity(frame.width(), frame.height());
+    frame.to_rgb(&mut image);
+    checksum([image.get_rgb().as_slice()])
+}
+
+/// 32x24 gradients shifting from frame to frame, with a bright bar moving right by 3 pixels
+/// from the top field to the bottom one and by 6 from frame to frame
+fn gradient_frame(index: usize, chroma_format: ChromaFormat) -> YuvFrame {
+    let mut frame = YuvFrame::new(32, 24, chroma_format);
+    for y in 0..24 {
+        for x in 0..32 {
+            frame
+                .y
+                .set(x, y, (16 + (4 * x + 3 * y + 5 * index) % 200) as u8);
+        }
+        if (4..18).contains(&y) {
+            let left = 4 + 6 * index + 3 * (y % 2);
+            for x in left..left + 8 {
+                frame.y.set(x, y, 235);
+            }
+        }
+    }
+    for y in 0..frame.cb.height() {
+        for x in 0..frame.cb.width() {
+            frame.cb.set(x, y, (64 + 8 * x + 4 * index) as u8);
+            frame.cr.set(x, y, (200 - 6 * y - 3 * index) as u8);
+        }
+    }
+    frame
+}
+
+/// Frames and tvid.log of a fixture
+fn synthetic(fixture: &str) -> (Vec<YuvFrame>, &'static str) {
+    let frames = |count, chroma_format| {
+        (0..count)
+            .map(|index| gradient_frame(index, chroma_format))
+            .collect()
+    };
+
+    match fixture {
+        "synthetic_tff" => (
+            frames(3, ChromaFormat::Yuv420),
+            "SEQ 1080000 4:2:0\nPIC 00000000 0 TFF I\nPIC 00000400 2 TFF P\nPIC 00000a00 1 TFF B\n",
+        ),
+        "synthetic_prog422" => (
+            frames(2, ChromaFormat::Yuv422),
+            "SEQ 900900 PROG 4:2:2\nPIC 00000000 0 PROG I\nPIC 00000800 1 PROG P\n",
+        ),
+        _ => panic!("unknown fixture {}", fixture),
+    }
+}
+
+fn fixture_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// One line per checked output: `<frame> <stage> <checksum>`
+fn report(fixture: &str) -> String {
+    let mut source = PgmSource::open(&fixture_dir(fixture), None).unwrap();
+    let mut report = String::new();
+    let mut prev: Option<YuvFrame> = None;
+    let mut index = 0;
+
+    while let Some(frame) = source.next_frame().unwrap() {
+        let picture = frame.picture.expect("fixture without metadata");
+        let frame = frame.frame;
+
+        let mut line = |stage: &str, value: u64| {
+            writeln!(report, "{} {} {:016x}", index, stage, value).unwrap();
+        };
+
+        line("yuv", frame_checksum(&frame));
+        line("rgb", rgb_checksum(&frame));
+
+        let fields = match picture.picture_type {
+            FrameMode::BFF | FrameMode::RFF_BFF => [Field::Bottom, Field::Top],
+            _ => [Field::Top, Field::Bottom],
+        };
+        for field in fields {
+            assert_eq!(
+                Deinterlacer::Weave.apply(&frame, prev.as_ref(), field),
+                frame
+            );
+        }
+        for (name, deinterlacer) in DEINTERLACERS {
+            for field in fields {
+                let out = deinterlacer.apply(&frame, prev.as_ref(), field);
+                let stage = format!("{}-{:?}", name, field).to_lowercase();
+                line(&stage, rgb_checksum(&out));
+            }
+        }
+
+        prev = Some(frame);
+        index += 1;
+    }
+
+    report
+}
+
+fn check(fixture: &str) {
+    let actual = report(fixture);
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.txt", fixture));
+
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        fs::write(&golden, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&golden)
+        .unwrap_or_else(|e| panic!("{}: {}, run with {}=1", golden.display(), e, UPDATE_VAR));
+
+    for (actual, expected) in actual.lines().zip(expected.lines()) {
+        assert_eq!(actual, expected, "{} differs from its golden", fixture);
+    }
+    assert_eq!(
+        actual.lines().count(),
+        expected.lines().count(),
+        "{} has a different number of outputs than its golden",
+        fixture
+    );
+}
+
+#[test]
+fn interlaced_tff_420() {
+    check("synthetic_tff");
+}
+
+#[test]
+fn progressive_422() {
+    check("synthetic_prog422");
+}
+
+#[test]
+fn fixtures_match_their_generator() {
+    for fixture in FIXTURES {
+        let dir = fixture_dir(fixture);
+        let (frames, log) = synthetic(fixture);
+
+        if std::env::var_os(UPDATE_VAR).is_some() {
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("tvid.log"), log).unwrap();
+            for (index, frame) in frames.iter().enumerate() {
+                write_frame(&dir.join(format!("{}.pgm", index)), frame).unwrap();
+            }
+            continue;
+        }
+
+        assert_eq!(fs::read_to_string(dir.join("tvid.log")).unwrap(), log);
+        let mut source = PgmSource::open(&dir, None).unwrap();
+        for (index, expected) in frames.iter().enumerate() {
+            let frame = source.next_frame().unwrap().unwrap().frame;
+            let same = frame
+                .planes()
+                .into_iter()
+                .zip(expected.planes())
+                .all(|(plane, expected)| plane.rows().eq(expected.rows()));
+            assert!(
+                same,
+                "{} frame {} differs from its generator",
+                fixture, index
+            );
+        }
+        assert!(source.next_frame().unwrap().is_none());
+    }
+}
+
+#[test]
+fn empty_folder_has_no_frames() {
+    let dir = std::env::temp_dir().join(format!("mpeg2-{}-empty", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let result = PgmSource::open(&dir, None);
+    fs::remove_dir(&dir).unwrap();
+
+    assert!(matches!(result, Err(Error::NoFrames)));
+}
+
+#[test]
+fn fixtures_round_trip_through_pgm() {
+    // The PGM round trip must be lossless for the goldens to mean anything
+    for fixture in FIXTURES {
+        let mut source = PgmSource::open(&fixture_dir(fixture), None).unwrap();
+        let frame = source.next_frame().unwrap().unwrap();
+        let path = frame.path.unwrap();
+
+        let out = std::env::temp_dir().join(format!(
+            "mpeg2-{}-golden-{}.pgm",
+            std::process::id(),
+            fixture
+        ));
+        mpeg2::write_frame(&out, &frame.frame).unwrap();
+        let written = fs::read(&out).unwrap();
+        fs::remove_file(&out).unwrap();
+
+        assert_eq!(written, fs::read(&path).unwrap(), "{}", path.display());
+    }
+}
+
+#[test]
+fn chroma_format_guessed_from_the_layout() {
+    for chroma_format in [ChromaFormat::Yuv420, ChromaFormat::Yuv444] {
+        let frame = gradient_frame(0, chroma_format);
+        let path = std::env::temp_dir().join(format!(
+            "mpeg2-{}-guess-{:?}.pgm",
+            std::process::id(),
+            chroma_format
+        ));
+        write_frame(&path, &frame).unwrap();
+        let image = read_netpbm(&path).unwrap();
+        let guessed = read_frame_guessing_chroma(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(guess_chroma_format(&image), chroma_format);
+        assert_eq!(guessed.chroma_format, chroma_format);
+        assert!(guessed.y.rows().eq(frame.y.rows()));
+        assert!(guessed.cr.rows().eq(frame.cr.rows()));
+    }
+}
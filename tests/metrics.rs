@@ -0,0 +1,54 @@
+use mpeg2::{ms_ssim, psnr, ssim, Plane};
+
+fn gradient(width: usize, height: usize) -> Plane {
+    let data = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (16 + (3 * x + 2 * y) % 200) as u8))
+        .collect();
+    Plane::from_vec(width, height, width, data)
+}
+
+fn with_offset(plane: &Plane, offset: u8) -> Plane {
+    let data = plane
+        .rows()
+        .flatten()
+        .map(|v| v.saturating_add(offset))
+        .collect();
+    Plane::from_vec(plane.width(), plane.height(), plane.width(), data)
+}
+
+#[test]
+fn identical_planes() {
+    let plane = gradient(64, 48);
+
+    assert!(psnr(&plane, &plane).is_infinite());
+    assert!((ssim(&plane, &plane) - 1.0).abs() < 1e-12);
+    assert!((ms_ssim(&plane, &plane) - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn psnr_of_a_constant_error() {
+    let plane = gradient(64, 48);
+
+    // MSE of 4: 10 * log10(255^2 / 4)
+    let expected = 10.0 * (255.0f64 * 255.0 / 4.0).log10();
+    assert!((psnr(&plane, &with_offset(&plane, 2)) - expected).abs() < 1e-9);
+}
+
+#[test]
+fn ssim_decreases_with_the_error() {
+    let plane = gradient(176, 144);
+    let near = with_offset(&plane, 4);
+    let far = with_offset(&plane, 40);
+
+    assert!(ssim(&plane, &near) > ssim(&plane, &far));
+    assert!(ms_ssim(&plane, &near) > ms_ssim(&plane, &far));
+    assert!(ssim(&plane, &far) < 1.0);
+}
+
+#[test]
+fn ms_ssim_of_planes_smaller_than_the_window() {
+    let plane = gradient(8, 6);
+
+    assert!((ms_ssim(&plane, &plane) - 1.0).abs() < 1e-12);
+    assert!(ms_ssim(&plane, &with_offset(&plane, 10)) < 1.0);
+}
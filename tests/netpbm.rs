@@ -0,0 +1,51 @@
+use std::{fs, path::PathBuf};
+
+use mpeg2::{read_netpbm, Error, NetpbmFormat};
+
+/// Writes `data` to a file of the temporary directory unique to this test
+fn temp_image(name: &str, data: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-{}", std::process::id(), name));
+    fs::write(&path, data).unwrap();
+    path
+}
+
+fn header_reason(name: &str, data: &[u8]) -> &'static str {
+    let path = temp_image(name, data);
+    let result = read_netpbm(&path);
+    fs::remove_file(&path).unwrap();
+    match result {
+        Err(Error::PgmHeader { reason, .. }) => reason,
+        other => panic!("expected a header error, got {:?}", other),
+    }
+}
+
+#[test]
+fn reads_ascii_and_binary_images() {
+    let path = temp_image("ascii.pgm", b"P2\n# comment\n2 2\n15\n0 5\n10 15\n");
+    let image = read_netpbm(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(image.format, NetpbmFormat::P2);
+    assert_eq!(image.samples_u8(), [0, 85, 170, 255]);
+
+    let path = temp_image("binary.ppm", b"P6 1 1 255\n\x01\x02\x03");
+    let image = read_netpbm(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(image.samples, [1, 2, 3]);
+}
+
+#[test]
+fn image_sizes_are_checked_before_allocating() {
+    let huge = format!("P5 {} {} 255\n", usize::MAX / 2, 3);
+    assert_eq!(
+        header_reason("overflow.pgm", huge.as_bytes()),
+        "image size overflows"
+    );
+    assert_eq!(
+        header_reason("short.pgm", b"P2 100000 100000 255\n0 1 2\n"),
+        "fewer bytes left than the header announces samples"
+    );
+    assert_eq!(
+        header_reason("sample.pgm", b"P2 2 1 255\n0 x1\n"),
+        "expected ASCII sample"
+    );
+}
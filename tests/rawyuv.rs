@@ -0,0 +1,27 @@
+use std::fs;
+
+use mpeg2::{Error, FrameSource, PixelFormat, RawYuvSource};
+
+#[test]
+fn frame_sizes() {
+    assert_eq!(PixelFormat::I420.frame_size(720, 576), Some(622_080));
+    assert_eq!(PixelFormat::Yuy2.frame_size(5, 2), Some(24));
+    assert_eq!(PixelFormat::Nv12.frame_size(usize::MAX / 2, 3), None);
+    assert_eq!(PixelFormat::Uyvy.frame_size(usize::MAX, 1), None);
+}
+
+#[test]
+fn sizes_too_large_for_a_file_have_no_frames() {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-huge.yuv", std::process::id()));
+    fs::write(&path, [0; 6]).unwrap();
+    let huge = RawYuvSource::open(&path, usize::MAX / 2, 3, PixelFormat::I420);
+    let mut source = RawYuvSource::open(&path, 2, 2, PixelFormat::I420).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(matches!(huge, Err(Error::NoFrames)));
+
+    // Seeking past the end is no error, there are no frames left
+    source.seek(usize::MAX).unwrap();
+    assert!(source.next_frame().unwrap().is_none());
+    source.seek(0).unwrap();
+    assert!(source.next_frame().unwrap().is_some());
+}
@@ -0,0 +1,84 @@
+use mpeg2::{resize, scale_plane, ChromaFormat, Kernel, Plane, YuvFrame};
+
+fn ramp(width: usize, height: usize) -> Plane {
+    let data = (0..height)
+        .flat_map(|_| (0..width).map(move |x| (x * 255 / (width - 1)) as u8))
+        .collect();
+    Plane::from_vec(width, height, width, data)
+}
+
+#[test]
+fn kernels_interpolate_their_samples() {
+    for kernel in Kernel::ALL {
+        assert_eq!(kernel.weight(0.0), 1.0, "{}", kernel);
+        for x in 1..kernel.radius() as usize {
+            assert!(kernel.weight(x as f32).abs() < 1e-6, "{} at {}", kernel, x);
+        }
+        assert_eq!(kernel.weight(kernel.radius()), 0.0);
+    }
+
+    assert_eq!("Lanczos3".parse(), Ok(Kernel::Lanczos));
+    assert!("nearest".parse::<Kernel>().is_err());
+}
+
+#[test]
+fn flat_planes_stay_flat() {
+    let plane = Plane::new(13, 7, 90);
+    for kernel in Kernel::ALL {
+        for (width, height) in [(40, 21), (5, 3), (13, 14)] {
+            let scaled = scale_plane(&plane, width, height, kernel);
+            assert_eq!((scaled.width(), scaled.height()), (width, height));
+            assert!(
+                scaled.rows().flatten().all(|sample| *sample == 90),
+                "{}",
+                kernel
+            );
+        }
+    }
+}
+
+#[test]
+fn same_size_is_a_copy() {
+    let plane = ramp(16, 4);
+    assert_eq!(scale_plane(&plane, 16, 4, Kernel::Lanczos), plane);
+}
+
+#[test]
+fn bilinear_upscale_of_a_ramp() {
+    let scaled = scale_plane(&ramp(16, 2), 64, 2, Kernel::Bilinear);
+
+    let row = scaled.row(0);
+    assert_eq!(row[0], 0);
+    assert_eq!(row[63], 255);
+    assert!(row.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+#[test]
+fn downscale_filters_out_fine_detail() {
+    // Alternate black and white columns average to grey instead of aliasing
+    let data = (0..8 * 32)
+        .map(|i| if i % 2 == 0 { 0 } else { 255 })
+        .collect();
+    let stripes = Plane::from_vec(32, 8, 32, data);
+
+    for kernel in Kernel::ALL {
+        let scaled = scale_plane(&stripes, 8, 8, kernel);
+        for sample in scaled.row(4)[1..7].iter() {
+            assert!((120..=135).contains(sample), "{} gave {}", kernel, sample);
+        }
+    }
+}
+
+#[test]
+fn resize_keeps_chroma_subsampling() {
+    let frame = YuvFrame::new(720, 576, ChromaFormat::Yuv420);
+    let scaled = resize(&frame, 1024, 576, Kernel::Bicubic);
+
+    assert_eq!((scaled.width(), scaled.height()), (1024, 576));
+    assert_eq!((scaled.cb.width(), scaled.cb.height()), (512, 288));
+    assert_eq!(scaled.chroma_format, ChromaFormat::Yuv420);
+
+    let frame = YuvFrame::new(720, 480, ChromaFormat::Yuv422);
+    let scaled = resize(&frame, 161, 120, Kernel::Lanczos);
+    assert_eq!((scaled.cr.width(), scaled.cr.height()), (81, 120));
+}
@@ -0,0 +1,139 @@
+use std::{fs, time::Duration};
+
+use mpeg2::{duration_from_27mhz, duration_from_90khz, meta_decode, Error, FrameRate};
+
+/// (frame_period in 27 MHz ticks, frame rate, frame duration in ns)
+const RATES: [(u64, (u64, u64), u64); 4] = [
+    (1_080_000, (25, 1), 40_000_000),
+    (900_900, (30000, 1001), 33_366_667),
+    (1_126_125, (24000, 1001), 41_708_333),
+    (450_450, (60000, 1001), 16_683_333),
+];
+
+#[test]
+fn frame_rates_are_exact_fractions() {
+    for (frame_period, (num, den), _) in RATES {
+        let rate = FrameRate::from_frame_period(frame_period);
+        assert_eq!((rate.num(), rate.den()), (num, den), "{}", frame_period);
+    }
+
+    assert_eq!(
+        FrameRate::from_frame_period(900_900).to_string(),
+        "30000/1001"
+    );
+    assert_eq!(FrameRate::from_frame_period(1_080_000).to_string(), "25");
+}
+
+#[test]
+fn frame_durations() {
+    for (frame_period, _, nanos) in RATES {
+        let expected = Duration::from_nanos(nanos);
+        let rate = FrameRate::from_frame_period(frame_period);
+
+        assert_eq!(rate.frame_duration(), expected, "{}", frame_period);
+        assert_eq!(
+            duration_from_27mhz(frame_period),
+            expected,
+            "{}",
+            frame_period
+        );
+    }
+
+    // 3003 ticks of the 90 kHz clock are one 29.97 fps frame
+    assert_eq!(duration_from_90khz(3003), Duration::from_nanos(33_366_667));
+}
+
+#[test]
+fn long_durations_do_not_drift() {
+    // 30000 frames at 30000/1001 fps last exactly 1001 seconds
+    let rate = FrameRate::new(30000, 1001);
+    assert_eq!(rate.frames_duration(30000), Duration::from_secs(1001));
+
+    let rate = FrameRate::new(24000, 1001);
+    assert_eq!(
+        rate.frames_duration(24000 * 60),
+        Duration::from_secs(1001 * 60)
+    );
+}
+
+#[test]
+fn log_durations() {
+    for (frame_period, _, nanos) in RATES {
+        let path = std::env::temp_dir().join(format!(
+            "mpeg2-{}-timing-{}.log",
+            std::process::id(),
+            frame_period
+        ));
+        fs::write(&path, format!("SEQ {}\nPIC 00000000 0 TFF\n", frame_period)).unwrap();
+        let pictures = meta_decode(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(pictures[0].duration, Duration::from_nanos(nanos));
+        assert_eq!(
+            pictures[0].frame_rate(),
+            FrameRate::from_frame_period(frame_period)
+        );
+    }
+}
+
+#[test]
+fn zero_frame_period() {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-timing-zero.log", std::process::id()));
+    fs::write(&path, "SEQ 0\nPIC 00000000 0 TFF\n").unwrap();
+    let result = meta_decode(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        result,
+        Err(Error::LogLine {
+            line: 1,
+            reason: "frame_period must be non-zero",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn presentation_times_count_repeated_fields() {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-timing-pts.log", std::process::id()));
+    fs::write(
+        &path,
+        "SEQ 900900\n\
+         PIC 00000000 0 TFF RFF\n\
+         PIC 00001000 1\n\
+         PIC 00002000 2 RFF\n\
+         PIC 00003000 3 TFF\n\
+         SEQ 1080000\n\
+         PIC 00004000 0 TFF\n",
+    )
+    .unwrap();
+    let pictures = meta_decode(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let pts: Vec<_> = pictures.iter().map(|p| p.pts.as_nanos()).collect();
+    // 3, 2, 3 and 2 fields of 30000/1001 fps frames, then a 25 fps sequence
+    assert_eq!(pts, [0, 50_050_000, 83_416_667, 133_466_667, 166_833_333]);
+}
+
+#[test]
+fn long_frame_period() {
+    let path = std::env::temp_dir().join(format!("mpeg2-{}-timing-long.log", std::process::id()));
+    fs::write(&path, "SEQ 18446744073709551615\nPIC 00000000 0 TFF\n").unwrap();
+    let result = meta_decode(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        result,
+        Err(Error::LogLine {
+            line: 1,
+            reason: "frame_period must be at most one second",
+            ..
+        })
+    ));
+
+    // Durations of any number of ticks do not overflow
+    assert_eq!(
+        duration_from_27mhz(u64::MAX),
+        Duration::new(683_212_743_470, 724_133_889)
+    );
+}
@@ -215,7 +215,7 @@ void dump_state (FILE * f, mpeg2_state_t state, const mpeg2_info_t * info,
 	// TVID ADDED
 	if (simple_tvid_log)
 	{
-		// PIC <offset> <temp_ref> [PROG] [RFF] [TFF]
+		// PIC <offset> <temp_ref> [PROG] [RFF] [TFF] [I|P|B|D]
 		if (state == STATE_PICTURE || state == STATE_PICTURE_2ND)
 		{
 			const mpeg2_picture_t * pic = ((state == STATE_PICTURE) ?
@@ -231,19 +231,64 @@ void dump_state (FILE * f, mpeg2_state_t state, const mpeg2_info_t * info,
 				fprintf(f, " RFF");	
 			if (pic->flags & PIC_FLAG_TOP_FIELD_FIRST)
 				fprintf (f, " TFF");
+			if ((pic->flags & PIC_MASK_CODING_TYPE) >= PIC_FLAG_CODING_TYPE_I &&
+			    (pic->flags & PIC_MASK_CODING_TYPE) <= PIC_FLAG_CODING_TYPE_D)
+				fprintf (f, " %c", "IPBD"[(pic->flags & PIC_MASK_CODING_TYPE) - 1]);
 
 			fprintf(f, "\n");
+
+			// USR <hex bytes>, the picture user data (e.g. A/53 captions)
+			if (info->user_data_len)
+			{
+				unsigned int i;
+
+				fprintf(f, "USR ");
+				for (i = 0; i < info->user_data_len; i++)
+					fprintf(f, "%02x", info->user_data[i]);
+				fprintf(f, "\n");
+			}
 		}
 
-		// SEQ <frame_period> [PROG]
+		// GOP <offset> [CLOSED] [BROKEN]
+		if (state == STATE_GOP)
+		{
+			const mpeg2_gop_t * gop = info->gop;
+
+			fprintf(f, "GOP ");
+			fprintf(f, "%08x", offset);
+
+			if (gop->flags & GOP_FLAG_CLOSED_GOP)
+				fprintf(f, " CLOSED");
+			if (gop->flags & GOP_FLAG_BROKEN_LINK)
+				fprintf(f, " BROKEN");
+
+			fprintf(f, "\n");
+		}
+
+		// SEQ <frame_period> [PROG] [4:2:0|4:2:2|4:4:4] [SIZE <w>x<h>] [DISP <w>x<h>] [SAR <w>:<h>]
 		if (state == STATE_SEQUENCE || state == STATE_SEQUENCE_MODIFIED || state == STATE_SEQUENCE_REPEATED)
 		{
+			const mpeg2_sequence_t * seq = info->sequence;
+
 			fprintf(f, "SEQ ");
-			fprintf(f, "%d", info->sequence->frame_period);
+			fprintf(f, "%d", seq->frame_period);
 
-			if (info->sequence->flags & SEQ_FLAG_PROGRESSIVE_SEQUENCE)
+			if (seq->flags & SEQ_FLAG_PROGRESSIVE_SEQUENCE)
 				fprintf(f, " PROG");
 
+			if (seq->chroma_width == seq->width)
+				fprintf(f, " 4:4:4");
+			else if (seq->chroma_height == seq->height)
+				fprintf(f, " 4:2:2");
+			else
+				fprintf(f, " 4:2:0");
+
+			fprintf(f, " SIZE %ux%u", seq->picture_width, seq->picture_height);
+			fprintf(f, " DISP %ux%u", seq->display_width, seq->display_height);
+			// Sample aspect ratio, 0:0 for an illegal aspect_ratio_information
+			if (seq->pixel_width && seq->pixel_height)
+				fprintf(f, " SAR %u:%u", seq->pixel_width, seq->pixel_height);
+
 			fprintf(f, "\n");
 		}
 
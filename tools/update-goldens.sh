@@ -0,0 +1,7 @@
+#!/bin/sh
+# Regenerates the synthetic tests/fixtures and tests/golden/*.txt from the current output of
+# the crate. Review the diff before committing: every changed line is a changed frame.
+
+cd "$(dirname "$0")/.." || exit 1
+
+MPEG2_UPDATE_GOLDENS=1 cargo test --test golden "$@" && git diff --stat -- tests/fixtures tests/golden